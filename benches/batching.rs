@@ -0,0 +1,69 @@
+//! Scene graph frame times drawn shape by shape versus batched into one draw call.
+//!
+//! Run with `cargo bench --bench batching`. Each case draws a grid of small squares into an
+//! offscreen 1280x720 target, first unchanged between frames and then with every shape
+//! moved each frame, waiting for the GPU after every frame.
+
+use std::time::{Duration, Instant};
+
+use wgpu_sandbox::adapter::AdapterOptions;
+use wgpu_sandbox::fill::Fill;
+use wgpu_sandbox::graph::{NodeId, Transform};
+use wgpu_sandbox::shape::{Shape, ShapeConfig};
+use wgpu_sandbox::state::State;
+
+const FRAMES: u32 = 5;
+
+fn main() {
+    let square = ShapeConfig { shape: Shape::Square, size: 0.004, ..Default::default() }.vertices(720);
+    for side in [30, 100, 200] {
+        for batch in [false, true] {
+            let config = ShapeConfig {
+                fill: Fill::None,
+                adapter: AdapterOptions { force_fallback_adapter: true, ..Default::default() },
+                batch,
+                ..Default::default()
+            };
+            let mut state = match pollster::block_on(State::headless(config, 1280, 720)) {
+                Ok(state) => state,
+                Err(e) => {
+                    eprintln!("{e}");
+                    return;
+                }
+            };
+            let at = |i: u32, t: f32| {
+                let [x, y] = [i % side, i / side].map(|c| -0.9 + 1.8 * c as f32 / (side - 1) as f32);
+                Transform { translation: [x * 1.7 + 0.01 * t, y], ..Default::default() }
+            };
+            let nodes: Vec<NodeId> =
+                (0..side * side).map(|i| state.add_shape(None, &square, [0.2, 0.4, 0.8, 1.0], at(i, 0.0)).unwrap()).collect();
+
+            // Warm-up frame: pipeline compilation and first upload
+            state.render();
+            state.wait_idle();
+
+            let fixed = time_frames(&mut state, |_| {});
+            let moving = time_frames(&mut state, |state| {
+                let t = state.graph().transform(nodes[0]).unwrap().translation[0] + 1.0;
+                for (i, &id) in nodes.iter().enumerate() {
+                    state.set_transform(id, at(i as u32, t)).unwrap();
+                }
+            });
+            let path = if batch { "batched" } else { "per-shape" };
+            println!("{:>6} shapes, {path:>9}: {fixed:8.2} ms/frame static, {moving:8.2} ms/frame moving", nodes.len());
+        }
+    }
+}
+
+/// Mean milliseconds per frame, calling `update` before each.
+fn time_frames(state: &mut State, mut update: impl FnMut(&mut State)) -> f64 {
+    let mut total = Duration::ZERO;
+    for _ in 0..FRAMES {
+        let start = Instant::now();
+        update(state);
+        state.render();
+        state.wait_idle();
+        total += start.elapsed();
+    }
+    total.as_secs_f64() * 1000.0 / FRAMES as f64
+}
@@ -0,0 +1,48 @@
+//! Frame times of the instanced shape path on a software adapter.
+//!
+//! Run with `cargo bench --bench instancing`. Each case draws a grid of squares into an
+//! offscreen 1280x720 target and waits for the GPU after every frame.
+
+use std::time::{Duration, Instant};
+
+use wgpu_sandbox::adapter::AdapterOptions;
+use wgpu_sandbox::instance;
+use wgpu_sandbox::shape::{Shape, ShapeConfig};
+use wgpu_sandbox::state::State;
+
+const FRAMES: u32 = 10;
+
+fn main() {
+    for (columns, rows) in [(40, 25), (400, 250), (1250, 800)] {
+        let instances = instance::grid(columns, rows);
+        let count = instances.len();
+        let config = ShapeConfig {
+            shape: Shape::Square,
+            size: 0.002,
+            adapter: AdapterOptions { force_fallback_adapter: true, ..Default::default() },
+            instances: Some(instances),
+            ..Default::default()
+        };
+        let mut state = match pollster::block_on(State::headless(config, 1280, 720)) {
+            Ok(state) => state,
+            Err(e) => {
+                eprintln!("{e}");
+                return;
+            }
+        };
+
+        // Warm-up frame: pipeline compilation and first upload
+        state.render();
+        state.wait_idle();
+
+        let mut total = Duration::ZERO;
+        for _ in 0..FRAMES {
+            let start = Instant::now();
+            state.render();
+            state.wait_idle();
+            total += start.elapsed();
+        }
+        let ms = total.as_secs_f64() * 1000.0 / FRAMES as f64;
+        println!("{count:>8} instances: {ms:8.2} ms/frame");
+    }
+}
@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use clap::ValueEnum;
+use wgpu::{Adapter, Backends, Instance, InstanceDescriptor, Surface, TextureFormat};
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, EventLoop},
+    window::{Window, WindowId},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    Vulkan,
+    Metal,
+    Dx12,
+    Gl,
+}
+
+impl From<Backend> for Backends {
+    fn from(b: Backend) -> Backends {
+        match b {
+            Backend::Vulkan => Backends::VULKAN,
+            Backend::Metal => Backends::METAL,
+            Backend::Dx12 => Backends::DX12,
+            Backend::Gl => Backends::GL,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum PowerPreference {
+    /// Let the platform choose
+    #[default]
+    None,
+    /// Prefer an integrated GPU
+    LowPower,
+    /// Prefer a discrete GPU
+    HighPerformance,
+}
+
+impl From<PowerPreference> for wgpu::PowerPreference {
+    fn from(p: PowerPreference) -> wgpu::PowerPreference {
+        match p {
+            PowerPreference::None => wgpu::PowerPreference::None,
+            PowerPreference::LowPower => wgpu::PowerPreference::LowPower,
+            PowerPreference::HighPerformance => wgpu::PowerPreference::HighPerformance,
+        }
+    }
+}
+
+/// How the instance and adapter are chosen.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AdapterOptions {
+    /// Only this backend; every backend wgpu was built with when `None`
+    pub backend: Option<Backend>,
+    pub power_preference: PowerPreference,
+    /// Ask for a software adapter (e.g. llvmpipe or WARP)
+    pub force_fallback_adapter: bool,
+}
+
+impl AdapterOptions {
+    pub fn backends(&self) -> Backends {
+        self.backend.map_or(Backends::all(), Backends::from)
+    }
+
+    pub fn instance(&self) -> Instance {
+        Instance::new(&InstanceDescriptor { backends: self.backends(), ..Default::default() })
+    }
+
+    /// The adapter matching these options, able to present to `surface` if given.
+    pub async fn request(&self, instance: &Instance, surface: Option<&Surface<'_>>) -> Result<Adapter, String> {
+        instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: self.power_preference.into(),
+                force_fallback_adapter: self.force_fallback_adapter,
+                compatible_surface: surface,
+            })
+            .await
+            .map_err(|e| {
+                format!(
+                    "No suitable GPU adapter found: {e}\n\
+                     Run `wgpu-sandbox adapters` to see what is available, or try --backend or --force-fallback-adapter"
+                )
+            })
+    }
+}
+
+/// Prints every adapter allowed by `options` with its features, limits and, when a window can be
+/// opened, the surface formats it supports.
+pub fn list(options: AdapterOptions) {
+    let instance = options.instance();
+    match EventLoop::new() {
+        Ok(event_loop) => {
+            let mut lister = Lister { instance, backends: options.backends(), done: false };
+            if let Err(e) = event_loop.run_app(&mut lister) {
+                eprintln!("{e}");
+            }
+        }
+        Err(e) => {
+            eprintln!("Cannot open a window ({e}); surface formats are not listed");
+            print_adapters(&instance, options.backends(), None);
+        }
+    }
+}
+
+/// Opens a hidden window just long enough to create a surface to query.
+struct Lister {
+    instance: Instance,
+    backends: Backends,
+    done: bool,
+}
+
+impl ApplicationHandler for Lister {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.done {
+            return;
+        }
+        self.done = true;
+        let surface = event_loop
+            .create_window(Window::default_attributes().with_visible(false))
+            .map_err(|e| e.to_string())
+            .and_then(|window| self.instance.create_surface(Arc::new(window)).map_err(|e| e.to_string()))
+            .inspect_err(|e| eprintln!("Cannot create a surface ({e}); surface formats are not listed"))
+            .ok();
+        print_adapters(&self.instance, self.backends, surface.as_ref());
+        event_loop.exit();
+    }
+
+    fn window_event(&mut self, _: &ActiveEventLoop, _: WindowId, _: WindowEvent) {}
+}
+
+fn print_adapters(instance: &Instance, backends: Backends, surface: Option<&Surface>) {
+    let adapters = instance.enumerate_adapters(backends);
+    if adapters.is_empty() {
+        println!("No adapters found");
+    }
+    for (i, adapter) in adapters.iter().enumerate() {
+        let info = adapter.get_info();
+        println!("[{i}] {} ({:?}, {:?})", info.name, info.backend, info.device_type);
+        println!("    vendor 0x{:04x}, device 0x{:04x}", info.vendor, info.device);
+        if !info.driver.is_empty() {
+            println!("    driver: {} {}", info.driver, info.driver_info);
+        }
+
+        if let Some(surface) = surface {
+            let formats: Vec<TextureFormat> = surface.get_capabilities(adapter).formats;
+            if formats.is_empty() {
+                println!("    surface formats: none (cannot present to this window)");
+            } else {
+                let names: Vec<String> = formats.iter().map(|f| format!("{f:?}")).collect();
+                println!("    surface formats: {}", names.join(", "));
+            }
+        }
+
+        println!("    features:");
+        for (name, _) in adapter.features().iter_names() {
+            println!("        {name}");
+        }
+
+        // Derived Debug output, one `name: value` per line
+        println!("    limits:");
+        let limits = format!("{:#?}", adapter.limits());
+        for line in limits.lines().filter(|l| l.starts_with("    ")) {
+            println!("    {}", line.trim_end_matches(','));
+        }
+    }
+}
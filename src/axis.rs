@@ -1,22 +1,59 @@
 use std::f32::consts::PI;
 
-const TICK_SPACING: f32 = 0.1;
+use crate::color;
+use crate::colormap::Colormap;
+use crate::scale::{Scale, Scales};
+use crate::shape::RenderMode;
+use crate::stroke::stroke_path;
+use crate::tessellate::Contour;
+
+/// Distance between ticks and grid lines of the default linear axes, in NDC.
+pub const TICK_SPACING: f32 = 0.1;
 const AXIS_HALF_T: f32 = 0.005; // half-thickness of main axis lines in NDC Y units
 const GRID_HALF_T: f32 = 0.003; // thick enough to survive Retina/HiDPI scaling
 const DOT_RADIUS: f32 = 0.007; // in NDC Y units
 const DOT_SEGMENTS: u32 = 12;
-// Grid lines always fill the full NDC range regardless of arm_len
-const GRID_FULL_SPAN: f32 = 1.0;
+const PEN_RADIUS: f32 = 0.015; // in NDC Y units
+// SDF dot quads extend past the radius so the anti-aliased edge is not clipped
+const DOT_QUAD_SCALE: f32 = 1.5;
+/// Grid lines always fill the full NDC range regardless of arm_len.
+pub const GRID_FULL_SPAN: f32 = 1.0;
 
 const DARK_BLUE: [f32; 4] = [0.05, 0.15, 0.7, 1.0];
 const GREY: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+// Minimum WCAG contrast against the background; the grid is meant to stay subtle
+const AXIS_MIN_CONTRAST: f32 = 3.0;
+const GRID_MIN_CONTRAST: f32 = 1.5;
+
+/// Axis and grid colors, adjusted so they stay visible on the background.
+#[derive(Clone, Copy)]
+pub struct AxisColors {
+    pub axis: [f32; 4],
+    pub grid: [f32; 4],
+}
 
-fn vert(x: f32, y: f32, c: [f32; 4]) -> [f32; 6] {
-    [x, y, c[0], c[1], c[2], c[3]]
+impl AxisColors {
+    /// `bg_luminance` is the relative luminance of the background (0 = black, 1 = white).
+    pub fn for_background(bg_luminance: f32) -> AxisColors {
+        AxisColors {
+            axis: color::ensure_contrast(DARK_BLUE, bg_luminance, AXIS_MIN_CONTRAST),
+            grid: color::ensure_contrast(GREY, bg_luminance, GRID_MIN_CONTRAST),
+        }
+    }
+}
+
+// Vertex layout: [x, y, r, g, b, a, u, v]. (u, v) is the position inside an SDF dot in
+// units of its radius; everything else uses (0, 0), which the shader treats as fully covered.
+fn vert(x: f32, y: f32, c: [f32; 4]) -> [f32; 8] {
+    vert_uv(x, y, c, 0.0, 0.0)
+}
+
+fn vert_uv(x: f32, y: f32, c: [f32; 4], u: f32, v: f32) -> [f32; 8] {
+    [x, y, c[0], c[1], c[2], c[3], u, v]
 }
 
 // Horizontal quad from (x0, y-ty) to (x1, y+ty)
-fn h_quad(v: &mut Vec<[f32; 6]>, x0: f32, x1: f32, y: f32, ty: f32, c: [f32; 4]) {
+fn h_quad(v: &mut Vec<[f32; 8]>, x0: f32, x1: f32, y: f32, ty: f32, c: [f32; 4]) {
     v.push(vert(x0, y - ty, c));
     v.push(vert(x1, y - ty, c));
     v.push(vert(x1, y + ty, c));
@@ -27,7 +64,7 @@ fn h_quad(v: &mut Vec<[f32; 6]>, x0: f32, x1: f32, y: f32, ty: f32, c: [f32; 4])
 
 // Vertical quad from (x-tx, y0) to (x+tx, y1)
 // tx is already aspect-corrected by the caller
-fn v_quad(v: &mut Vec<[f32; 6]>, x: f32, y0: f32, y1: f32, tx: f32, c: [f32; 4]) {
+fn v_quad(v: &mut Vec<[f32; 8]>, x: f32, y0: f32, y1: f32, tx: f32, c: [f32; 4]) {
     v.push(vert(x - tx, y0, c));
     v.push(vert(x + tx, y0, c));
     v.push(vert(x + tx, y1, c));
@@ -37,7 +74,7 @@ fn v_quad(v: &mut Vec<[f32; 6]>, x: f32, y0: f32, y1: f32, tx: f32, c: [f32; 4])
 }
 
 // Filled circle (dot) at (cx, cy). rx = aspect-corrected x radius, ry = y radius.
-fn dot(v: &mut Vec<[f32; 6]>, cx: f32, cy: f32, rx: f32, ry: f32, c: [f32; 4]) {
+fn dot(v: &mut Vec<[f32; 8]>, cx: f32, cy: f32, rx: f32, ry: f32, c: [f32; 4]) {
     for i in 0..DOT_SEGMENTS {
         let a1 = 2.0 * PI * i as f32 / DOT_SEGMENTS as f32;
         let a2 = 2.0 * PI * (i + 1) as f32 / DOT_SEGMENTS as f32;
@@ -47,11 +84,38 @@ fn dot(v: &mut Vec<[f32; 6]>, cx: f32, cy: f32, rx: f32, ry: f32, c: [f32; 4]) {
     }
 }
 
+// Single quad dot at (cx, cy); the fragment shader cuts out the circle.
+fn sdf_dot(v: &mut Vec<[f32; 8]>, cx: f32, cy: f32, rx: f32, ry: f32, c: [f32; 4]) {
+    let k = DOT_QUAD_SCALE;
+    let (x0, x1, y0, y1) = (cx - rx * k, cx + rx * k, cy - ry * k, cy + ry * k);
+    v.push(vert_uv(x0, y0, c, -k, -k));
+    v.push(vert_uv(x1, y0, c,  k, -k));
+    v.push(vert_uv(x1, y1, c,  k,  k));
+    v.push(vert_uv(x0, y0, c, -k, -k));
+    v.push(vert_uv(x1, y1, c,  k,  k));
+    v.push(vert_uv(x0, y1, c, -k,  k));
+}
+
 /// Generates TriangleList vertices for axes (thick quads) and tick dots.
-/// `aspect` = window width / height, used to keep lines and dots visually square.
-pub fn generate_vertices(arm_len: f32, grid: bool, aspect: f32) -> Vec<[f32; 6]> {
-    let mut v: Vec<[f32; 6]> = Vec::new();
-    let steps = (arm_len / TICK_SPACING).round() as u32;
+/// Ticks and grid lines sit where `scales` put their ticks, and the axes cross where each
+/// scale has its origin. `aspect` = window width / height, used to keep lines and dots
+/// visually square. `dot_mode` picks between triangle-fan dots and single-quad SDF dots.
+pub fn generate_vertices(
+    arm_len: f32,
+    grid: bool,
+    scales: &Scales,
+    aspect: f32,
+    dot_mode: RenderMode,
+    colors: AxisColors,
+) -> Vec<[f32; 8]> {
+    let mut v: Vec<[f32; 8]> = Vec::new();
+    let [ox, oy] = [scales.x.origin_ndc(), scales.y.origin_ndc()];
+    // Tick positions in NDC, except where the other axis already crosses
+    let ticks = |scale: &Scale, origin: f32| -> Vec<(f32, bool)> {
+        let ticks = scale.ticks().into_iter().map(|tick| (scale.to_ndc(tick.value), tick.major));
+        ticks.filter(|&(n, _)| (n - origin).abs() > 1e-4).collect()
+    };
+    let (x_ticks, y_ticks) = (ticks(&scales.x, ox), ticks(&scales.y, oy));
 
     // X thickness corrected for aspect so vertical elements look the same width as horizontal
     let axis_tx = AXIS_HALF_T / aspect;
@@ -59,31 +123,144 @@ pub fn generate_vertices(arm_len: f32, grid: bool, aspect: f32) -> Vec<[f32; 6]>
     let grid_tx = GRID_HALF_T / aspect;
 
     // Grid quads first (behind everything).
-    // Lines span the full screen (±GRID_FULL_SPAN) and cover all positions up to ±GRID_FULL_SPAN
-    // so the grid always fills the viewport regardless of arm_len.
+    // Lines span the full screen (±GRID_FULL_SPAN) at every tick, so the grid always fills the
+    // viewport regardless of arm_len. Minor lines are fainter
     if grid {
-        let grid_steps = (GRID_FULL_SPAN / TICK_SPACING).round() as u32;
-        for i in 1..=grid_steps {
-            let t = i as f32 * TICK_SPACING;
-            h_quad(&mut v, -GRID_FULL_SPAN, GRID_FULL_SPAN,  t, GRID_HALF_T, GREY);
-            h_quad(&mut v, -GRID_FULL_SPAN, GRID_FULL_SPAN, -t, GRID_HALF_T, GREY);
-            v_quad(&mut v,  t, -GRID_FULL_SPAN, GRID_FULL_SPAN, grid_tx, GREY);
-            v_quad(&mut v, -t, -GRID_FULL_SPAN, GRID_FULL_SPAN, grid_tx, GREY);
+        let grid_color = |major: bool| if major { colors.grid } else { faded(colors.grid) };
+        for &(y, major) in &y_ticks {
+            h_quad(&mut v, -GRID_FULL_SPAN, GRID_FULL_SPAN, y, GRID_HALF_T, grid_color(major));
+        }
+        for &(x, major) in &x_ticks {
+            v_quad(&mut v, x, -GRID_FULL_SPAN, GRID_FULL_SPAN, grid_tx, grid_color(major));
         }
     }
 
     // Main axes (thick, dark blue) — span full screen in grid mode, arm_len in axis mode
     let axes_span = if grid { GRID_FULL_SPAN } else { arm_len };
-    h_quad(&mut v, -axes_span, axes_span, 0.0, AXIS_HALF_T, DARK_BLUE);
-    v_quad(&mut v, 0.0, -axes_span, axes_span, axis_tx, DARK_BLUE);
-
-    // Tick dots at each interval position
-    for i in 1..=steps {
-        let t = i as f32 * TICK_SPACING;
-        dot(&mut v,  t, 0.0, dot_rx, DOT_RADIUS, DARK_BLUE);
-        dot(&mut v, -t, 0.0, dot_rx, DOT_RADIUS, DARK_BLUE);
-        dot(&mut v, 0.0,  t, dot_rx, DOT_RADIUS, DARK_BLUE);
-        dot(&mut v, 0.0, -t, dot_rx, DOT_RADIUS, DARK_BLUE);
+    h_quad(&mut v, -axes_span, axes_span, oy, AXIS_HALF_T, colors.axis);
+    v_quad(&mut v, ox, -axes_span, axes_span, axis_tx, colors.axis);
+
+    // Tick dots along the axes, half size for minor ticks
+    let draw_dot = match dot_mode {
+        RenderMode::Mesh => dot,
+        RenderMode::Sdf => sdf_dot,
+    };
+    let radius = |major: bool| if major { 1.0 } else { 0.5 };
+    for &(x, major) in x_ticks.iter().filter(|&&(x, _)| x.abs() <= axes_span + 1e-4) {
+        draw_dot(&mut v, x, oy, dot_rx * radius(major), DOT_RADIUS * radius(major), colors.axis);
+    }
+    for &(y, major) in y_ticks.iter().filter(|&&(y, _)| y.abs() <= axes_span + 1e-4) {
+        draw_dot(&mut v, ox, y, dot_rx * radius(major), DOT_RADIUS * radius(major), colors.axis);
+    }
+
+    v
+}
+
+// A grid color at half its opacity
+fn faded(c: [f32; 4]) -> [f32; 4] {
+    [c[0], c[1], c[2], c[3] * 0.5]
+}
+
+/// Generates TriangleList vertices marking a curve's control points with dots joined by thin
+/// lines. `points` are shape-local, as from `ShapeConfig::control_polygon`, and are placed at
+/// `position` the way the shape shader places the shape.
+pub fn control_vertices(
+    points: &[[f32; 2]],
+    position: [f32; 2],
+    aspect: f32,
+    dot_mode: RenderMode,
+    colors: AxisColors,
+) -> Vec<[f32; 8]> {
+    let mut v: Vec<[f32; 8]> = Vec::new();
+    let ndc: Vec<[f32; 2]> = points.iter().map(|&[x, y]| [x / aspect + position[0], y + position[1]]).collect();
+
+    for pair in ndc.windows(2) {
+        segment_quad(&mut v, pair[0], pair[1], GRID_HALF_T, aspect, colors.grid);
+    }
+
+    let draw_dot = match dot_mode {
+        RenderMode::Mesh => dot,
+        RenderMode::Sdf => sdf_dot,
+    };
+    for &[x, y] in &ndc {
+        draw_dot(&mut v, x, y, DOT_RADIUS / aspect, DOT_RADIUS, colors.axis);
+    }
+
+    v
+}
+
+/// Generates TriangleList vertices stroking `curves`, given in axis coordinates with whether
+/// each is closed. `half_width` is in NDC Y units, and widths and joins are measured on screen.
+pub fn curve_vertices(curves: &[Contour], closed: &[bool], half_width: f32, aspect: f32, c: [f32; 4]) -> Vec<[f32; 8]> {
+    curves
+        .iter()
+        .zip(closed)
+        .flat_map(|(curve, &closed)| {
+            let screen: Vec<[f32; 2]> = curve.iter().map(|&[x, y]| [x * aspect, y]).collect();
+            stroke_path(&screen, closed)
+        })
+        .map(|[x, y, nx, ny]| vert((x + nx * half_width) / aspect, y + ny * half_width, c))
+        .collect()
+}
+
+/// Generates TriangleList vertices for the dot tracing a plotted curve, at `p` in axis
+/// coordinates. Always the same number of vertices, so they can be rewritten in place.
+pub fn pen_vertices(p: [f32; 2], aspect: f32, c: [f32; 4]) -> Vec<[f32; 8]> {
+    let mut v: Vec<[f32; 8]> = Vec::new();
+    dot(&mut v, p[0], p[1], PEN_RADIUS / aspect, PEN_RADIUS, c);
+    v
+}
+
+// Quad of half-thickness t along the segment from a to b, measured perpendicular to it on
+// screen rather than in NDC
+fn segment_quad(v: &mut Vec<[f32; 8]>, a: [f32; 2], b: [f32; 2], t: f32, aspect: f32, c: [f32; 4]) {
+    let (dx, dy) = ((b[0] - a[0]) * aspect, b[1] - a[1]);
+    let len = (dx * dx + dy * dy).sqrt();
+    if len == 0.0 {
+        return;
+    }
+    let (nx, ny) = (-dy / len * t / aspect, dx / len * t);
+    let (a0, a1) = (vert(a[0] - nx, a[1] - ny, c), vert(a[0] + nx, a[1] + ny, c));
+    let (b0, b1) = (vert(b[0] - nx, b[1] - ny, c), vert(b[0] + nx, b[1] + ny, c));
+    v.extend_from_slice(&[a0, b0, b1, a0, b1, a1]);
+}
+
+// Colorbar placement in NDC: right edge, half-width in NDC Y units, and vertical extent
+const COLORBAR_RIGHT: f32 = 0.95;
+const COLORBAR_HALF_W: f32 = 0.025;
+const COLORBAR_HALF_H: f32 = 0.75;
+const COLORBAR_TICK_LEN: f32 = 0.02;
+const COLORBAR_TICKS: u32 = 4;
+
+/// Generates TriangleList vertices for a legend of `colormap`: a vertical bar along the right
+/// of the view, low values at the bottom, framed and ticked at quarters in the axis color.
+/// Each stretch between two colormap stops is one quad with a stop color at either end, so
+/// the interpolated vertex colors reproduce the colormap exactly.
+pub fn colorbar_vertices(colormap: &Colormap, aspect: f32, colors: AxisColors) -> Vec<[f32; 8]> {
+    let mut v: Vec<[f32; 8]> = Vec::new();
+    let (x1, half_w) = (COLORBAR_RIGHT, COLORBAR_HALF_W / aspect);
+    let x0 = x1 - 2.0 * half_w;
+    let y_at = |t: f32| -COLORBAR_HALF_H + 2.0 * COLORBAR_HALF_H * t;
+
+    let stops = colormap.stops();
+    let last = (stops.len() - 1) as f32;
+    for (i, pair) in stops.windows(2).enumerate() {
+        let (y0, y1) = (y_at(i as f32 / last), y_at((i + 1) as f32 / last));
+        let (c0, c1) = (pair[0], pair[1]);
+        v.extend_from_slice(&[vert(x0, y0, c0), vert(x1, y0, c0), vert(x1, y1, c1)]);
+        v.extend_from_slice(&[vert(x0, y0, c0), vert(x1, y1, c1), vert(x0, y1, c1)]);
+    }
+
+    // Frame, with ticks reaching out to the left
+    let (top, bottom) = (y_at(1.0), y_at(0.0));
+    let grid_tx = GRID_HALF_T / aspect;
+    h_quad(&mut v, x0 - grid_tx, x1 + grid_tx, top, GRID_HALF_T, colors.axis);
+    h_quad(&mut v, x0 - grid_tx, x1 + grid_tx, bottom, GRID_HALF_T, colors.axis);
+    v_quad(&mut v, x0, bottom, top, grid_tx, colors.axis);
+    v_quad(&mut v, x1, bottom, top, grid_tx, colors.axis);
+    for i in 0..=COLORBAR_TICKS {
+        let y = y_at(i as f32 / COLORBAR_TICKS as f32);
+        h_quad(&mut v, x0 - COLORBAR_TICK_LEN / aspect, x0, y, GRID_HALF_T, colors.axis);
     }
 
     v
@@ -1,23 +1,31 @@
 // Axis/grid shader — vertices are already in NDC, no aspect correction needed.
 // Per-vertex color is passed through directly.
+// `local` is non-zero only for SDF tick dots: it is the position inside the dot in
+// units of its radius, and the circle is cut out with an anti-aliased edge.
 
 struct VertexOutput {
     @builtin(position) pos: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) local: vec2<f32>,
 }
 
 @vertex
 fn vs_main(
     @location(0) position: vec2<f32>,
     @location(1) color: vec4<f32>,
+    @location(2) local: vec2<f32>,
 ) -> VertexOutput {
     var out: VertexOutput;
     out.pos = vec4<f32>(position, 0.0, 1.0);
     out.color = color;
+    out.local = local;
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return in.color;
+    let d = length(in.local) - 1.0;
+    let aa = max(fwidth(d), 1e-6);
+    let coverage = 1.0 - smoothstep(-aa, aa, d);
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
 }
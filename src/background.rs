@@ -0,0 +1,195 @@
+use wgpu::{
+    util::DeviceExt, BindGroup, BindGroupLayoutEntry, Buffer, Device, PipelineLayout, Queue, RenderPass, RenderPipeline,
+    TextureFormat,
+};
+
+use crate::color;
+use crate::pipeline::{self, Depth};
+use crate::shaders::{self, ShaderSources};
+use crate::texture::{self, Filter, Wrap};
+
+// Side of one checkerboard cell in physical pixels
+const CHECKER_CELL_PX: f32 = 16.0;
+// Clear color behind checkerboards and letterboxed images
+const LETTERBOX: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+/// Group 0 of the background pipeline: `BackgroundParams`. Group 1 is `texture::BIND_GROUP`.
+pub const BIND_GROUP: [BindGroupLayoutEntry; 1] = [pipeline::uniform_entry(0, wgpu::ShaderStages::FRAGMENT)];
+
+/// How a background image is scaled to the window.
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Fit {
+    /// Show the whole image, letterboxing the rest
+    Fit,
+    /// Cover the whole window, cropping the image
+    Fill,
+}
+
+pub enum Background {
+    Color([f32; 4]),
+    /// Light/dark grey checks, for inspecting alpha
+    Checkerboard,
+    Image { image: image::RgbaImage, fit: Fit },
+}
+
+impl Background {
+    /// Parses a color, `checkerboard`, or otherwise a path to an image file.
+    pub fn parse(s: &str, fit: Fit) -> Result<Background, String> {
+        if s.eq_ignore_ascii_case("checkerboard") {
+            return Ok(Background::Checkerboard);
+        }
+        if let Some(c) = color::parse(s) {
+            return Ok(Background::Color(c));
+        }
+        let image = texture::load_image(s)
+            .map_err(|e| format!("--background is not a color or 'checkerboard', and {e}"))?;
+        Ok(Background::Image { image, fit })
+    }
+
+    /// Approximate relative luminance (0 = black, 1 = white) of what is behind the scene.
+    pub fn luminance(&self) -> f32 {
+        match self {
+            Background::Color(c) => color::luminance(*c),
+            // Average of the two check colors in `background_shader.wgsl`
+            Background::Checkerboard => color::luminance([0.8, 0.8, 0.8, 1.0]),
+            Background::Image { image, .. } => {
+                let sum: f32 = image
+                    .pixels()
+                    .map(|p| color::luminance(p.0.map(|c| c as f32 / 255.0)))
+                    .sum();
+                sum / (image.width() * image.height()).max(1) as f32
+            }
+        }
+    }
+
+    pub fn clear_color(&self) -> wgpu::Color {
+        let c = match self {
+            Background::Color(c) => *c,
+            Background::Checkerboard | Background::Image { .. } => LETTERBOX,
+        };
+        wgpu::Color { r: c[0] as f64, g: c[1] as f64, b: c[2] as f64, a: c[3] as f64 }
+    }
+}
+
+/// Uniform block consumed by `background_shader.wgsl`. Layout must match `BackgroundParams` there.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct BackgroundParams {
+    /// 1 = checkerboard, 2 = image
+    mode: u32,
+    cell_px: f32,
+    /// Window UVs are scaled around the centre by this before sampling the image
+    uv_scale: [f32; 2],
+}
+
+/// Fullscreen pass drawn before everything else. Plain colors only need the clear
+/// color, so this exists just for checkerboards and images.
+pub struct BackgroundLayer {
+    pipeline_layout: PipelineLayout,
+    pipeline: RenderPipeline,
+    bind_group: BindGroup,
+    texture_bind_group: BindGroup,
+    params: BackgroundParams,
+    params_buffer: Buffer,
+    // Width / height of the background image, 1.0 for checkerboards
+    image_aspect: f32,
+    fit: Fit,
+}
+
+impl BackgroundLayer {
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        format: TextureFormat,
+        background: &Background,
+        window_aspect: f32,
+        sources: &ShaderSources,
+    ) -> Result<Option<BackgroundLayer>, String> {
+        let (mode, image, fit) = match background {
+            Background::Color(_) => return Ok(None),
+            Background::Checkerboard => (1, None, Fit::Fill),
+            Background::Image { image, fit } => (2, Some(image), *fit),
+        };
+
+        let image_aspect = image.map_or(1.0, |img| img.width() as f32 / img.height() as f32);
+        let params = BackgroundParams {
+            mode,
+            cell_px: CHECKER_CELL_PX,
+            uv_scale: uv_scale(fit, image_aspect, window_aspect),
+        };
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&params),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &BIND_GROUP,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: params_buffer.as_entire_binding() }],
+        });
+
+        let texture_layout = texture::bind_group_layout(device);
+        let view = match image {
+            Some(img) => texture::upload_rgba(device, queue, img.width(), img.height(), img.as_raw())
+                .map_err(|e| format!("--background: {e}"))?,
+            None => texture::upload_rgba(device, queue, 1, 1, &[255; 4])?,
+        };
+        let sampler = texture::create_sampler(device, Filter::Linear, Wrap::Clamp);
+        let texture_bind_group = texture::bind_group(device, &texture_layout, &view, &sampler);
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout, &texture_layout],
+            push_constant_ranges: &[],
+        });
+        // Fullscreen triangle generated from the vertex index; no vertex buffer
+        let files = [&shaders::BACKGROUND];
+        let pipeline = pipeline::load(device, sources, &files, &pipeline_layout, &[], format, Depth::Ignore);
+
+        Ok(Some(BackgroundLayer {
+            pipeline_layout,
+            pipeline,
+            bind_group,
+            texture_bind_group,
+            params,
+            params_buffer,
+            image_aspect,
+            fit,
+        }))
+    }
+
+    pub fn resize(&mut self, queue: &Queue, window_aspect: f32) {
+        self.params.uv_scale = uv_scale(self.fit, self.image_aspect, window_aspect);
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&self.params));
+    }
+
+    pub fn pipeline_layout(&self) -> &PipelineLayout {
+        &self.pipeline_layout
+    }
+
+    /// Swaps in a rebuilt pipeline, e.g. after a shader reload.
+    pub fn set_pipeline(&mut self, pipeline: RenderPipeline) {
+        self.pipeline = pipeline;
+    }
+
+    pub fn draw(&self, rpass: &mut RenderPass) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_bind_group(1, &self.texture_bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+// Image UV scale for `fit` so the image keeps its own aspect ratio in the window
+fn uv_scale(fit: Fit, image_aspect: f32, window_aspect: f32) -> [f32; 2] {
+    let r = window_aspect / image_aspect;
+    match fit {
+        Fit::Fit => [r.max(1.0), (1.0 / r).max(1.0)],
+        Fit::Fill => [r.min(1.0), (1.0 / r).min(1.0)],
+    }
+}
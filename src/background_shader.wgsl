@@ -0,0 +1,44 @@
+// Fullscreen background — a checkerboard for inspecting alpha, or an image scaled to fit or fill.
+
+struct BackgroundParams {
+    mode: u32,           // 1 = checkerboard, 2 = image
+    cell_px: f32,        // checkerboard cell size in physical pixels
+    uv_scale: vec2<f32>, // window UVs are scaled around the centre by this before sampling
+}
+
+@group(0) @binding(0) var<uniform> u_bg: BackgroundParams;
+@group(1) @binding(0) var t_bg: texture_2d<f32>;
+@group(1) @binding(1) var s_bg: sampler;
+
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// One triangle covering the screen: (-1,-1), (3,-1), (-1,3)
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> VertexOutput {
+    let xy = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u)) * 2.0 - 1.0;
+    var out: VertexOutput;
+    out.pos = vec4<f32>(xy, 0.0, 1.0);
+    out.uv = vec2<f32>(0.5 + 0.5 * xy.x, 0.5 - 0.5 * xy.y);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = (in.uv - 0.5) * u_bg.uv_scale + 0.5;
+    // Sample unconditionally: textureSample must stay in uniform control flow
+    let tex = textureSample(t_bg, s_bg, uv);
+
+    if u_bg.mode == 1u {
+        let cell = floor(in.pos.xy / u_bg.cell_px);
+        let dark = (i32(cell.x) + i32(cell.y)) % 2 != 0;
+        let shade = select(0.9, 0.7, dark);
+        return vec4<f32>(shade, shade, shade, 1.0);
+    }
+
+    // Outside the image (letterbox when fitting) let the clear color through
+    let inside = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+    return select(vec4<f32>(0.0), tex, inside);
+}
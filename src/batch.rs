@@ -0,0 +1,303 @@
+use std::ops::Range;
+
+use bytemuck::Zeroable;
+use wgpu::{
+    BindGroup, BindGroupLayoutEntry, Buffer, BufferUsages, Device, PipelineLayout, RenderPass, RenderPipeline,
+    ShaderStages, TextureFormat, VertexBufferLayout,
+};
+
+use crate::graph::{self, Changes, SceneGraph};
+use crate::pipeline::{self, Depth, Tie};
+use crate::shaders::{self, ShaderSources};
+
+/// A scene graph vertex with everything needed to draw it: no per-shape state remains.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BatchVertex {
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+    /// Index into the transform storage buffer (the node's slot)
+    pub transform: u32,
+}
+
+/// Per-node transform in the storage buffer. Layout must match `Affine` in `batch_shader.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Affine {
+    /// Column-major 2x2 matrix including the aspect correction
+    m: [f32; 4],
+    /// NDC translation and depth
+    t: [f32; 4],
+}
+
+impl Affine {
+    fn new(graph: &SceneGraph, slot: u32, aspect: f32) -> Affine {
+        let transform = graph.world(slot).0;
+        let (s, c) = transform.rotation.sin_cos();
+        let k = transform.scale;
+        let [tx, ty] = transform.translation;
+        let depth = pipeline::layer_depth(graph.world_z(slot), Tie::Graph);
+        Affine { m: [c * k / aspect, s * k, -s * k / aspect, c * k], t: [tx / aspect, ty, depth, 0.0] }
+    }
+}
+
+/// Batch vertices: [x, y, r, g, b, a, transform] — stride 28 bytes
+pub const BATCH_VERTEX_LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+    array_stride: 28,
+    step_mode: wgpu::VertexStepMode::Vertex,
+    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4, 2 => Uint32],
+};
+
+pub const BIND_GROUP: [BindGroupLayoutEntry; 1] = [BindGroupLayoutEntry {
+    binding: 0,
+    visibility: ShaderStages::VERTEX,
+    ty: wgpu::BindingType::Buffer {
+        ty: wgpu::BufferBindingType::Storage { read_only: true },
+        has_dynamic_offset: false,
+        min_binding_size: None,
+    },
+    count: None,
+}];
+
+const VERTEX_SIZE: u64 = size_of::<BatchVertex>() as u64;
+const AFFINE_SIZE: u64 = size_of::<Affine>() as u64;
+
+/// Draws the opaque shapes of a `SceneGraph` in one call. Vertices are laid out like the
+/// graph's packed meshes; removed shapes are collapsed to degenerate triangles until the
+/// graph compacts, and so are translucent ones, which must be drawn one by one in depth
+/// order with the other translucent layers (see `GraphLayer`).
+///
+/// Each vertex gets its node's depth, so the batch is drawn with the other opaque layers.
+///
+/// Transform changes only rewrite their storage entries. Color changes rewrite the shape's
+/// vertices, since colors are baked in.
+pub struct Batcher {
+    pipeline_layout: PipelineLayout,
+    pipeline: RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: BindGroup,
+    vertex_buffer: Buffer,
+    transform_buffer: Buffer,
+    aspect: f32,
+    /// Vertex count to draw, including removed shapes' collapsed vertices
+    vertex_count: u32,
+    /// Color each slot's vertices were written with
+    baked: Vec<[f32; 4]>,
+    /// Everything must be rewritten, e.g. after the aspect ratio changed
+    stale: bool,
+}
+
+impl Batcher {
+    /// Whether `adapter` can read storage buffers in the vertex stage, which batching needs.
+    pub fn supported(adapter: &wgpu::Adapter) -> bool {
+        adapter.get_downlevel_capabilities().flags.contains(wgpu::DownlevelFlags::VERTEX_STORAGE)
+            && adapter.limits().max_storage_buffers_per_shader_stage > 0
+    }
+
+    pub fn new(device: &Device, format: TextureFormat, aspect: f32, sources: &ShaderSources) -> Batcher {
+        let bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor { label: None, entries: &BIND_GROUP });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let buffers = [BATCH_VERTEX_LAYOUT];
+        let pipeline =
+            pipeline::load(device, sources, &[&shaders::BATCH], &pipeline_layout, &buffers, format, Depth::Opaque);
+        let transform_buffer = graph::grown_buffer(device, "batch transforms", BufferUsages::STORAGE, 0);
+        let bind_group = transform_bind_group(device, &bind_group_layout, &transform_buffer);
+        Batcher {
+            pipeline_layout,
+            pipeline,
+            bind_group_layout,
+            bind_group,
+            vertex_buffer: graph::grown_buffer(device, "batch vertices", BufferUsages::VERTEX, 0),
+            transform_buffer,
+            aspect,
+            vertex_count: 0,
+            baked: Vec::new(),
+            stale: false,
+        }
+    }
+
+    pub fn resize(&mut self, aspect: f32) {
+        self.aspect = aspect;
+        self.stale = true;
+    }
+
+    pub fn pipeline_layout(&self) -> &PipelineLayout {
+        &self.pipeline_layout
+    }
+
+    /// Swaps in a rebuilt pipeline, e.g. after a shader reload.
+    pub fn set_pipeline(&mut self, pipeline: RenderPipeline) {
+        self.pipeline = pipeline;
+    }
+
+    /// Uploads `changes`, taken from `graph`, reallocating buffers that are too small.
+    pub(crate) fn sync(&mut self, device: &Device, queue: &wgpu::Queue, graph: &SceneGraph, changes: &Changes) {
+        self.vertex_count = graph.vertices().len() as u32;
+        self.baked.resize(graph.slot_count(), [0.0; 4]);
+
+        let vertex_bytes = self.vertex_count as u64 * VERTEX_SIZE;
+        if vertex_bytes > self.vertex_buffer.size() {
+            self.vertex_buffer = graph::grown_buffer(device, "batch vertices", BufferUsages::VERTEX, vertex_bytes);
+            self.stale = true;
+        }
+        let transform_bytes = graph.slot_count() as u64 * AFFINE_SIZE;
+        if transform_bytes > self.transform_buffer.size() {
+            self.transform_buffer =
+                graph::grown_buffer(device, "batch transforms", BufferUsages::STORAGE, transform_bytes);
+            self.bind_group = transform_bind_group(device, &self.bind_group_layout, &self.transform_buffer);
+            self.stale = true;
+        }
+
+        if std::mem::take(&mut self.stale) {
+            // Removed shapes' vertices are zeroed so they draw nothing
+            let mut vertices = vec![BatchVertex::zeroed(); self.vertex_count as usize];
+            for (slot, mesh) in graph.shapes() {
+                let baked = self.bake(graph, slot, mesh.clone());
+                vertices[mesh.start as usize..mesh.end as usize].copy_from_slice(&baked);
+            }
+            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+            let transforms: Vec<Affine> =
+                (0..graph.slot_count() as u32).map(|slot| Affine::new(graph, slot, self.aspect)).collect();
+            queue.write_buffer(&self.transform_buffer, 0, bytemuck::cast_slice(&transforms));
+            return;
+        }
+
+        for removed in &changes.removed {
+            let zeros = vec![BatchVertex::zeroed(); removed.len()];
+            queue.write_buffer(&self.vertex_buffer, removed.start as u64 * VERTEX_SIZE, bytemuck::cast_slice(&zeros));
+        }
+        for (slot, mesh) in rebaked(graph, changes, &self.baked) {
+            let offset = mesh.start as u64 * VERTEX_SIZE;
+            let vertices = self.bake(graph, slot, mesh);
+            queue.write_buffer(&self.vertex_buffer, offset, bytemuck::cast_slice(&vertices));
+        }
+        for run in graph::runs(&changes.slots) {
+            let transforms: Vec<Affine> = run.clone().map(|slot| Affine::new(graph, slot, self.aspect)).collect();
+            let offset = run.start as u64 * AFFINE_SIZE;
+            queue.write_buffer(&self.transform_buffer, offset, bytemuck::cast_slice(&transforms));
+        }
+    }
+
+    /// The vertices of shape `slot` in its current world color, recorded as baked.
+    fn bake(&mut self, graph: &SceneGraph, slot: u32, mesh: Range<u32>) -> Vec<BatchVertex> {
+        self.baked[slot as usize] = graph.world(slot).1;
+        bake(graph, slot, mesh)
+    }
+
+    /// Draws the opaque shapes of the graph as last synced. Returns the number of vertices drawn.
+    pub fn draw(&self, rpass: &mut RenderPass) -> u32 {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        rpass.draw(0..self.vertex_count, 0..1);
+        self.vertex_count
+    }
+}
+
+/// The vertices of shape `slot` in its world color as of the last `take_changes`, or zeros
+/// if it is translucent.
+fn bake(graph: &SceneGraph, slot: u32, mesh: Range<u32>) -> Vec<BatchVertex> {
+    if !graph.opaque(slot) {
+        return vec![BatchVertex::zeroed(); mesh.len()];
+    }
+    let color = graph.world(slot).1;
+    graph.vertices()[mesh.start as usize..mesh.end as usize]
+        .iter()
+        .map(|&[x, y, ..]| BatchVertex { position: [x, y], color, transform: slot })
+        .collect()
+}
+
+/// Shapes whose vertices must be rewritten after `changes`: new ones and those moved by
+/// compaction, and those whose world color is no longer the one in `baked`.
+fn rebaked(graph: &SceneGraph, changes: &Changes, baked: &[[f32; 4]]) -> Vec<(u32, Range<u32>)> {
+    let added = changes.vertices.clone().unwrap_or_default();
+    let colored = |slot: u32| changes.slots.binary_search(&slot).is_ok();
+    graph
+        .shapes()
+        .filter(|(slot, mesh)| {
+            let moved = (mesh.start as usize) < added.end && added.start < mesh.end as usize;
+            moved || (colored(*slot) && baked[*slot as usize] != graph.world(*slot).1)
+        })
+        .collect()
+}
+
+fn transform_bind_group(device: &Device, layout: &wgpu::BindGroupLayout, buffer: &Buffer) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout,
+        entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Transform;
+
+    const TRIANGLE: [[f32; 4]; 3] = [[0.0, 0.0, 0.0, 0.0], [1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0]];
+    const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+
+    // What `Batcher::sync` would rebake, recording the colors as baked
+    fn sync(graph: &mut SceneGraph, baked: &mut Vec<[f32; 4]>) -> Vec<(u32, Range<u32>)> {
+        let changes = graph.take_changes();
+        baked.resize(graph.slot_count(), [0.0; 4]);
+        let shapes = rebaked(graph, &changes, baked);
+        for (slot, _) in &shapes {
+            baked[*slot as usize] = graph.world(*slot).1;
+        }
+        shapes
+    }
+
+    #[test]
+    fn bake_carries_slot_and_world_color() {
+        let mut graph = SceneGraph::new();
+        let group = graph.add_group(None, Transform::IDENTITY).unwrap();
+        graph.set_color(group, [1.0, 0.5, 0.5, 1.0]).unwrap();
+        graph.add_shape(Some(group), &TRIANGLE, [1.0, 1.0, 0.0, 1.0], Transform::IDENTITY).unwrap();
+        graph.take_changes();
+        let slot = graph.shapes().next().unwrap().0;
+        let vertices = bake(&graph, slot, 0..3);
+        assert_eq!(vertices.len(), 3);
+        assert_eq!(vertices[1].position, [1.0, 0.0]);
+        assert!(vertices.iter().all(|v| v.color == [1.0, 0.5, 0.0, 1.0] && v.transform == slot));
+
+        // Translucent shapes are left to the per-shape path
+        graph.set_color(group, [0.5; 4]).unwrap();
+        graph.take_changes();
+        assert!(bake(&graph, slot, 0..3).iter().all(|v| v.position == [0.0; 2] && v.color == [0.0; 4]));
+    }
+
+    #[test]
+    fn only_new_moved_or_recolored_shapes_are_rebaked() {
+        let mut graph = SceneGraph::new();
+        let mut baked = Vec::new();
+        let ids: Vec<_> = (0..3).map(|_| graph.add_shape(None, &TRIANGLE, RED, Transform::IDENTITY).unwrap()).collect();
+        assert_eq!(sync(&mut graph, &mut baked), [(0, 0..3), (1, 3..6), (2, 6..9)]);
+        assert!(sync(&mut graph, &mut baked).is_empty());
+
+        // Transforms live in the storage buffer, not the vertices
+        graph.set_transform(ids[0], Transform { scale: 2.0, ..Transform::IDENTITY }).unwrap();
+        assert!(sync(&mut graph, &mut baked).is_empty());
+
+        graph.set_color(ids[1], [0.0, 1.0, 0.0, 1.0]).unwrap();
+        assert_eq!(sync(&mut graph, &mut baked), [(1, 3..6)]);
+        // Set back and forth between syncs: nothing to rewrite
+        graph.set_color(ids[1], RED).unwrap();
+        graph.set_color(ids[1], [0.0, 1.0, 0.0, 1.0]).unwrap();
+        assert!(sync(&mut graph, &mut baked).is_empty());
+
+        let added = graph.add_shape(None, &TRIANGLE, RED, Transform::IDENTITY).unwrap();
+        assert_eq!(sync(&mut graph, &mut baked), [(3, 9..12)]);
+
+        // Removing half the vertices compacts, moving every remaining shape
+        graph.remove(ids[0]).unwrap();
+        graph.remove(ids[2]).unwrap();
+        graph.remove(added).unwrap();
+        assert_eq!(sync(&mut graph, &mut baked), [(1, 0..3)]);
+    }
+}
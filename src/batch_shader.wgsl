@@ -0,0 +1,32 @@
+// Batched scene graph shader: every shape in a single draw, without uniforms. Each vertex
+// carries its color and the index of its node's transform in the storage buffer.
+
+struct Affine {
+    m: vec4<f32>, // 2x2 matrix, column-major, with the aspect correction applied
+    t: vec4<f32>, // xy = translation in NDC, z = depth
+}
+
+@group(0) @binding(0) var<storage, read> transforms: array<Affine>;
+
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) transform: u32,
+) -> VertexOutput {
+    let a = transforms[transform];
+    var out: VertexOutput;
+    out.pos = vec4<f32>(mat2x2<f32>(a.m.xy, a.m.zw) * position + a.t.xy, a.t.z, 1.0);
+    out.color = color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
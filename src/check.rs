@@ -0,0 +1,524 @@
+use std::path::{Path, PathBuf};
+
+use naga::valid::ModuleInfo;
+use naga::{AddressSpace, Binding, Module, ScalarKind, ShaderStage, Span, TypeInner};
+use wgpu::{BindGroupLayoutEntry, BindingType, BufferBindingType, ShaderStages, VertexBufferLayout, VertexFormat};
+
+use crate::frag::UserFrag;
+use crate::shaders::{self, ShaderError, ShaderFile, ShaderSources};
+use crate::state::{
+    AXIS_VERTEX_LAYOUT, GLOBALS_BIND_GROUP, SHAPE_BIND_GROUP, SHAPE_VERTEX_LAYOUT, STROKE_VERTEX_LAYOUT, ShapeVariant,
+};
+use crate::batch::{self, BATCH_VERTEX_LAYOUT};
+use crate::{background, heatmap, texture};
+
+/// Every shader shipped with the crate.
+const BUILTINS: [&ShaderFile; 11] = [
+    &shaders::FILL,
+    &shaders::SHAPE,
+    &shaders::SDF,
+    &shaders::INSTANCED,
+    &shaders::STROKE,
+    &shaders::BATCH,
+    &shaders::AXIS,
+    &shaders::BACKGROUND,
+    &shaders::HEATMAP,
+    &shaders::FRAG_WRAPPER,
+    &shaders::TRIANGLE,
+];
+
+/// Stand-in for a user's `shade` when checking the `--frag` wrapper without one.
+const STUB_SHADE: &str = "fn shade(uv: vec2f, time: f32, resolution: vec2f) -> vec4f { return vec4f(uv, 0.0, 1.0); }";
+
+/// What the Rust side provides to a pipeline: its vertex buffers and bind group layouts.
+struct Interface {
+    buffers: &'static [VertexBufferLayout<'static>],
+    groups: &'static [&'static [BindGroupLayoutEntry]],
+}
+
+const SHAPE: Interface =
+    Interface { buffers: &[SHAPE_VERTEX_LAYOUT], groups: &[&SHAPE_BIND_GROUP, &texture::BIND_GROUP] };
+const FRAG: Interface = Interface {
+    buffers: &[SHAPE_VERTEX_LAYOUT],
+    groups: &[&SHAPE_BIND_GROUP, &texture::BIND_GROUP, &GLOBALS_BIND_GROUP],
+};
+const STROKE: Interface = Interface { buffers: &[STROKE_VERTEX_LAYOUT], groups: &[&SHAPE_BIND_GROUP] };
+const BATCH: Interface = Interface { buffers: &[BATCH_VERTEX_LAYOUT], groups: &[&batch::BIND_GROUP] };
+const AXIS: Interface = Interface { buffers: &[AXIS_VERTEX_LAYOUT], groups: &[] };
+// Fullscreen triangle generated from the vertex index
+const BACKGROUND: Interface = Interface { buffers: &[], groups: &[&background::BIND_GROUP, &texture::BIND_GROUP] };
+// Quad generated from the vertex index
+const HEATMAP: Interface = Interface { buffers: &[], groups: &[&heatmap::BIND_GROUP] };
+const TRIANGLE: Interface = Interface { buffers: &[], groups: &[] };
+
+/// One problem found in a module, pointing into the file it came from when naga gave a span.
+struct Diagnostic {
+    message: String,
+    file: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+}
+
+impl Diagnostic {
+    fn new(message: String) -> Diagnostic {
+        Diagnostic { message, file: None, line: None, column: None }
+    }
+}
+
+/// The result of checking one module.
+struct Report {
+    name: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// A module joined from several files, remembering the line each one starts on.
+struct Composed {
+    source: String,
+    parts: Vec<(String, u32)>,
+}
+
+impl Composed {
+    fn new(parts: Vec<(String, String)>) -> Composed {
+        let mut starts = Vec::new();
+        let mut line = 1;
+        for (name, source) in &parts {
+            starts.push((name.clone(), line));
+            line += source.matches('\n').count() as u32 + 1;
+        }
+        let source = parts.iter().map(|(_, s)| s.as_str()).collect::<Vec<_>>().join("\n");
+        Composed { source, parts: starts }
+    }
+
+    fn label(&self) -> String {
+        self.parts.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(" + ")
+    }
+
+    /// Maps a location in the joined source back to the file and line it came from.
+    fn diagnostic(&self, message: String, location: Option<naga::SourceLocation>) -> Diagnostic {
+        let Some(loc) = location else { return Diagnostic::new(message) };
+        let (file, start) = self.parts.iter().rev().find(|(_, start)| loc.line_number >= *start).unwrap();
+        Diagnostic {
+            message,
+            file: Some(file.clone()),
+            line: Some(loc.line_number - start + 1),
+            column: Some(loc.line_position),
+        }
+    }
+
+    fn report(&self, interface: &Interface, validated: Result<(Module, ModuleInfo), ShaderError>) -> Report {
+        let diagnostics = match validated {
+            Err(e) => vec![self.diagnostic(e.rendered, e.location)],
+            Ok((module, info)) => interface
+                .check(&module, &info)
+                .into_iter()
+                .map(|(message, span)| self.diagnostic(message, span.is_defined().then(|| span.location(&self.source))))
+                .collect(),
+        };
+        Report { name: self.label(), diagnostics }
+    }
+}
+
+impl Interface {
+    /// The shape or `--frag` interface with the vertex buffers of `variant`.
+    fn shape(base: &Interface, variant: ShapeVariant) -> Interface {
+        Interface { buffers: variant.buffers(), groups: base.groups }
+    }
+
+    /// Checks what `pipeline::build` would otherwise only find out on a device: the entry
+    /// points, the vertex inputs of `vs_main`, and every binding an entry point uses.
+    fn check(&self, module: &Module, info: &ModuleInfo) -> Vec<(String, Span)> {
+        let mut errors = Vec::new();
+        for (name, stage) in [("vs_main", ShaderStage::Vertex), ("fs_main", ShaderStage::Fragment)] {
+            if !module.entry_points.iter().any(|ep| ep.name == name && ep.stage == stage) {
+                errors.push((format!("missing {stage:?} entry point `{name}`"), Span::default()));
+            }
+        }
+
+        for (i, ep) in module.entry_points.iter().enumerate() {
+            let stage = match ep.stage {
+                ShaderStage::Vertex => ShaderStages::VERTEX,
+                ShaderStage::Fragment => ShaderStages::FRAGMENT,
+                _ => ShaderStages::COMPUTE,
+            };
+            let uses = info.get_entry_point(i);
+            for (handle, var) in module.global_variables.iter() {
+                let Some(rb) = &var.binding else { continue };
+                if uses[handle].is_empty() {
+                    continue;
+                }
+                let span = module.global_variables.get_span(handle);
+                let what = format!(
+                    "`{}` (@group({}) @binding({})) used by `{}`",
+                    var.name.as_deref().unwrap_or("?"),
+                    rb.group,
+                    rb.binding,
+                    ep.name
+                );
+                let entry = self.groups.get(rb.group as usize).and_then(|g| g.iter().find(|e| e.binding == rb.binding));
+                let Some(entry) = entry else {
+                    errors.push((format!("{what} has no entry in the bind group layout"), span));
+                    continue;
+                };
+                if !entry.visibility.contains(stage) {
+                    errors.push((format!("{what} is not visible to that stage ({:?})", entry.visibility), span));
+                }
+                let (declared, bound) = (shader_kind(module, var), layout_kind(&entry.ty));
+                if declared != bound {
+                    errors.push((format!("{what} is a {declared} but the layout binds a {bound}"), span));
+                }
+            }
+        }
+
+        if let Some(vs) = module.entry_points.iter().find(|ep| ep.name == "vs_main") {
+            for arg in &vs.function.arguments {
+                match (&arg.binding, &module.types[arg.ty].inner) {
+                    (Some(binding), inner) => {
+                        errors.extend(self.check_input(binding, arg.name.as_deref(), inner, Span::default()));
+                    }
+                    (None, TypeInner::Struct { members, .. }) => {
+                        let span = module.types.get_span(arg.ty);
+                        for member in members {
+                            if let Some(binding) = &member.binding {
+                                let inner = &module.types[member.ty].inner;
+                                errors.extend(self.check_input(binding, member.name.as_deref(), inner, span));
+                            }
+                        }
+                    }
+                    (None, _) => {}
+                }
+            }
+        }
+        errors
+    }
+
+    fn check_input(
+        &self,
+        binding: &Binding,
+        name: Option<&str>,
+        inner: &TypeInner,
+        span: Span,
+    ) -> Option<(String, Span)> {
+        let &Binding::Location { location, .. } = binding else { return None };
+        let name = name.unwrap_or("?");
+        let attribute = self.buffers.iter().flat_map(|b| b.attributes).find(|a| a.shader_location == location);
+        let Some(attribute) = attribute else {
+            return Some((
+                format!("vertex input `{name}` at @location({location}) is not provided by any vertex buffer"),
+                span,
+            ));
+        };
+        match vertex_format(inner) {
+            Some(format) if format == attribute.format => None,
+            Some(format) => Some((
+                format!(
+                    "vertex input `{name}` at @location({location}) expects {format:?} but the buffer provides {:?}",
+                    attribute.format
+                ),
+                span,
+            )),
+            None => Some((
+                format!("vertex input `{name}` at @location({location}) has a type no vertex format provides"),
+                span,
+            )),
+        }
+    }
+}
+
+/// The 32-bit vertex format matching a WGSL scalar or vector type.
+fn vertex_format(inner: &TypeInner) -> Option<VertexFormat> {
+    use VertexFormat::*;
+    let (scalar, size) = match *inner {
+        TypeInner::Scalar(scalar) => (scalar, 1),
+        TypeInner::Vector { size, scalar } => (scalar, size as u32),
+        _ => return None,
+    };
+    if scalar.width != 4 {
+        return None;
+    }
+    Some(match (scalar.kind, size) {
+        (ScalarKind::Float, 1) => Float32,
+        (ScalarKind::Float, 2) => Float32x2,
+        (ScalarKind::Float, 3) => Float32x3,
+        (ScalarKind::Float, 4) => Float32x4,
+        (ScalarKind::Sint, 1) => Sint32,
+        (ScalarKind::Sint, 2) => Sint32x2,
+        (ScalarKind::Sint, 3) => Sint32x3,
+        (ScalarKind::Sint, 4) => Sint32x4,
+        (ScalarKind::Uint, 1) => Uint32,
+        (ScalarKind::Uint, 2) => Uint32x2,
+        (ScalarKind::Uint, 3) => Uint32x3,
+        (ScalarKind::Uint, 4) => Uint32x4,
+        _ => return None,
+    })
+}
+
+fn shader_kind(module: &Module, var: &naga::GlobalVariable) -> &'static str {
+    match (var.space, &module.types[var.ty].inner) {
+        (AddressSpace::Uniform, _) => "uniform buffer",
+        (AddressSpace::Storage { .. }, _) => "storage buffer",
+        (AddressSpace::Handle, TypeInner::Image { .. }) => "texture",
+        (AddressSpace::Handle, TypeInner::Sampler { .. }) => "sampler",
+        _ => "resource",
+    }
+}
+
+fn layout_kind(ty: &BindingType) -> &'static str {
+    match ty {
+        BindingType::Buffer { ty: BufferBindingType::Uniform, .. } => "uniform buffer",
+        BindingType::Buffer { ty: BufferBindingType::Storage { .. }, .. } => "storage buffer",
+        BindingType::Texture { .. } => "texture",
+        BindingType::Sampler(_) => "sampler",
+        _ => "other resource",
+    }
+}
+
+/// `file` as named in diagnostics: its path when read from the shader directory.
+fn part_name(sources: &ShaderSources, file: &ShaderFile) -> String {
+    match sources.dir().map(|dir| dir.join(file.name)) {
+        Some(path) if path.exists() => path.display().to_string(),
+        _ => file.name.to_owned(),
+    }
+}
+
+fn check_files(sources: &ShaderSources, files: &[&ShaderFile], interface: &Interface) -> Report {
+    let parts = files.iter().map(|f| Ok((part_name(sources, f), sources.read(f)?))).collect::<Result<Vec<_>, String>>();
+    match parts {
+        Ok(parts) => {
+            let composed = Composed::new(parts);
+            composed.report(interface, shaders::validate(&composed.source, &composed.label()))
+        }
+        Err(e) => Report { name: shaders::label(files), diagnostics: vec![Diagnostic::new(e)] },
+    }
+}
+
+/// The `--frag` wrapper linked into the shape shader for `variant`, with the user's `shade`
+/// or a stub standing in for it.
+fn check_frag(sources: &ShaderSources, frag: Option<&UserFrag>, variant: ShapeVariant) -> Report {
+    let files = [&shaders::FRAG_WRAPPER, variant.files()[1]];
+    let user = match frag {
+        Some(frag) => (frag.path.display().to_string(), frag.source().to_owned()),
+        None => ("stub shade()".to_owned(), STUB_SHADE.to_owned()),
+    };
+    let parts = files.iter().map(|f| Ok((part_name(sources, f), sources.read(f)?)));
+    let parts = std::iter::once(Ok(user)).chain(parts).collect::<Result<Vec<_>, String>>();
+    match parts {
+        Ok(parts) => {
+            let composed = Composed::new(parts);
+            let validated = match frag {
+                Some(frag) => frag.check(&composed.source),
+                None => shaders::validate(&composed.source, &composed.label()),
+            };
+            composed.report(&Interface::shape(&FRAG, variant), validated)
+        }
+        Err(e) => Report { name: shaders::label(&files), diagnostics: vec![Diagnostic::new(e)] },
+    }
+}
+
+/// Every pipeline the sandbox can build from `sources` whose files satisfy `include`.
+fn check_builtins(sources: &ShaderSources, include: impl Fn(&[&ShaderFile]) -> bool) -> Vec<Report> {
+    let mut pipelines: Vec<(Vec<&ShaderFile>, Interface)> =
+        ShapeVariant::ALL.iter().map(|&v| (v.files().to_vec(), Interface::shape(&SHAPE, v))).collect();
+    pipelines.push((vec![&shaders::STROKE], STROKE));
+    pipelines.push((vec![&shaders::BATCH], BATCH));
+    pipelines.push((vec![&shaders::AXIS], AXIS));
+    pipelines.push((vec![&shaders::BACKGROUND], BACKGROUND));
+    pipelines.push((vec![&shaders::HEATMAP], HEATMAP));
+    pipelines.push((vec![&shaders::TRIANGLE], TRIANGLE));
+
+    let mut reports: Vec<Report> = pipelines
+        .iter()
+        .filter(|(files, _)| include(files))
+        .map(|(files, interface)| check_files(sources, files, interface))
+        .collect();
+    for variant in ShapeVariant::ALL {
+        if include(&[&shaders::FRAG_WRAPPER, variant.files()[1]]) {
+            reports.push(check_frag(sources, None, variant));
+        }
+    }
+    reports
+}
+
+/// A file defining `shade` without entry points is checked as a `--frag` file in every
+/// shape variant, linked with the wrapper and shape shaders from `sources`; anything else
+/// is validated on its own.
+fn check_user_file(sources: &ShaderSources, path: &Path) -> Vec<Report> {
+    let frag = match UserFrag::load(path) {
+        Ok(frag) => frag,
+        Err(e) => return vec![Report { name: path.display().to_string(), diagnostics: vec![Diagnostic::new(e)] }],
+    };
+    let source = frag.source();
+    if source.contains("fn shade") && !source.contains("@vertex") && !source.contains("@fragment") {
+        return ShapeVariant::ALL.iter().map(|&v| check_frag(sources, Some(&frag), v)).collect();
+    }
+    let composed = Composed::new(vec![(path.display().to_string(), source.to_owned())]);
+    let diagnostics = match shaders::validate(&composed.source, &composed.label()) {
+        Ok(_) => Vec::new(),
+        Err(e) => vec![composed.diagnostic(e.rendered, e.location)],
+    };
+    vec![Report { name: composed.label(), diagnostics }]
+}
+
+/// Validates every built-in pipeline (read from `shader_dir` where it has a copy) and
+/// each of `files`, printing the results. Returns whether everything passed.
+pub fn run(files: &[PathBuf], shader_dir: Option<PathBuf>, json: bool) -> bool {
+    let sources = ShaderSources::new(shader_dir);
+    let mut reports = check_builtins(&sources, |_| true);
+
+    // Files named like a built-in shader are checked in the pipelines they would replace
+    // when their directory is used as --shader-dir
+    let mut overrides: Vec<(PathBuf, Vec<&str>)> = Vec::new();
+    for path in files {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        let Some(builtin) = BUILTINS.iter().find(|f| f.name == name) else {
+            reports.extend(check_user_file(&sources, path));
+            continue;
+        };
+        let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        match overrides.iter_mut().find(|(d, _)| d == dir) {
+            Some((_, names)) => names.push(builtin.name),
+            None => overrides.push((dir.to_owned(), vec![builtin.name])),
+        }
+    }
+    for (dir, names) in overrides {
+        let sources = ShaderSources::new(Some(dir));
+        reports.extend(check_builtins(&sources, |files| files.iter().any(|f| names.contains(&f.name))));
+    }
+    let failed = reports.iter().filter(|r| !r.diagnostics.is_empty()).count();
+
+    if json {
+        let modules: Vec<_> = reports
+            .iter()
+            .map(|r| {
+                let errors: Vec<_> = r
+                    .diagnostics
+                    .iter()
+                    .map(|d| {
+                        serde_json::json!({
+                            "message": d.message,
+                            "file": d.file,
+                            "line": d.line,
+                            "column": d.column,
+                        })
+                    })
+                    .collect();
+                serde_json::json!({ "name": r.name, "ok": errors.is_empty(), "errors": errors })
+            })
+            .collect();
+        let out = serde_json::json!({ "ok": failed == 0, "modules": modules });
+        println!("{}", serde_json::to_string_pretty(&out).unwrap());
+    } else {
+        for report in &reports {
+            if report.diagnostics.is_empty() {
+                println!("ok    {}", report.name);
+                continue;
+            }
+            println!("FAIL  {}", report.name);
+            for d in &report.diagnostics {
+                match (&d.file, d.line, d.column) {
+                    (Some(file), Some(line), Some(column)) => println!("  {file}:{line}:{column}: {}", d.message),
+                    _ => println!("  {}", d.message),
+                }
+            }
+        }
+        println!("{} of {} modules passed", reports.len() - failed, reports.len());
+    }
+    failed == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(source: &str, interface: &Interface) -> Vec<Diagnostic> {
+        let composed = Composed::new(vec![("snippet.wgsl".to_owned(), source.to_owned())]);
+        composed.report(interface, shaders::validate(&composed.source, &composed.label())).diagnostics
+    }
+
+    const FRAGMENT: &str = "@fragment\nfn fs_main() -> @location(0) vec4f {\n    return vec4f(1.0);\n}\n";
+
+    #[test]
+    fn vertex_inputs_must_match_the_buffers() {
+        let vertex = |ty: &str| {
+            let signature = format!("fn vs_main(@location(0) position: {ty}) -> @builtin(position) vec4f");
+            format!("@vertex\n{signature} {{\n    return vec4f(1.0);\n}}\n")
+        };
+        assert!(check(&(vertex("vec2f") + FRAGMENT), &STROKE).is_empty());
+
+        let diagnostics = check(&(vertex("vec3f") + FRAGMENT), &STROKE);
+        assert_eq!(diagnostics.len(), 1);
+        let expected = "vertex input `position` at @location(0) expects Float32x3 but the buffer provides Float32x2";
+        assert_eq!(diagnostics[0].message, expected);
+
+        let diagnostics = check(&vertex("vec2f"), &STROKE);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "missing Fragment entry point `fs_main`");
+    }
+
+    #[test]
+    fn bindings_must_be_in_the_layout() {
+        let source = "\
+@group(0) @binding(3) var<uniform> tint: vec4f;
+
+@vertex
+fn vs_main(@location(0) position: vec2f) -> @builtin(position) vec4f {
+    return vec4f(position, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4f {
+    return tint;
+}
+";
+        let diagnostics = check(source, &AXIS);
+        assert_eq!(diagnostics.len(), 1);
+        let d = &diagnostics[0];
+        assert_eq!(d.message, "`tint` (@group(0) @binding(3)) used by `fs_main` has no entry in the bind group layout");
+        assert_eq!((d.file.as_deref(), d.line), (Some("snippet.wgsl"), Some(1)));
+    }
+
+    #[test]
+    fn frag_errors_point_into_the_user_file() {
+        let path = std::env::temp_dir().join(format!("check-{}.wgsl", std::process::id()));
+        let source = "\
+// Fades with time
+fn shade(uv: vec2f, time: f32, resolution: vec2f) -> vec4f {
+    let t: i32 = time;
+    return vec4f(uv, 0.0, 1.0);
+}
+";
+        std::fs::write(&path, source).unwrap();
+        let reports = check_user_file(&ShaderSources::default(), &path);
+        std::fs::remove_file(&path).unwrap();
+
+        // Checked as a `--frag` file in every shape variant
+        assert_eq!(reports.len(), ShapeVariant::ALL.len());
+        for report in reports {
+            assert_eq!(report.diagnostics.len(), 1, "{}", report.name);
+            let d = &report.diagnostics[0];
+            assert_eq!(d.file, Some(path.display().to_string()));
+            assert_eq!(d.line, Some(3));
+        }
+    }
+
+    #[test]
+    fn frag_files_are_linked_with_the_shader_dir_wrapper() {
+        let dir = std::env::temp_dir().join(format!("check-dir-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // A wrapper whose `shade` takes no resolution
+        let builtin = shaders::FRAG_WRAPPER.builtin;
+        let call = "shade(uv, u_globals.time, u_globals.resolution)";
+        let wrapper = builtin.replace(call, "shade(uv, u_globals.time)");
+        assert_ne!(wrapper, builtin);
+        std::fs::write(dir.join(shaders::FRAG_WRAPPER.name), wrapper).unwrap();
+        let path = dir.join("user.wgsl");
+        let shade = "fn shade(uv: vec2f, time: f32) -> vec4f {\n    return vec4f(uv, time, 1.0);\n}\n";
+        std::fs::write(&path, shade).unwrap();
+
+        let passed = |sources: ShaderSources| check_user_file(&sources, &path).iter().all(|r| r.diagnostics.is_empty());
+        let with_dir = passed(ShaderSources::new(Some(dir.clone())));
+        let builtin_only = passed(ShaderSources::default());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(with_dir);
+        assert!(!builtin_only);
+    }
+}
@@ -0,0 +1,76 @@
+/// Parses a named color (`red`, `orange`, ...) or a hex color (`#rgb`, `#rrggbb`, `#rrggbbaa`).
+pub fn parse(s: &str) -> Option<[f32; 4]> {
+    let s = s.trim().to_lowercase();
+    if let Some(hex) = s.strip_prefix('#') {
+        return parse_hex(hex);
+    }
+    let c = match s.as_str() {
+        "red"     => [1.0, 0.0, 0.0, 1.0],
+        "green"   => [0.0, 1.0, 0.0, 1.0],
+        "blue"    => [0.0, 0.0, 1.0, 1.0],
+        "white"   => [1.0, 1.0, 1.0, 1.0],
+        "black"   => [0.0, 0.0, 0.0, 1.0],
+        "yellow"  => [1.0, 1.0, 0.0, 1.0],
+        "cyan"    => [0.0, 1.0, 1.0, 1.0],
+        "magenta" => [1.0, 0.0, 1.0, 1.0],
+        "orange"  => [1.0, 0.5, 0.0, 1.0],
+        "purple"  => [0.5, 0.0, 0.5, 1.0],
+        "transparent" => [0.0, 0.0, 0.0, 0.0],
+        _         => return None,
+    };
+    Some(c)
+}
+
+fn parse_hex(hex: &str) -> Option<[f32; 4]> {
+    if !hex.is_ascii() {
+        return None;
+    }
+    let digits: Vec<u8> = match hex.len() {
+        // Short form: each digit is doubled, so 0xf -> 0xff
+        3 | 4 => hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| (d * 17) as u8))
+            .collect::<Option<_>>()?,
+        6 | 8 => (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+            .collect::<Option<_>>()?,
+        _ => return None,
+    };
+    let a = digits.get(3).copied().unwrap_or(255);
+    Some([digits[0], digits[1], digits[2], a].map(|d| d as f32 / 255.0))
+}
+
+/// WCAG relative luminance of an sRGB-encoded color, ignoring alpha.
+pub fn luminance(c: [f32; 4]) -> f32 {
+    let lin = |v: f32| {
+        if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+    };
+    0.2126 * lin(c[0]) + 0.7152 * lin(c[1]) + 0.0722 * lin(c[2])
+}
+
+/// WCAG contrast ratio between two luminances, from 1 (none) to 21 (black on white).
+pub fn contrast_ratio(l1: f32, l2: f32) -> f32 {
+    (l1.max(l2) + 0.05) / (l1.min(l2) + 0.05)
+}
+
+/// Returns `c`, or `c` mixed towards white or black (whichever contrasts more with
+/// the background) just far enough to reach `min_ratio` against `bg_luminance`.
+pub fn ensure_contrast(c: [f32; 4], bg_luminance: f32, min_ratio: f32) -> [f32; 4] {
+    if contrast_ratio(luminance(c), bg_luminance) >= min_ratio {
+        return c;
+    }
+    let target = if contrast_ratio(1.0, bg_luminance) > contrast_ratio(0.0, bg_luminance) { 1.0 } else { 0.0 };
+    let mix = |t: f32| [0, 1, 2].map(|i| c[i] + (target - c[i]) * t);
+    let steps = 20;
+    (1..=steps)
+        .map(|i| {
+            let [r, g, b] = mix(i as f32 / steps as f32);
+            [r, g, b, c[3]]
+        })
+        .find(|&m| contrast_ratio(luminance(m), bg_luminance) >= min_ratio)
+        .unwrap_or_else(|| {
+            let [r, g, b] = mix(1.0);
+            [r, g, b, c[3]]
+        })
+}
@@ -0,0 +1,187 @@
+// Scalar-to-color mapping for data-driven coloring.
+//
+// A colormap is a list of evenly spaced sRGB colors, linearly interpolated between. The
+// built-ins are sampled at nine even steps from their reference definitions, which keeps
+// them within a few percent of the originals; custom maps are read from text files.
+
+use std::path::Path;
+
+use crate::color;
+
+/// A built-in colormap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Builtin {
+    /// Perceptually uniform, dark blue through green to yellow
+    Viridis,
+    /// Perceptually uniform, black through purple and orange to pale yellow
+    Magma,
+    /// Perceptually uniform, black through purple and orange to yellow
+    Inferno,
+    /// Perceptually uniform, blue through magenta to yellow
+    Plasma,
+    /// Perceptually uniform and readable with color vision deficiencies, blue to yellow
+    Cividis,
+    /// Diverging, blue through light grey to red
+    Coolwarm,
+    /// Diverging, red through white to blue
+    Rdbu,
+}
+
+const VIRIDIS: [u32; 9] = [0x440154, 0x472c7a, 0x3b518b, 0x2c718e, 0x21908d, 0x27ad81, 0x5cc863, 0xaadc32, 0xfde725];
+const MAGMA: [u32; 9] = [0x000004, 0x1d1147, 0x51127c, 0x832681, 0xb73779, 0xe75263, 0xfc8961, 0xfec287, 0xfcfdbf];
+const INFERNO: [u32; 9] = [0x000004, 0x1f0c48, 0x550f6d, 0x88226a, 0xba3655, 0xe35933, 0xf98e09, 0xf8c931, 0xfcffa4];
+const PLASMA: [u32; 9] = [0x0d0887, 0x4c02a1, 0x7e03a8, 0xa92395, 0xcc4778, 0xe56b5d, 0xf89540, 0xfdc527, 0xf0f921];
+const CIVIDIS: [u32; 9] = [0x00224e, 0x123570, 0x3b496c, 0x575d6d, 0x707173, 0x8a8779, 0xa69d75, 0xc4b56c, 0xfee838];
+const COOLWARM: [u32; 9] = [0x3b4cc0, 0x6282ea, 0x8db0fe, 0xb8d0f9, 0xdddddd, 0xf5c4ad, 0xf49a7b, 0xde604d, 0xb40426];
+const RDBU: [u32; 9] = [0xb2182b, 0xd6604d, 0xf4a582, 0xfddbc7, 0xf7f7f7, 0xd1e5f0, 0x92c5de, 0x4393c3, 0x2166ac];
+
+impl Builtin {
+    fn stops(self) -> &'static [u32] {
+        match self {
+            Builtin::Viridis => &VIRIDIS,
+            Builtin::Magma => &MAGMA,
+            Builtin::Inferno => &INFERNO,
+            Builtin::Plasma => &PLASMA,
+            Builtin::Cividis => &CIVIDIS,
+            Builtin::Coolwarm => &COOLWARM,
+            Builtin::Rdbu => &RDBU,
+        }
+    }
+}
+
+/// Colors evenly spaced over 0..=1, from a built-in or a file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Colormap {
+    stops: Vec<[f32; 4]>,
+}
+
+impl Default for Colormap {
+    fn default() -> Colormap {
+        Colormap::from(Builtin::Viridis)
+    }
+}
+
+impl From<Builtin> for Colormap {
+    fn from(builtin: Builtin) -> Colormap {
+        let rgb = |hex: u32| {
+            let [r, g, b] = [16, 8, 0].map(|shift| ((hex >> shift) & 0xff) as f32 / 255.0);
+            [r, g, b, 1.0]
+        };
+        Colormap { stops: builtin.stops().iter().map(|&hex| rgb(hex)).collect() }
+    }
+}
+
+impl Colormap {
+    /// A colormap through `stops`, which needs at least two colors.
+    pub fn new(stops: Vec<[f32; 4]>) -> Result<Colormap, String> {
+        if stops.len() < 2 {
+            return Err(format!("a colormap needs at least 2 colors, got {}", stops.len()));
+        }
+        Ok(Colormap { stops })
+    }
+
+    /// A built-in name (`viridis`, `coolwarm`, ...) or the path of a colormap file.
+    pub fn parse(s: &str) -> Result<Colormap, String> {
+        use clap::ValueEnum;
+        match Builtin::from_str(s, true) {
+            Ok(builtin) => Ok(builtin.into()),
+            Err(_) if Path::new(s).is_file() => Colormap::load(Path::new(s)),
+            Err(_) => {
+                let names: Vec<String> =
+                    Builtin::value_variants().iter().filter_map(|b| Some(b.to_possible_value()?.get_name().to_owned())).collect();
+                Err(format!("unknown colormap '{s}': expected one of {} or a file", names.join(", ")))
+            }
+        }
+    }
+
+    /// Reads one color per line, from the low end to the high end: a color as for `--color`
+    /// (`#rrggbb`, `orange`) or red, green and blue from 0 to 1 separated by spaces or
+    /// commas. Blank lines and lines starting with `//` are skipped.
+    pub fn load(path: &Path) -> Result<Colormap, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        let stops = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with("//"))
+            .map(|(i, line)| parse_stop(line).ok_or_else(|| format!("{}:{}: invalid color '{line}'", path.display(), i + 1)))
+            .collect::<Result<Vec<_>, String>>()?;
+        Colormap::new(stops).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn stops(&self) -> &[[f32; 4]] {
+        &self.stops
+    }
+
+    /// The color at `t`, clamped to 0..=1. NaN maps to the low end.
+    pub fn sample(&self, t: f32) -> [f32; 4] {
+        let x = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) } * (self.stops.len() - 1) as f32;
+        let i = (x as usize).min(self.stops.len() - 2);
+        let (a, b, f) = (self.stops[i], self.stops[i + 1], x - i as f32);
+        [0, 1, 2, 3].map(|k| a[k] + (b[k] - a[k]) * f)
+    }
+
+    /// The color of `value` on a scale running from `min` to `max`.
+    pub fn sample_range(&self, value: f32, [min, max]: [f32; 2]) -> [f32; 4] {
+        let span = max - min;
+        self.sample(if span != 0.0 { (value - min) / span } else { 0.5 })
+    }
+}
+
+/// The smallest and largest finite values, or [0, 1] when there are none: the default scale
+/// of values colored through a colormap.
+pub fn extent(values: &[f32]) -> [f32; 2] {
+    let finite = values.iter().copied().filter(|v| v.is_finite());
+    let min = finite.clone().fold(f32::INFINITY, f32::min);
+    let max = finite.fold(f32::NEG_INFINITY, f32::max);
+    if min <= max { [min, max] } else { [0.0, 1.0] }
+}
+
+fn parse_stop(line: &str) -> Option<[f32; 4]> {
+    if let Some(c) = color::parse(line) {
+        return Some(c);
+    }
+    let channels: Vec<f32> =
+        line.split(|c: char| c == ',' || c.is_whitespace()).filter(|s| !s.is_empty()).map(|s| s.parse().ok()).collect::<Option<_>>()?;
+    match channels[..] {
+        [r, g, b] => Some([r, g, b, 1.0]),
+        [r, g, b, a] => Some([r, g, b, a]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampling_interpolates_between_stops() {
+        let map = Colormap::new(vec![[0.0, 0.0, 0.0, 1.0], [1.0, 0.5, 0.0, 1.0], [1.0, 1.0, 1.0, 1.0]]).unwrap();
+        assert_eq!(map.sample(0.0), [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(map.sample(0.25), [0.5, 0.25, 0.0, 1.0]);
+        assert_eq!(map.sample(1.0), [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(map.sample(7.0), map.sample(1.0));
+        assert_eq!(map.sample(f32::NAN), map.sample(0.0));
+        assert_eq!(map.sample_range(15.0, [10.0, 20.0]), map.sample(0.5));
+        assert_eq!(extent(&[2.0, f32::NAN, 4.0, f32::INFINITY, 3.0]), [2.0, 4.0]);
+        assert_eq!(extent(&[f32::NAN]), [0.0, 1.0]);
+    }
+
+    #[test]
+    fn builtins_and_files() {
+        assert_eq!(Colormap::parse("Viridis").unwrap(), Colormap::default());
+        assert!(Colormap::parse("nonexistent").unwrap_err().contains("viridis, magma"));
+        // Diverging maps are light in the middle
+        let coolwarm = Colormap::from(Builtin::Coolwarm);
+        assert!(color::luminance(coolwarm.sample(0.5)) > color::luminance(coolwarm.sample(0.0)));
+
+        let path = std::env::temp_dir().join(format!("colormap-{}.txt", std::process::id()));
+        std::fs::write(&path, "// low to high\n#000000\n\n0.5, 0.5 0.5\nwhite\n").unwrap();
+        let map = Colormap::load(&path).unwrap();
+        assert_eq!(map.stops().len(), 3);
+        assert_eq!(map.sample(0.5), [0.5, 0.5, 0.5, 1.0]);
+        std::fs::write(&path, "#000000\n0.5 0.5\n").unwrap();
+        assert!(Colormap::load(&path).unwrap_err().contains(":2:"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
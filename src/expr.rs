@@ -0,0 +1,391 @@
+// Arithmetic expressions over named variables, such as `sin(3t)` or `x^2 + y^2 - 0.25`.
+//
+// Expressions are parsed once into a tree and then evaluated many times, with variables
+// resolved to slots up front so that evaluation is a plain walk over the tree. Products may
+// be written by juxtaposition (`3t`, `2(x + 1)`, `x y`), and `^` binds tighter than unary
+// minus as in mathematics, so `-x^2` is `-(x^2)`.
+
+/// A parsed expression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr {
+    node: Node,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Number(f64),
+    /// Slot in the value list passed to `eval`
+    Var(usize),
+    Neg(Box<Node>),
+    Binary(Op, Box<Node>, Box<Node>),
+    Call(Func, Box<Node>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Func {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Exp,
+    Ln,
+    Log10,
+    Sqrt,
+    Abs,
+    Floor,
+    Ceil,
+    Sign,
+}
+
+const FUNCS: [(&str, Func); 18] = [
+    ("sin", Func::Sin),
+    ("cos", Func::Cos),
+    ("tan", Func::Tan),
+    ("asin", Func::Asin),
+    ("acos", Func::Acos),
+    ("atan", Func::Atan),
+    ("sinh", Func::Sinh),
+    ("cosh", Func::Cosh),
+    ("tanh", Func::Tanh),
+    ("exp", Func::Exp),
+    ("ln", Func::Ln),
+    ("log", Func::Ln),
+    ("log10", Func::Log10),
+    ("sqrt", Func::Sqrt),
+    ("abs", Func::Abs),
+    ("floor", Func::Floor),
+    ("ceil", Func::Ceil),
+    ("sign", Func::Sign),
+];
+
+impl Func {
+    fn apply(self, v: f64) -> f64 {
+        match self {
+            Func::Sin => v.sin(),
+            Func::Cos => v.cos(),
+            Func::Tan => v.tan(),
+            Func::Asin => v.asin(),
+            Func::Acos => v.acos(),
+            Func::Atan => v.atan(),
+            Func::Sinh => v.sinh(),
+            Func::Cosh => v.cosh(),
+            Func::Tanh => v.tanh(),
+            Func::Exp => v.exp(),
+            Func::Ln => v.ln(),
+            Func::Log10 => v.log10(),
+            Func::Sqrt => v.sqrt(),
+            Func::Abs => v.abs(),
+            Func::Floor => v.floor(),
+            Func::Ceil => v.ceil(),
+            Func::Sign => if v == 0.0 { 0.0 } else { v.signum() },
+        }
+    }
+}
+
+impl Expr {
+    /// Parses `s`, in which `vars` name the values later passed to `eval`, in order.
+    /// `pi` and `e` are constants unless named in `vars`.
+    pub fn parse(s: &str, vars: &[&str]) -> Result<Expr, String> {
+        let mut p = Parser { s: s.as_bytes(), i: 0, vars };
+        let node = p.sum()?;
+        p.end()?;
+        Ok(Expr { node })
+    }
+
+    /// Parses comma-separated expressions such as `(-y, x)`; the parentheses are optional.
+    pub fn parse_list(s: &str, vars: &[&str]) -> Result<Vec<Expr>, String> {
+        let mut p = Parser { s: s.as_bytes(), i: 0, vars };
+        // A leading parenthesis encloses the whole list only if it is closed at the very end
+        let wrapped = p.peek() == Some(b'(') && p.closing(p.i) == Some(s.trim_end().len() - 1);
+        if wrapped {
+            p.i += 1;
+        }
+        let mut list = vec![Expr { node: p.sum()? }];
+        while p.eat(b',') {
+            list.push(Expr { node: p.sum()? });
+        }
+        if wrapped && !p.eat(b')') {
+            return Err(p.error("expected ')'"));
+        }
+        p.end()?;
+        Ok(list)
+    }
+
+    /// The value with the variables set to `values`, in the order they were named when parsing.
+    pub fn eval(&self, values: &[f64]) -> f64 {
+        eval(&self.node, values)
+    }
+}
+
+fn eval(node: &Node, values: &[f64]) -> f64 {
+    match node {
+        Node::Number(v) => *v,
+        Node::Var(i) => values.get(*i).copied().unwrap_or(f64::NAN),
+        Node::Neg(a) => -eval(a, values),
+        Node::Binary(op, a, b) => {
+            let (a, b) = (eval(a, values), eval(b, values));
+            match op {
+                Op::Add => a + b,
+                Op::Sub => a - b,
+                Op::Mul => a * b,
+                Op::Div => a / b,
+                Op::Pow => a.powf(b),
+            }
+        }
+        Node::Call(f, a) => f.apply(eval(a, values)),
+    }
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+    i: usize,
+    vars: &'a [&'a str],
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> String {
+        let at = String::from_utf8_lossy(&self.s[self.i.min(self.s.len())..]);
+        if at.is_empty() { format!("{message} at end of expression") } else { format!("{message} at '{at}'") }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        while self.s.get(self.i).is_some_and(|c| c.is_ascii_whitespace()) {
+            self.i += 1;
+        }
+        self.s.get(self.i).copied()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.i += 1;
+        }
+        found
+    }
+
+    fn end(&mut self) -> Result<(), String> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error("unexpected input")),
+        }
+    }
+
+    // Index of the parenthesis closing the one at `open`
+    fn closing(&self, open: usize) -> Option<usize> {
+        let mut depth = 0;
+        for (i, &c) in self.s.iter().enumerate().skip(open) {
+            match c {
+                b'(' => depth += 1,
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    // sum = product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<Node, String> {
+        let mut node = self.product()?;
+        loop {
+            let op = match self.peek() {
+                Some(b'+') => Op::Add,
+                Some(b'-') => Op::Sub,
+                _ => return Ok(node),
+            };
+            self.i += 1;
+            node = Node::Binary(op, Box::new(node), Box::new(self.product()?));
+        }
+    }
+
+    // product = unary (('*' | '/')? unary)*, where a missing operator multiplies
+    fn product(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(b'*') => Op::Mul,
+                Some(b'/') => Op::Div,
+                // Juxtaposition: `3t`, `2(x + 1)`, `x y`, `2 sin(t)`
+                Some(c) if c == b'(' || c == b'.' || c.is_ascii_alphanumeric() => {
+                    node = Node::Binary(Op::Mul, Box::new(node), Box::new(self.unary()?));
+                    continue;
+                }
+                _ => return Ok(node),
+            };
+            self.i += 1;
+            node = Node::Binary(op, Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    // unary = ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.eat(b'-') {
+            return Ok(Node::Neg(Box::new(self.unary()?)));
+        }
+        if self.eat(b'+') {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    // power = atom ('^' unary)?, right-associative
+    fn power(&mut self) -> Result<Node, String> {
+        let base = self.atom()?;
+        if self.eat(b'^') {
+            return Ok(Node::Binary(Op::Pow, Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        match self.peek() {
+            Some(b'(') => {
+                self.i += 1;
+                let node = self.sum()?;
+                if !self.eat(b')') {
+                    return Err(self.error("expected ')'"));
+                }
+                Ok(node)
+            }
+            Some(c) if c.is_ascii_digit() || c == b'.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => self.name(),
+            _ => Err(self.error("expected a number, variable or '('")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Node, String> {
+        let start = self.i;
+        while self.s.get(self.i).is_some_and(|c| c.is_ascii_digit() || *c == b'.') {
+            self.i += 1;
+        }
+        // An exponent only if digits follow, so that `2e` stays 2 times e
+        if matches!(self.s.get(self.i), Some(b'e' | b'E')) {
+            let digits_at = self.i + 1 + usize::from(matches!(self.s.get(self.i + 1), Some(b'+' | b'-')));
+            if self.s.get(digits_at).is_some_and(u8::is_ascii_digit) {
+                self.i = digits_at;
+                while self.s.get(self.i).is_some_and(u8::is_ascii_digit) {
+                    self.i += 1;
+                }
+            }
+        }
+        let text = std::str::from_utf8(&self.s[start..self.i]).unwrap();
+        text.parse().map(Node::Number).map_err(|_| {
+            self.i = start;
+            self.error("invalid number")
+        })
+    }
+
+    fn name(&mut self) -> Result<Node, String> {
+        let start = self.i;
+        while self.s.get(self.i).is_some_and(|c| c.is_ascii_alphanumeric() || *c == b'_') {
+            self.i += 1;
+        }
+        let name = std::str::from_utf8(&self.s[start..self.i]).unwrap();
+        if let Some(slot) = self.vars.iter().position(|v| *v == name) {
+            return Ok(Node::Var(slot));
+        }
+        if let Some(&(_, func)) = FUNCS.iter().find(|(n, _)| *n == name) {
+            if self.peek() == Some(b'(') {
+                return Ok(Node::Call(func, Box::new(self.atom()?)));
+            }
+            // Parentheses are optional around a single factor: `sin t`, `sin 2^t`. A factor
+            // juxtaposed after it, as in `sin 3t` or `sin x cos x`, could belong to either side
+            let arg = self.unary()?;
+            if self.peek().is_some_and(|c| c == b'(' || c == b'.' || c.is_ascii_alphanumeric()) {
+                return Err(self.error(&format!("put the argument of '{name}' in parentheses")));
+            }
+            return Ok(Node::Call(func, Box::new(arg)));
+        }
+        match name {
+            "pi" => Ok(Node::Number(std::f64::consts::PI)),
+            "e" => Ok(Node::Number(std::f64::consts::E)),
+            _ => {
+                self.i = start;
+                let known = if self.vars.is_empty() { String::new() } else { format!("; variables are {}", self.vars.join(", ")) };
+                Err(self.error(&format!("unknown name '{name}'{known}")))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(s: &str, x: f64, y: f64) -> f64 {
+        Expr::parse(s, &["x", "y"]).unwrap().eval(&[x, y])
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-12
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(value("1 + 2 * 3", 0.0, 0.0), 7.0);
+        assert_eq!(value("(1 + 2) * 3", 0.0, 0.0), 9.0);
+        assert_eq!(value("2 ^ 3 ^ 2", 0.0, 0.0), 512.0);
+        assert_eq!(value("-x^2", 3.0, 0.0), -9.0);
+        assert_eq!(value("2^-1", 0.0, 0.0), 0.5);
+        assert_eq!(value("8 / 4 / 2", 0.0, 0.0), 1.0);
+        assert_eq!(value("x - y - 1", 5.0, 2.0), 2.0);
+    }
+
+    #[test]
+    fn implicit_products_and_functions() {
+        assert_eq!(value("3x", 2.0, 0.0), 6.0);
+        assert_eq!(value("2(x + 1)", 2.0, 0.0), 6.0);
+        assert_eq!(value("x y", 2.0, 5.0), 10.0);
+        assert!(close(value("sin(pi / 2) + cos 0", 0.0, 0.0), 2.0));
+        assert!(close(value("2 sin x", std::f64::consts::FRAC_PI_2, 0.0), 2.0));
+        assert!(close(value("sin x * cos x", 0.5, 0.0), 0.5f64.sin() * 0.5f64.cos()));
+        assert!(close(value("sin(x) cos(x)", 0.5, 0.0), 0.5f64.sin() * 0.5f64.cos()));
+        assert!(close(value("sin -x^2 + 1", 0.5, 0.0), 1.0 - 0.25f64.sin()));
+        assert!(close(value("sqrt(x^2 + y^2)", 3.0, 4.0), 5.0));
+        assert!(close(value("1.5e-1 + 2e", 0.0, 0.0), 0.15 + 2.0 * std::f64::consts::E));
+        assert!(close(value("log10(1000) + ln(e)", 0.0, 0.0), 4.0));
+    }
+
+    #[test]
+    fn lists() {
+        let field = Expr::parse_list("(-y, x)", &["x", "y"]).unwrap();
+        assert_eq!(field.len(), 2);
+        assert_eq!((field[0].eval(&[1.0, 2.0]), field[1].eval(&[1.0, 2.0])), (-2.0, 1.0));
+        assert_eq!(Expr::parse_list("cos(3t), sin(2t)", &["t"]).unwrap().len(), 2);
+        // The first parenthesis only groups `x + 1` here
+        let list = Expr::parse_list("(x + 1) * 2, y", &["x", "y"]).unwrap();
+        assert_eq!(list[0].eval(&[1.0, 0.0]), 4.0);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Expr::parse("", &["x"]).is_err());
+        assert!(Expr::parse("x +", &["x"]).is_err());
+        assert!(Expr::parse("(x", &["x"]).is_err());
+        assert!(Expr::parse("x)", &["x"]).is_err());
+        assert!(Expr::parse("z", &["x", "y"]).unwrap_err().contains("unknown name 'z'"));
+        // Unparenthesised arguments end at the first factor rather than guessing where they end
+        let e = Expr::parse("2 sin x cos x", &["x"]).unwrap_err();
+        assert_eq!(e, "put the argument of 'sin' in parentheses at 'cos x'");
+        assert!(Expr::parse("sin 3t", &["t"]).is_err());
+        assert!(Expr::parse_list("(x, y", &["x", "y"]).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+// Vector fields drawn as arrows on the axis grid.
+//
+// The field is sampled at every grid point inside the view, and each sample becomes one
+// instance of an arrow mesh pointing along +x: rotated to the field's direction, scaled by its
+// magnitude relative to the largest sampled one, and colored through a colormap.
+
+use crate::axis::{GRID_FULL_SPAN, TICK_SPACING};
+use crate::colormap::Colormap;
+use crate::expr::Expr;
+use crate::instance::ShapeInstance;
+use crate::scale::Scales;
+
+/// Half the length of the arrow mesh drawn for the strongest sample, in NDC Y units, so that
+/// it spans most of a grid cell.
+pub const ARROW_SIZE: f32 = 0.45 * TICK_SPACING;
+
+/// A 2D vector field of the axis coordinates `x` and `y`.
+#[derive(Clone, Debug)]
+pub struct VectorField {
+    components: [Expr; 2],
+    pub colormap: Colormap,
+}
+
+impl VectorField {
+    /// Parses a field such as `(-y, x)`: two expressions of `x` and `y`.
+    pub fn parse(s: &str) -> Result<VectorField, String> {
+        let list = Expr::parse_list(s, &["x", "y"]).map_err(|e| format!("vector field: {e}"))?;
+        let components: [Expr; 2] = list
+            .try_into()
+            .map_err(|list: Vec<Expr>| format!("vector field: expected 2 components, got {}", list.len()))?;
+        Ok(VectorField { components, colormap: Colormap::default() })
+    }
+
+    /// The field's value at `p`.
+    pub fn at(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        let values = [f64::from(x), f64::from(y)];
+        self.components.each_ref().map(|c| c.eval(&values) as f32)
+    }
+
+    /// One arrow instance per point of an even grid strictly inside the view, for an arrow mesh
+    /// pointing along +x. Field directions, which are in axis units, are carried onto the screen
+    /// through `scales` and `aspect` (width / height). Points where the field is zero or
+    /// undefined get no arrow.
+    pub fn instances(&self, scales: &Scales, aspect: f32) -> Vec<ShapeInstance> {
+        let n = (GRID_FULL_SPAN / TICK_SPACING).round() as i32;
+        let samples: Vec<([f32; 2], [f32; 2], f32)> = (1 - n..n)
+            .flat_map(|j| (1 - n..n).map(move |i| [i as f32 * TICK_SPACING, j as f32 * TICK_SPACING]))
+            .filter_map(|position| {
+                let p = scales.from_ndc(position);
+                let v = self.at(p);
+                let magnitude = v[0].hypot(v[1]);
+                let direction = scales.direction_to_ndc(p, v);
+                (magnitude.is_finite() && magnitude > 0.0).then_some((position, direction, magnitude))
+            })
+            .collect();
+        let max = samples.iter().map(|s| s.2).fold(0.0, f32::max);
+        samples
+            .into_iter()
+            .map(|(position, [vx, vy], magnitude)| ShapeInstance {
+                position,
+                scale: magnitude / max,
+                rotation: vy.atan2(vx * aspect),
+                color: self.colormap.sample(magnitude / max),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scale::{Scale, ScaleKind};
+
+    #[test]
+    fn rotation_field() {
+        let field = VectorField::parse("(-y, x)").unwrap();
+        assert_eq!(field.at([0.3, 0.2]), [-0.2, 0.3]);
+        let instances = field.instances(&Scales::default(), 1.0);
+        // Every interior grid point but the origin, where the field vanishes
+        let n = (GRID_FULL_SPAN / TICK_SPACING).round() as usize;
+        assert_eq!(instances.len(), (2 * n - 1).pow(2) - 1);
+        let longest = instances.iter().map(|i| i.scale).fold(0.0, f32::max);
+        assert_eq!(longest, 1.0);
+        // Counter-clockwise around the origin: at (x, 0) the arrow points up
+        let right = instances.iter().find(|i| i.position[1] == 0.0 && i.position[0] > 0.0).unwrap();
+        assert!((right.rotation - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn aspect_stretches_directions() {
+        let field = VectorField::parse("1, 1").unwrap();
+        let instances = field.instances(&Scales::default(), 2.0);
+        assert!(instances.iter().all(|i| (i.rotation - 0.5f32.atan()).abs() < 1e-6 && i.scale == 1.0));
+    }
+
+    #[test]
+    fn log_axes_bend_directions() {
+        // Along (x, 2y) log y grows twice as fast as log x everywhere: a slope of 2 on log axes
+        let field = VectorField::parse("x, 2y").unwrap();
+        let log = Scale::new(ScaleKind::Log, None).unwrap();
+        let instances = field.instances(&Scales { x: log, y: log }, 1.0);
+        assert!(instances.iter().all(|i| (i.rotation - 2f32.atan()).abs() < 1e-4));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(VectorField::parse("(x)").unwrap_err().contains("expected 2 components"));
+        assert!(VectorField::parse("(x, y, 1)").is_err());
+        assert!(VectorField::parse("(x, z)").is_err());
+        // Nothing to draw where the field vanishes or is undefined everywhere
+        for field in ["(0, 0)", "(sqrt(-1), 0)"] {
+            assert!(VectorField::parse(field).unwrap().instances(&Scales::default(), 1.0).is_empty());
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use std::f32::consts::PI;
+
+use crate::color;
+
+/// Maximum number of color stops; must match `MAX_STOPS` in `fill.wgsl`.
+pub const MAX_STOPS: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientKind {
+    Linear,
+    Radial,
+    Conic,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorStop {
+    pub color: [f32; 4],
+    /// Position along the gradient in 0..=1
+    pub offset: f32,
+}
+
+/// A gradient evaluated in shape-local coordinates normalised to the shape bounds,
+/// where (-1, -1) is the bottom-left corner and (1, 1) the top-right.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    /// Direction of a linear gradient or start of a conic one, in radians clockwise from up
+    pub angle: f32,
+    /// Centre of radial and conic gradients
+    pub center: [f32; 2],
+    pub stops: Vec<ColorStop>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fill {
+    None,
+    Solid([f32; 4]),
+    Gradient(Gradient),
+}
+
+/// Uniform block consumed by `fill.wgsl`. Layout must match `FillParams` there.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FillParams {
+    /// 0 = solid `u_color`, 1 = linear, 2 = radial, 3 = conic
+    pub kind: u32,
+    pub stop_count: u32,
+    pub angle: f32,
+    /// 0 = untextured, 1 = texture replaces the fill, 2 = texture tinted by the fill
+    pub texture: u32,
+    pub center: [f32; 2],
+    /// Half extents of the shape, used to normalise local coordinates
+    pub half_size: [f32; 2],
+    /// Multiplier applied to the vertex UVs before sampling
+    pub uv_scale: [f32; 2],
+    pub _pad: [f32; 2],
+    /// Stop offsets packed four per vec4
+    pub offsets: [[f32; 4]; MAX_STOPS / 4],
+    pub colors: [[f32; 4]; MAX_STOPS],
+}
+
+impl Fill {
+    /// GPU parameters for a shape whose local bounds are `±half_size`.
+    pub fn params(&self, half_size: [f32; 2]) -> FillParams {
+        let mut params = FillParams {
+            kind: 0,
+            stop_count: 0,
+            angle: 0.0,
+            texture: 0,
+            center: [0.0, 0.0],
+            half_size,
+            uv_scale: [1.0, 1.0],
+            _pad: [0.0; 2],
+            offsets: [[0.0; 4]; MAX_STOPS / 4],
+            colors: [[0.0; 4]; MAX_STOPS],
+        };
+        if let Fill::Gradient(g) = self {
+            params.kind = match g.kind {
+                GradientKind::Linear => 1,
+                GradientKind::Radial => 2,
+                GradientKind::Conic => 3,
+            };
+            params.stop_count = g.stops.len() as u32;
+            params.angle = g.angle;
+            params.center = g.center;
+            for (i, stop) in g.stops.iter().enumerate() {
+                params.offsets[i / 4][i % 4] = stop.offset;
+                params.colors[i] = stop.color;
+            }
+        }
+        params
+    }
+
+    /// Color written to the flat `u_color` uniform: transparent when there is no fill.
+    pub fn solid_color(&self) -> [f32; 4] {
+        match self {
+            Fill::Solid(c) => *c,
+            Fill::None | Fill::Gradient(_) => [0.0; 4],
+        }
+    }
+
+    /// Whether the fill covers everything behind it, so it can be drawn with depth writes.
+    pub fn is_opaque(&self) -> bool {
+        match self {
+            Fill::None => false,
+            Fill::Solid(c) => c[3] >= 1.0,
+            Fill::Gradient(g) => g.stops.iter().all(|s| s.color[3] >= 1.0),
+        }
+    }
+}
+
+/// Parses `none`, a color, or a CSS-like gradient:
+///
+/// - `linear([ANGLE,] STOPS...)` — angle defaults to `180deg` (top to bottom)
+/// - `radial([at X Y,] STOPS...)`
+/// - `conic([from ANGLE] [at X Y,] STOPS...)`
+///
+/// A stop is `COLOR [POSITION]` where the position is a percentage or a 0..1 fraction.
+/// Stops without a position are spread evenly between their neighbours.
+pub fn parse_fill(s: &str) -> Result<Fill, String> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("none") {
+        return Ok(Fill::None);
+    }
+    let Some((name, rest)) = s.split_once('(') else {
+        return color::parse(s)
+            .map(Fill::Solid)
+            .ok_or_else(|| format!("unknown color '{s}'"));
+    };
+    let args = rest
+        .trim_end()
+        .strip_suffix(')')
+        .ok_or_else(|| format!("missing ')' in '{s}'"))?;
+    let mut args: Vec<&str> = args.split(',').map(str::trim).collect();
+
+    let mut gradient = Gradient {
+        kind: match name.trim().to_lowercase().as_str() {
+            "linear" => GradientKind::Linear,
+            "radial" => GradientKind::Radial,
+            "conic" => GradientKind::Conic,
+            other => return Err(format!("unknown gradient '{other}', expected linear, radial or conic")),
+        },
+        angle: PI,
+        center: [0.0, 0.0],
+        stops: Vec::new(),
+    };
+    if gradient.kind == GradientKind::Conic {
+        gradient.angle = 0.0;
+    }
+
+    // Optional leading geometry argument
+    let first = args.first().copied().unwrap_or_default();
+    match gradient.kind {
+        GradientKind::Linear => {
+            if let Some(angle) = parse_angle(first) {
+                gradient.angle = angle?;
+                args.remove(0);
+            }
+        }
+        GradientKind::Radial | GradientKind::Conic => {
+            if first.starts_with("at ") || first.starts_with("from ") {
+                parse_center_and_from(first, &mut gradient)?;
+                args.remove(0);
+            }
+        }
+    }
+
+    gradient.stops = parse_stops(&args)?;
+    Ok(Fill::Gradient(gradient))
+}
+
+// `Some(Err)` for a malformed angle, `None` if the token is not an angle at all
+fn parse_angle(s: &str) -> Option<Result<f32, String>> {
+    let (value, to_radians) = if let Some(v) = s.strip_suffix("deg") {
+        (v, PI / 180.0)
+    } else if let Some(v) = s.strip_suffix("rad") {
+        (v, 1.0)
+    } else if let Some(v) = s.strip_suffix("turn") {
+        (v, 2.0 * PI)
+    } else {
+        return None;
+    };
+    Some(
+        value
+            .trim()
+            .parse::<f32>()
+            .map(|v| v * to_radians)
+            .map_err(|_| format!("invalid angle '{s}'")),
+    )
+}
+
+// `from ANGLE`, `at X Y` or `from ANGLE at X Y`
+fn parse_center_and_from(s: &str, gradient: &mut Gradient) -> Result<(), String> {
+    let mut words = s.split_whitespace();
+    while let Some(word) = words.next() {
+        match word {
+            "from" if gradient.kind == GradientKind::Conic => {
+                let angle = words.next().ok_or("expected an angle after 'from'")?;
+                gradient.angle = parse_angle(angle)
+                    .unwrap_or_else(|| Err(format!("invalid angle '{angle}'")))?;
+            }
+            "at" => {
+                let mut coord = || -> Result<f32, String> {
+                    let w = words.next().ok_or("expected 'at X Y'")?;
+                    w.parse().map_err(|_| format!("invalid coordinate '{w}'"))
+                };
+                gradient.center = [coord()?, coord()?];
+            }
+            other => return Err(format!("unexpected '{other}' in '{s}'")),
+        }
+    }
+    Ok(())
+}
+
+fn parse_stops(args: &[&str]) -> Result<Vec<ColorStop>, String> {
+    if args.len() < 2 {
+        return Err("a gradient needs at least two color stops".into());
+    }
+    if args.len() > MAX_STOPS {
+        return Err(format!("a gradient supports at most {MAX_STOPS} color stops"));
+    }
+
+    let mut colors = Vec::with_capacity(args.len());
+    let mut offsets: Vec<Option<f32>> = Vec::with_capacity(args.len());
+    for arg in args {
+        let mut parts = arg.split_whitespace();
+        let name = parts.next().ok_or("empty color stop")?;
+        let c = color::parse(name).ok_or_else(|| format!("unknown color '{name}'"))?;
+        let offset = parts.next().map(parse_offset).transpose()?;
+        if let Some(extra) = parts.next() {
+            return Err(format!("unexpected '{extra}' in color stop '{arg}'"));
+        }
+        colors.push(c);
+        offsets.push(offset);
+    }
+
+    // CSS rules: the ends default to 0 and 1, positions never go backwards,
+    // and runs of unpositioned stops are spaced evenly between positioned ones.
+    let last = offsets.len() - 1;
+    offsets[0].get_or_insert(0.0);
+    offsets[last].get_or_insert(1.0);
+    let mut max_so_far = 0.0_f32;
+    for o in offsets.iter_mut().flatten() {
+        max_so_far = max_so_far.max(*o);
+        *o = max_so_far;
+    }
+    let mut i = 1;
+    while i < last {
+        if offsets[i].is_some() {
+            i += 1;
+            continue;
+        }
+        let start = i - 1;
+        let end = (i..=last).find(|&j| offsets[j].is_some()).unwrap();
+        let (a, b) = (offsets[start].unwrap(), offsets[end].unwrap());
+        for (j, o) in offsets.iter_mut().enumerate().take(end).skip(i) {
+            *o = Some(a + (b - a) * (j - start) as f32 / (end - start) as f32);
+        }
+        i = end;
+    }
+
+    Ok(colors
+        .into_iter()
+        .zip(offsets)
+        .map(|(color, offset)| ColorStop { color, offset: offset.unwrap() })
+        .collect())
+}
+
+fn parse_offset(s: &str) -> Result<f32, String> {
+    let value = match s.strip_suffix('%') {
+        Some(pct) => pct.parse::<f32>().map(|v| v / 100.0),
+        None => s.parse::<f32>(),
+    };
+    value.map_err(|_| format!("invalid stop position '{s}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+    const BLUE: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+    const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+    fn gradient(s: &str) -> Gradient {
+        match parse_fill(s).unwrap() {
+            Fill::Gradient(g) => g,
+            other => panic!("expected a gradient, got {other:?}"),
+        }
+    }
+
+    fn offsets(g: &Gradient) -> Vec<f32> {
+        g.stops.iter().map(|s| s.offset).collect()
+    }
+
+    #[test]
+    fn none_and_solid_colors() {
+        assert_eq!(parse_fill("none").unwrap(), Fill::None);
+        assert_eq!(parse_fill("Red").unwrap(), Fill::Solid(RED));
+        assert_eq!(parse_fill("#00f").unwrap(), Fill::Solid(BLUE));
+        assert!(parse_fill("notacolor").is_err());
+    }
+
+    #[test]
+    fn linear_with_angle_and_mixed_stops() {
+        let g = gradient("linear(0deg, red, blue 70%, white)");
+        assert_eq!(g.kind, GradientKind::Linear);
+        assert_eq!(g.angle, 0.0);
+        assert_eq!(g.stops.iter().map(|s| s.color).collect::<Vec<_>>(), vec![RED, BLUE, WHITE]);
+        assert_eq!(offsets(&g), vec![0.0, 0.7, 1.0]);
+    }
+
+    #[test]
+    fn linear_defaults_to_top_to_bottom() {
+        let g = gradient("linear(red, blue)");
+        assert_eq!(g.angle, PI);
+        assert_eq!(offsets(&g), vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn unpositioned_stops_are_spread_evenly() {
+        let g = gradient("linear(90deg, red, white, white, blue 0.9)");
+        let o = offsets(&g);
+        assert!((o[1] - 0.3).abs() < 1e-6 && (o[2] - 0.6).abs() < 1e-6, "{o:?}");
+        assert_eq!(o[3], 0.9);
+    }
+
+    #[test]
+    fn stop_positions_never_decrease() {
+        let g = gradient("linear(red 50%, blue 20%)");
+        assert_eq!(offsets(&g), vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn radial_and_conic_geometry() {
+        let g = gradient("radial(at 0.5 -0.25, red, blue)");
+        assert_eq!(g.kind, GradientKind::Radial);
+        assert_eq!(g.center, [0.5, -0.25]);
+
+        let g = gradient("conic(from 0.25turn at 0 1, red, blue, red)");
+        assert_eq!(g.kind, GradientKind::Conic);
+        assert!((g.angle - PI / 2.0).abs() < 1e-6);
+        assert_eq!(g.center, [0.0, 1.0]);
+    }
+
+    #[test]
+    fn malformed_gradients_are_rejected() {
+        assert!(parse_fill("linear(red)").is_err());
+        assert!(parse_fill("linear(red, blue").is_err());
+        assert!(parse_fill("spiral(red, blue)").is_err());
+        assert!(parse_fill("linear(xdeg, red, blue)").is_err());
+        assert!(parse_fill("linear(red, blue 5 6)").is_err());
+        assert!(parse_fill("radial(at 1, red, blue)").is_err());
+        let many = vec!["red"; MAX_STOPS + 1].join(", ");
+        assert!(parse_fill(&format!("linear({many})")).is_err());
+    }
+
+    #[test]
+    fn params_pack_offsets_four_per_vec4() {
+        let fill = parse_fill("linear(red, blue, red, blue, white)").unwrap();
+        let p = fill.params([0.5, 0.5]);
+        assert_eq!(p.kind, 1);
+        assert_eq!(p.stop_count, 5);
+        assert_eq!(p.offsets[0], [0.0, 0.25, 0.5, 0.75]);
+        assert_eq!(p.offsets[1][0], 1.0);
+        assert_eq!(p.colors[4], WHITE);
+    }
+}
@@ -0,0 +1,73 @@
+// Shared fill evaluation, prepended to the shape shaders (see `ShapeVariant::files` and `ShaderSources::compose`).
+// Gradients are evaluated in shape-local coordinates normalised to the shape bounds,
+// so they move and scale with the shape.
+
+const MAX_STOPS: u32 = 16u;
+const PI: f32 = 3.14159265;
+
+struct FillParams {
+    kind: u32,        // 0 = solid u_color, 1 = linear, 2 = radial, 3 = conic
+    stop_count: u32,
+    angle: f32,       // radians clockwise from up
+    texture: u32,     // 0 = none, 1 = texture replaces the fill, 2 = texture tinted by the fill
+    center: vec2<f32>,
+    half_size: vec2<f32>,
+    uv_scale: vec2<f32>,
+    offsets: array<vec4<f32>, 4>, // MAX_STOPS offsets packed four per vec4
+    colors: array<vec4<f32>, 16>,
+}
+
+@group(0) @binding(0) var<uniform> u_color: vec4<f32>;
+@group(0) @binding(4) var<uniform> u_fill: FillParams;
+@group(1) @binding(0) var t_fill: texture_2d<f32>;
+@group(1) @binding(1) var s_fill: sampler;
+
+fn stop_offset(i: u32) -> f32 {
+    return u_fill.offsets[i / 4u][i % 4u];
+}
+
+fn gradient_at(t: f32) -> vec4<f32> {
+    var color = u_fill.colors[0];
+    for (var i = 1u; i < min(u_fill.stop_count, MAX_STOPS); i++) {
+        let a = stop_offset(i - 1u);
+        let b = stop_offset(i);
+        let k = select(step(b, t), clamp((t - a) / (b - a), 0.0, 1.0), b > a);
+        color = mix(color, u_fill.colors[i], k);
+    }
+    return color;
+}
+
+// `local` is the shape-local position, `uv` spans 0..1 across the shape bounds
+fn fill_color(local: vec2<f32>, uv: vec2<f32>) -> vec4<f32> {
+    // Sample unconditionally: textureSample must stay in uniform control flow
+    let tex = textureSample(t_fill, s_fill, uv * u_fill.uv_scale);
+    let base = base_color(local);
+    switch u_fill.texture {
+        case 1u: { return tex; }
+        case 2u: { return tex * base; }
+        default: { return base; }
+    }
+}
+
+fn base_color(local: vec2<f32>) -> vec4<f32> {
+    let q = local / u_fill.half_size;
+    let dir = vec2<f32>(sin(u_fill.angle), cos(u_fill.angle));
+    switch u_fill.kind {
+        case 1u: {
+            // CSS-style: the gradient line spans the bounds along `dir`
+            let extent = abs(dir.x) + abs(dir.y);
+            return gradient_at(0.5 + 0.5 * dot(q, dir) / extent);
+        }
+        case 2u: {
+            return gradient_at(length(q - u_fill.center));
+        }
+        case 3u: {
+            let p = q - u_fill.center;
+            let a = atan2(p.x, p.y) - u_fill.angle;
+            return gradient_at(fract(a / (2.0 * PI)));
+        }
+        default: {
+            return u_color;
+        }
+    }
+}
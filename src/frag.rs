@@ -0,0 +1,71 @@
+use std::path::{Path, PathBuf};
+
+use naga::valid::ModuleInfo;
+
+use crate::shaders::{self, ShaderError, ShaderFile, ShaderSources};
+
+/// Uniform block consumed by `frag_wrapper.wgsl`. Layout must match `Globals` there.
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Globals {
+    pub color: [f32; 4],
+    pub mouse: [f32; 2],
+    pub resolution: [f32; 2],
+    pub time: f32,
+    pub frame: u32,
+    pub _pad: [u32; 2],
+}
+
+/// A user fragment file linked into the shape pipeline with `--frag`.
+pub struct UserFrag {
+    pub path: PathBuf,
+    source: String,
+}
+
+impl UserFrag {
+    pub fn load(path: &Path) -> Result<UserFrag, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        Ok(UserFrag { path: path.to_owned(), source })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn file_name(&self) -> Option<&str> {
+        self.path.file_name()?.to_str()
+    }
+
+    /// The user's code followed by the wrapper and the shape shader for the render mode.
+    /// Putting the user's code first keeps its line numbers intact in naga diagnostics.
+    pub fn compose(&self, sources: &ShaderSources, shape: &ShaderFile) -> Result<String, String> {
+        let rest = sources.compose(&[&shaders::FRAG_WRAPPER, shape])?;
+        Ok(format!("{}\n{}", self.source, rest))
+    }
+
+    /// Validates the composed module, explaining errors that land in the generated code.
+    pub fn check(&self, composed: &str) -> Result<(naga::Module, ModuleInfo), ShaderError> {
+        let path = self.path.display().to_string();
+        let err = match shaders::validate(composed, &path) {
+            Ok(validated) => return Ok(validated),
+            Err(err) => err,
+        };
+        // Lines taken by the user's file plus the joining newline
+        let user_lines = self.source.matches('\n').count() as u32 + 1;
+        match err.location {
+            Some(loc) if loc.line_number > user_lines => Err(ShaderError {
+                rendered: format!(
+                    "{}\nnote: line {} is not in {path} but line {} of the generated wrapper appended to it; \
+                     check that {path} defines\n    fn shade(uv: vec2f, time: f32, resolution: vec2f) -> vec4f",
+                    err.rendered,
+                    loc.line_number,
+                    loc.line_number - user_lines,
+                ),
+                // Nothing in the user's file to point at
+                location: None,
+            }),
+            _ => Err(err),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+// Wrapper linked after a user `--frag` file. The user's code comes first so its line
+// numbers are unchanged; it must define
+//
+//     fn shade(uv: vec2f, time: f32, resolution: vec2f) -> vec4f
+//
+// and may also read `u_globals` directly. `fill_color` is the hook the shape shaders call,
+// so the user's color replaces the regular fill inside the shape (and under SDF coverage).
+
+struct Globals {
+    color: vec4<f32>,      // the shape's --color
+    mouse: vec2<f32>,      // cursor position in pixels, origin top-left
+    resolution: vec2<f32>, // surface size in pixels
+    time: f32,             // seconds since start
+    frame: u32,            // frames rendered since start
+}
+
+@group(2) @binding(0) var<uniform> u_globals: Globals;
+
+fn fill_color(local: vec2<f32>, uv: vec2<f32>) -> vec4<f32> {
+    return shade(uv, u_globals.time, u_globals.resolution);
+}
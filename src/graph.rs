@@ -0,0 +1,680 @@
+use std::fmt;
+use std::ops::Range;
+
+use wgpu::{util::DeviceExt, BindGroup, Buffer, BufferUsages, Device, PipelineLayout, Queue, RenderPass, RenderPipeline};
+
+use crate::fill::Fill;
+use crate::instance::ShapeInstance;
+use crate::pipeline::{self, Depth};
+use crate::shaders::ShaderSources;
+use crate::shape::ShapeVertex;
+use crate::state::{SHAPE_BIND_GROUP, ShapeVariant};
+use crate::texture;
+
+const WHITE: [f32; 4] = [1.0; 4];
+const VERTEX_SIZE: u64 = size_of::<ShapeVertex>() as u64;
+const INSTANCE_SIZE: u64 = size_of::<ShapeInstance>() as u64;
+
+/// Handle to a node in a `SceneGraph`. It stays invalid once the node is removed, even
+/// after its slot is reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: u32,
+    generation: u32,
+}
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "node {}v{}", self.index, self.generation)
+    }
+}
+
+/// Scale, then rotate, then translate. Both axes are in NDC Y units (the units of `size`),
+/// so rotations stay circular at any window aspect ratio.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: [f32; 2],
+    /// Radians, counter-clockwise
+    pub rotation: f32,
+    pub scale: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform { translation: [0.0, 0.0], rotation: 0.0, scale: 1.0 };
+
+    pub fn apply(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        let (s, c) = self.rotation.sin_cos();
+        let [tx, ty] = self.translation;
+        [tx + self.scale * (c * x - s * y), ty + self.scale * (s * x + c * y)]
+    }
+
+    /// `child`, given relative to this transform, relative to this transform's parent instead.
+    pub fn then(&self, child: &Transform) -> Transform {
+        Transform {
+            translation: self.apply(child.translation),
+            rotation: self.rotation + child.rotation,
+            scale: self.scale * child.scale,
+        }
+    }
+}
+
+struct Node {
+    generation: u32,
+    live: bool,
+    parent: Option<u32>,
+    children: Vec<u32>,
+    local: Transform,
+    /// Multiplies the color of every descendant
+    color: [f32; 4],
+    /// Layer, added to the parent's
+    z: f32,
+    world: Transform,
+    world_color: [f32; 4],
+    world_z: f32,
+    /// Vertices in `SceneGraph::vertices`; groups have none
+    mesh: Option<Range<u32>>,
+    dirty: bool,
+}
+
+/// A retained tree of shapes, drawn on top of the main shape unless given a lower `z`.
+/// Changes are recorded so that only the affected vertices and transforms are uploaded on
+/// the next frame.
+#[derive(Default)]
+pub struct SceneGraph {
+    nodes: Vec<Node>,
+    free: Vec<u32>,
+    roots: Vec<u32>,
+    /// Meshes of all shapes packed back to back
+    vertices: Vec<ShapeVertex>,
+    /// Vertices left behind by removed shapes, reclaimed by compaction
+    garbage: usize,
+    /// Nodes whose world transform or color must be recomputed along with their subtree
+    dirty: Vec<u32>,
+    /// Slots whose world transform or color changed since the last upload
+    changed: Vec<u32>,
+    vertices_changed: Option<Range<usize>>,
+    /// Meshes of shapes removed since the last upload, until compaction reuses the space
+    removed: Vec<Range<u32>>,
+    /// Shapes in drawing order (parents before children), rebuilt when the tree changes
+    draw_list: Vec<(u32, Range<u32>)>,
+    structure_changed: bool,
+}
+
+impl SceneGraph {
+    pub fn new() -> SceneGraph {
+        SceneGraph::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Adds an empty node whose transform and color apply to its children.
+    pub fn add_group(&mut self, parent: Option<NodeId>, transform: Transform) -> Result<NodeId, String> {
+        self.insert(parent, transform, WHITE, None)
+    }
+
+    /// Adds a shape: a TriangleList in local coordinates (e.g. `ShapeConfig::vertices`) drawn
+    /// in `color`. A root when `parent` is `None`.
+    pub fn add_shape(
+        &mut self,
+        parent: Option<NodeId>,
+        vertices: &[ShapeVertex],
+        color: [f32; 4],
+        transform: Transform,
+    ) -> Result<NodeId, String> {
+        let start = self.vertices.len();
+        let mesh = start as u32..(start + vertices.len()) as u32;
+        let id = self.insert(parent, transform, color, Some(mesh))?;
+        self.vertices.extend_from_slice(vertices);
+        self.mark_vertices(start..self.vertices.len());
+        Ok(id)
+    }
+
+    /// Removes `id` and everything below it.
+    pub fn remove(&mut self, id: NodeId) -> Result<(), String> {
+        let index = self.slot(id)?;
+        match self.nodes[index as usize].parent {
+            Some(parent) => self.nodes[parent as usize].children.retain(|&c| c != index),
+            None => self.roots.retain(|&r| r != index),
+        }
+        self.free_subtree(index);
+        self.structure_changed = true;
+        if self.garbage > self.vertices.len() / 2 {
+            self.compact();
+        }
+        Ok(())
+    }
+
+    pub fn set_transform(&mut self, id: NodeId, transform: Transform) -> Result<(), String> {
+        let index = self.slot(id)?;
+        self.nodes[index as usize].local = transform;
+        self.mark_dirty(index);
+        Ok(())
+    }
+
+    pub fn set_color(&mut self, id: NodeId, color: [f32; 4]) -> Result<(), String> {
+        let index = self.slot(id)?;
+        self.nodes[index as usize].color = color;
+        self.mark_dirty(index);
+        Ok(())
+    }
+
+    /// Moves `id` and its descendants `z` layers above its parent (see `ShapeConfig::z`).
+    pub fn set_z(&mut self, id: NodeId, z: f32) -> Result<(), String> {
+        let index = self.slot(id)?;
+        self.nodes[index as usize].z = z;
+        self.mark_dirty(index);
+        Ok(())
+    }
+
+    pub fn transform(&self, id: NodeId) -> Result<Transform, String> {
+        self.slot(id).map(|index| self.nodes[index as usize].local)
+    }
+
+    fn slot(&self, id: NodeId) -> Result<u32, String> {
+        match self.nodes.get(id.index as usize) {
+            Some(node) if node.live && node.generation == id.generation => Ok(id.index),
+            _ => Err(format!("{id} does not exist (it may have been removed)")),
+        }
+    }
+
+    fn insert(
+        &mut self,
+        parent: Option<NodeId>,
+        local: Transform,
+        color: [f32; 4],
+        mesh: Option<Range<u32>>,
+    ) -> Result<NodeId, String> {
+        let parent = parent.map(|p| self.slot(p)).transpose()?;
+        let node = Node {
+            generation: 0,
+            live: true,
+            parent,
+            children: Vec::new(),
+            local,
+            color,
+            z: 0.0,
+            world: Transform::IDENTITY,
+            world_color: WHITE,
+            world_z: 0.0,
+            mesh,
+            dirty: false,
+        };
+        let index = match self.free.pop() {
+            Some(index) => {
+                let generation = self.nodes[index as usize].generation;
+                self.nodes[index as usize] = Node { generation, ..node };
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() as u32 - 1
+            }
+        };
+        match parent {
+            Some(parent) => self.nodes[parent as usize].children.push(index),
+            None => self.roots.push(index),
+        }
+        self.mark_dirty(index);
+        self.structure_changed = true;
+        Ok(NodeId { index, generation: self.nodes[index as usize].generation })
+    }
+
+    fn free_subtree(&mut self, index: u32) {
+        let node = &mut self.nodes[index as usize];
+        node.live = false;
+        node.generation += 1;
+        if let Some(mesh) = node.mesh.take() {
+            self.garbage += mesh.len();
+            self.removed.push(mesh);
+        }
+        for child in std::mem::take(&mut node.children) {
+            self.free_subtree(child);
+        }
+        self.free.push(index);
+    }
+
+    /// Repacks the meshes of live shapes, dropping the space left by removed ones.
+    fn compact(&mut self) {
+        let mut vertices = Vec::with_capacity(self.vertices.len() - self.garbage);
+        for node in self.nodes.iter_mut().filter(|n| n.live) {
+            if let Some(mesh) = &mut node.mesh {
+                let start = vertices.len() as u32;
+                vertices.extend_from_slice(&self.vertices[mesh.start as usize..mesh.end as usize]);
+                *mesh = start..vertices.len() as u32;
+            }
+        }
+        self.vertices = vertices;
+        self.garbage = 0;
+        self.removed.clear();
+        self.vertices_changed = Some(0..self.vertices.len());
+        self.structure_changed = true;
+    }
+
+    fn mark_dirty(&mut self, index: u32) {
+        let node = &mut self.nodes[index as usize];
+        if !node.dirty {
+            node.dirty = true;
+            self.dirty.push(index);
+        }
+    }
+
+    fn mark_vertices(&mut self, range: Range<usize>) {
+        self.vertices_changed = Some(match self.vertices_changed.take() {
+            Some(r) => r.start.min(range.start)..r.end.max(range.end),
+            None => range,
+        });
+    }
+
+    /// Recomputes world transforms of dirty subtrees and returns what must be uploaded.
+    pub(crate) fn take_changes(&mut self) -> Changes {
+        for index in std::mem::take(&mut self.dirty) {
+            let node = &self.nodes[index as usize];
+            // Already updated as part of an ancestor's subtree, or removed since
+            if node.dirty && node.live {
+                self.update_world(index);
+            }
+        }
+        if std::mem::take(&mut self.structure_changed) {
+            self.draw_list.clear();
+            for i in 0..self.roots.len() {
+                self.collect_draws(self.roots[i]);
+            }
+        }
+        let mut slots = std::mem::take(&mut self.changed);
+        slots.sort_unstable();
+        slots.dedup();
+        Changes {
+            slots,
+            vertices: self.vertices_changed.take(),
+            removed: std::mem::take(&mut self.removed),
+        }
+    }
+
+    fn update_world(&mut self, index: u32) {
+        let (parent_world, parent_color, parent_z) = match self.nodes[index as usize].parent {
+            Some(p) => {
+                let parent = &self.nodes[p as usize];
+                (parent.world, parent.world_color, parent.world_z)
+            }
+            None => (Transform::IDENTITY, WHITE, 0.0),
+        };
+        let node = &mut self.nodes[index as usize];
+        node.world = parent_world.then(&node.local);
+        node.world_color = std::array::from_fn(|i| parent_color[i] * node.color[i]);
+        node.world_z = parent_z + node.z;
+        node.dirty = false;
+        self.changed.push(index);
+        for i in 0..self.nodes[index as usize].children.len() {
+            self.update_world(self.nodes[index as usize].children[i]);
+        }
+    }
+
+    fn collect_draws(&mut self, index: u32) {
+        if let Some(mesh) = &self.nodes[index as usize].mesh {
+            self.draw_list.push((index, mesh.clone()));
+        }
+        for i in 0..self.nodes[index as usize].children.len() {
+            self.collect_draws(self.nodes[index as usize].children[i]);
+        }
+    }
+
+    /// Number of node slots, live or free; slot indices are below this.
+    pub(crate) fn slot_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Packed meshes of all shapes, including space left by removed ones.
+    pub(crate) fn vertices(&self) -> &[ShapeVertex] {
+        &self.vertices
+    }
+
+    /// Live shapes as (slot, mesh) in slot order.
+    pub(crate) fn shapes(&self) -> impl Iterator<Item = (u32, Range<u32>)> + '_ {
+        let live = self.nodes.iter().enumerate().filter(|(_, n)| n.live);
+        live.filter_map(|(i, n)| n.mesh.clone().map(|mesh| (i as u32, mesh)))
+    }
+
+    /// World transform and color of slot `index` as of the last `take_changes`.
+    pub(crate) fn world(&self, index: u32) -> (Transform, [f32; 4]) {
+        let node = &self.nodes[index as usize];
+        (node.world, node.world_color)
+    }
+
+    /// Whether slot `index` is drawn fully opaque, as of the last `take_changes`; other shapes
+    /// must be blended back to front.
+    pub(crate) fn opaque(&self, index: u32) -> bool {
+        self.nodes[index as usize].world_color[3] >= 1.0
+    }
+
+    /// Layer of slot `index` as of the last `take_changes`.
+    pub(crate) fn world_z(&self, index: u32) -> f32 {
+        self.nodes[index as usize].world_z
+    }
+
+    /// Shapes as (slot, mesh) in drawing order, as of the last `take_changes`.
+    pub(crate) fn draw_list(&self) -> &[(u32, Range<u32>)] {
+        &self.draw_list
+    }
+
+    /// GPU instance for slot `index`, with the translation converted to NDC for `aspect`.
+    fn instance(&self, index: u32, aspect: f32) -> ShapeInstance {
+        let node = &self.nodes[index as usize];
+        let [x, y] = node.world.translation;
+        ShapeInstance {
+            position: [x / aspect, y],
+            scale: node.world.scale,
+            rotation: node.world.rotation,
+            color: node.world_color,
+        }
+    }
+}
+
+/// What changed in a `SceneGraph` since the last upload.
+pub(crate) struct Changes {
+    /// Slots whose world transform or color changed, ascending
+    pub slots: Vec<u32>,
+    /// Vertices added or moved by compaction
+    pub vertices: Option<Range<usize>>,
+    /// Meshes of removed shapes not yet reclaimed
+    pub removed: Vec<Range<u32>>,
+}
+
+/// Draws a `SceneGraph` with the instanced shape pipeline: one draw per shape, each using
+/// the instance in its node's slot. Buffers grow as the graph does.
+///
+/// Shapes are drawn one at a time so that they can be ordered by depth among the other
+/// layers; opaque and translucent shapes use separate pipelines. Alongside a `Batcher`, only
+/// the translucent shapes are drawn this way.
+pub struct GraphLayer {
+    pipeline_layout: PipelineLayout,
+    opaque_pipeline: RenderPipeline,
+    translucent_pipeline: RenderPipeline,
+    bind_group: BindGroup,
+    texture_bind_group: BindGroup,
+    aspect_buffer: Buffer,
+    aspect: f32,
+    vertex_buffer: Buffer,
+    instance_buffer: Buffer,
+    /// Every instance must be rewritten, e.g. after the aspect ratio changed
+    stale: bool,
+}
+
+impl GraphLayer {
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        format: wgpu::TextureFormat,
+        aspect: f32,
+        sources: &ShaderSources,
+    ) -> GraphLayer {
+        let uniform = |contents: &[u8], usage| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label: None, contents, usage })
+        };
+        // White base color and solid fill, so the instance color is the shape color
+        let color_buffer = uniform(bytemuck::cast_slice(&WHITE), BufferUsages::UNIFORM);
+        let fill_buffer = uniform(bytemuck::bytes_of(&Fill::Solid(WHITE).params([1.0, 1.0])), BufferUsages::UNIFORM);
+        let aspect_buffer =
+            uniform(bytemuck::cast_slice(&[aspect, 0.0, 0.0, 0.0]), BufferUsages::UNIFORM | BufferUsages::COPY_DST);
+        // No offset beyond the instances' own; also bound in place of the unused SDF parameters
+        let zero_buffer = uniform(&[0; 16], BufferUsages::UNIFORM);
+
+        let bind_group_layout = device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor { label: None, entries: &SHAPE_BIND_GROUP });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: color_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: aspect_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: zero_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: zero_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 4, resource: fill_buffer.as_entire_binding() },
+            ],
+        });
+        let texture_layout = texture::bind_group_layout(device);
+        let texture_bind_group =
+            texture::create_bind_group(device, queue, &texture_layout, None).expect("a 1x1 texture always fits");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout, &texture_layout],
+            push_constant_ranges: &[],
+        });
+        let variant = ShapeVariant::INSTANCED;
+        let load = |depth| {
+            pipeline::load(device, sources, &variant.files(), &pipeline_layout, variant.buffers(), format, depth)
+        };
+        let opaque_pipeline = load(Depth::Opaque);
+        let translucent_pipeline = load(Depth::Translucent);
+
+        GraphLayer {
+            pipeline_layout,
+            opaque_pipeline,
+            translucent_pipeline,
+            bind_group,
+            texture_bind_group,
+            aspect_buffer,
+            aspect,
+            vertex_buffer: grown_buffer(device, "graph vertices", BufferUsages::VERTEX, 0),
+            instance_buffer: grown_buffer(device, "graph instances", BufferUsages::VERTEX, 0),
+            stale: false,
+        }
+    }
+
+    pub fn resize(&mut self, queue: &Queue, aspect: f32) {
+        self.aspect = aspect;
+        queue.write_buffer(&self.aspect_buffer, 0, bytemuck::cast_slice(&[aspect, 0.0, 0.0, 0.0]));
+        // Translations are converted to NDC on upload
+        self.stale = true;
+    }
+
+    pub fn pipeline_layout(&self) -> &PipelineLayout {
+        &self.pipeline_layout
+    }
+
+    /// Swaps in rebuilt pipelines, e.g. after a shader reload.
+    pub fn set_pipelines(&mut self, opaque: RenderPipeline, translucent: RenderPipeline) {
+        self.opaque_pipeline = opaque;
+        self.translucent_pipeline = translucent;
+    }
+
+    /// Uploads `changes`, taken from `graph`, reallocating buffers that are too small.
+    pub(crate) fn sync(&mut self, device: &Device, queue: &Queue, graph: &SceneGraph, changes: &Changes) {
+        let vertex_bytes = graph.vertices.len() as u64 * VERTEX_SIZE;
+        if vertex_bytes > self.vertex_buffer.size() {
+            self.vertex_buffer = grown_buffer(device, "graph vertices", BufferUsages::VERTEX, vertex_bytes);
+            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&graph.vertices));
+        } else if let Some(range) = changes.vertices.clone().filter(|r| !r.is_empty()) {
+            let offset = range.start as u64 * VERTEX_SIZE;
+            queue.write_buffer(&self.vertex_buffer, offset, bytemuck::cast_slice(&graph.vertices[range]));
+        }
+
+        let instance_bytes = graph.nodes.len() as u64 * INSTANCE_SIZE;
+        if instance_bytes > self.instance_buffer.size() {
+            self.instance_buffer = grown_buffer(device, "graph instances", BufferUsages::VERTEX, instance_bytes);
+            self.stale = true;
+        }
+        if std::mem::take(&mut self.stale) {
+            let instances: Vec<ShapeInstance> =
+                (0..graph.nodes.len() as u32).map(|i| graph.instance(i, self.aspect)).collect();
+            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+            return;
+        }
+        for run in runs(&changes.slots) {
+            let instances: Vec<ShapeInstance> = run.clone().map(|slot| graph.instance(slot, self.aspect)).collect();
+            let offset = run.start as u64 * INSTANCE_SIZE;
+            queue.write_buffer(&self.instance_buffer, offset, bytemuck::cast_slice(&instances));
+        }
+    }
+
+    /// Sets the pipeline and bindings shared by every shape, ahead of `draw_shape` calls.
+    pub fn bind(&self, rpass: &mut RenderPass, opaque: bool) {
+        rpass.set_pipeline(if opaque { &self.opaque_pipeline } else { &self.translucent_pipeline });
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_bind_group(1, &self.texture_bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+    }
+
+    /// Draws one entry of the graph's draw list as last synced. Returns the number of vertices drawn.
+    pub fn draw_shape(&self, rpass: &mut RenderPass, slot: u32, mesh: Range<u32>) -> u32 {
+        // Offsetting the binding rather than using `first_instance`, which not every backend supports
+        let offset = slot as u64 * INSTANCE_SIZE;
+        rpass.set_vertex_buffer(1, self.instance_buffer.slice(offset..offset + INSTANCE_SIZE));
+        let vertices = mesh.len() as u32;
+        rpass.draw(mesh, 0..1);
+        vertices
+    }
+}
+
+/// Ascending, distinct `slots` as runs of consecutive slots, so that each run is uploaded in
+/// one write.
+pub(crate) fn runs(slots: &[u32]) -> impl Iterator<Item = Range<u32>> + '_ {
+    slots.chunk_by(|a, b| a + 1 == *b).map(|run| run[0]..run[run.len() - 1] + 1)
+}
+
+/// A COPY_DST buffer of at least `size` bytes, rounded up to a power of two so that a
+/// growing graph reallocates rarely.
+pub(crate) fn grown_buffer(device: &Device, label: &str, usage: BufferUsages, size: u64) -> Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: size.next_power_of_two().max(256),
+        usage: usage | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+        (a[0] - b[0]).abs() < 1e-5 && (a[1] - b[1]).abs() < 1e-5
+    }
+
+    // A triangle whose vertices record `tag`, to tell meshes apart after compaction
+    fn mesh(tag: f32) -> [ShapeVertex; 3] {
+        [[tag, 0.0, 0.0, 0.0], [tag, 1.0, 0.0, 0.0], [tag, 2.0, 0.0, 0.0]]
+    }
+
+    #[test]
+    fn runs_split_at_gaps() {
+        assert_eq!(runs(&[]).count(), 0);
+        assert_eq!(runs(&[4]).collect::<Vec<_>>(), vec![4..5]);
+        assert_eq!(runs(&[0, 1, 2, 5, 7, 8]).collect::<Vec<_>>(), vec![0..3, 5..6, 7..9]);
+    }
+
+    #[test]
+    fn then_applies_the_child_inside_the_parent() {
+        let parent = Transform { translation: [1.0, 0.0], rotation: std::f32::consts::FRAC_PI_2, scale: 2.0 };
+        let child = Transform { translation: [1.0, 0.0], rotation: 0.5, scale: 3.0 };
+        let combined = parent.then(&child);
+        assert!(close(combined.translation, [1.0, 2.0]));
+        assert_eq!(combined.rotation, std::f32::consts::FRAC_PI_2 + 0.5);
+        assert_eq!(combined.scale, 6.0);
+        // Same as applying the child, then the parent
+        let p = [0.3, -0.7];
+        assert!(close(combined.apply(p), parent.apply(child.apply(p))));
+        assert_eq!(Transform::IDENTITY.then(&child), child);
+    }
+
+    #[test]
+    fn stale_ids_are_rejected_after_their_slot_is_reused() {
+        let mut graph = SceneGraph::new();
+        let old = graph.add_shape(None, &mesh(0.0), WHITE, Transform::IDENTITY).unwrap();
+        graph.remove(old).unwrap();
+        let new = graph.add_shape(None, &mesh(1.0), WHITE, Transform::IDENTITY).unwrap();
+        assert_eq!(new.index, old.index);
+        assert_ne!(new, old);
+        assert!(graph.set_color(old, [1.0, 0.0, 0.0, 1.0]).unwrap_err().contains("does not exist"));
+        assert!(graph.remove(old).is_err());
+        assert!(graph.add_group(Some(old), Transform::IDENTITY).is_err());
+        assert!(graph.transform(new).is_ok());
+    }
+
+    #[test]
+    fn parents_carry_transform_color_and_z_to_children() {
+        let mut graph = SceneGraph::new();
+        let offset = Transform { translation: [1.0, 0.0], ..Transform::IDENTITY };
+        let group = graph.add_group(None, offset).unwrap();
+        graph.set_color(group, [0.5, 1.0, 1.0, 0.5]).unwrap();
+        graph.set_z(group, 2.0).unwrap();
+        let child = graph.add_shape(Some(group), &mesh(0.0), [1.0, 0.5, 1.0, 1.0], offset).unwrap();
+        graph.set_z(child, 1.0).unwrap();
+        graph.take_changes();
+        let (world, color) = graph.world(child.index);
+        assert!(close(world.translation, [2.0, 0.0]));
+        assert_eq!(color, [0.5, 0.5, 1.0, 0.5]);
+        assert_eq!(graph.world_z(child.index), 3.0);
+
+        // Moving the parent alone updates the child too
+        graph.set_transform(group, Transform { scale: 2.0, ..offset }).unwrap();
+        let changes = graph.take_changes();
+        assert_eq!(changes.slots, [group.index, child.index]);
+        assert!(close(graph.world(child.index).0.translation, [3.0, 0.0]));
+        assert_eq!(graph.world(child.index).0.scale, 2.0);
+    }
+
+    #[test]
+    fn take_changes_reports_each_change_once() {
+        let mut graph = SceneGraph::new();
+        let a = graph.add_shape(None, &mesh(0.0), WHITE, Transform::IDENTITY).unwrap();
+        let b = graph.add_shape(None, &mesh(1.0), WHITE, Transform::IDENTITY).unwrap();
+        let changes = graph.take_changes();
+        assert_eq!(changes.slots, [a.index, b.index]);
+        assert_eq!(changes.vertices, Some(0..6));
+        assert!(changes.removed.is_empty());
+        assert_eq!(graph.draw_list(), [(a.index, 0..3), (b.index, 3..6)]);
+
+        // Nothing changed since
+        let changes = graph.take_changes();
+        assert!(changes.slots.is_empty() && changes.vertices.is_none());
+
+        graph.set_color(b, [0.0; 4]).unwrap();
+        graph.set_transform(b, Transform::IDENTITY).unwrap();
+        assert_eq!(graph.take_changes().slots, [b.index]);
+
+        // Half the vertices are still live, so the removed mesh is kept until compaction
+        graph.remove(a).unwrap();
+        let changes = graph.take_changes();
+        assert_eq!(changes.removed.len(), 1);
+        assert_eq!(changes.removed[0], 0..3);
+        assert!(changes.vertices.is_none());
+        assert_eq!(graph.draw_list(), [(b.index, 3..6)]);
+    }
+
+    #[test]
+    fn compaction_keeps_draw_ranges_on_their_meshes() {
+        let mut graph = SceneGraph::new();
+        let ids: Vec<NodeId> =
+            (0..4).map(|i| graph.add_shape(None, &mesh(i as f32), WHITE, Transform::IDENTITY).unwrap()).collect();
+        let group = graph.add_group(None, Transform::IDENTITY).unwrap();
+        let child = graph.add_shape(Some(group), &mesh(4.0), WHITE, Transform::IDENTITY).unwrap();
+        graph.take_changes();
+
+        // Three of five meshes gone: more than half the vertices are garbage
+        graph.remove(ids[0]).unwrap();
+        graph.remove(ids[2]).unwrap();
+        graph.remove(group).unwrap();
+        assert!(graph.transform(child).is_err());
+        let changes = graph.take_changes();
+        assert_eq!(changes.vertices, Some(0..6));
+        assert!(changes.removed.is_empty());
+
+        assert_eq!(graph.vertices().len(), 6);
+        let draws = graph.draw_list();
+        assert_eq!(draws.iter().map(|(slot, _)| *slot).collect::<Vec<_>>(), [ids[1].index, ids[3].index]);
+        for ((_, range), tag) in draws.iter().zip([1.0, 3.0]) {
+            let vertices = &graph.vertices()[range.start as usize..range.end as usize];
+            assert_eq!(vertices, mesh(tag));
+        }
+        assert_eq!(graph.shapes().collect::<Vec<_>>(), draws);
+    }
+}
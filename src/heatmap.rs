@@ -0,0 +1,482 @@
+// Heatmaps: a 2D array of values drawn as an image over the axis coordinates.
+//
+// The values are uploaded unchanged as a float texture and mapped to colors in the fragment
+// shader through a lookup texture of the colormap, so the value range and sampling can change
+// without touching the data. Float textures are not filterable everywhere, so bilinear
+// sampling is done by hand from four loads.
+
+use std::path::Path;
+
+use wgpu::{
+    util::DeviceExt, BindGroup, BindGroupLayoutEntry, Buffer, Device, PipelineLayout, Queue, RenderPass, RenderPipeline,
+    TextureFormat,
+};
+
+use crate::colormap::{self, Colormap};
+use crate::pipeline::{self, Depth};
+use crate::scale::{Scale, ScaleKind, Scales, SYMLOG_THRESHOLD};
+use crate::shaders::{self, ShaderSources};
+use crate::texture;
+
+// Texels in the colormap lookup texture
+const LUT_SIZE: u32 = 256;
+
+/// Group 0 of the heatmap pipeline: `HeatmapParams`, the values, and the colormap lookup
+/// texture with its sampler.
+pub const BIND_GROUP: [BindGroupLayoutEntry; 4] = [
+    pipeline::uniform_entry(0, wgpu::ShaderStages::VERTEX_FRAGMENT),
+    BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    },
+    BindGroupLayoutEntry {
+        binding: 2,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    },
+    BindGroupLayoutEntry {
+        binding: 3,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    },
+];
+
+/// How values between cell centres are found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Sampling {
+    /// Each cell is one flat color
+    Nearest,
+    /// Values are interpolated between neighbouring cell centres
+    Bilinear,
+}
+
+/// A 2D array of values, row by row from the top. NaN marks missing values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    pub columns: usize,
+    pub rows: usize,
+    pub values: Vec<f32>,
+}
+
+impl Grid {
+    /// Reads a `.npy` file, or otherwise CSV.
+    pub fn load(path: &Path) -> Result<Grid, String> {
+        let npy = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("npy"));
+        if npy {
+            let bytes = std::fs::read(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+            parse_npy(&bytes).map_err(|e| format!("{}: {e}", path.display()))
+        } else {
+            let text = std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+            parse_csv(&text).map_err(|e| format!("{}: {e}", path.display()))
+        }
+    }
+
+    /// The value in `row` and `column`.
+    pub fn get(&self, row: usize, column: usize) -> f32 {
+        self.values[row * self.columns + column]
+    }
+}
+
+/// One cell of a heatmap.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    pub row: usize,
+    pub column: usize,
+    pub value: f32,
+}
+
+/// A grid of values and where and how it is drawn.
+#[derive(Clone, Debug)]
+pub struct Heatmap {
+    pub grid: Grid,
+    /// Left, right, bottom and top edges in axis coordinates
+    pub extent: [f32; 4],
+    /// Values mapped to the ends of the colormap; the grid's finite extent when unset
+    pub range: Option<[f32; 2]>,
+    pub sampling: Sampling,
+    /// Layer of the heatmap
+    pub z: f32,
+}
+
+impl Heatmap {
+    pub fn new(grid: Grid) -> Heatmap {
+        Heatmap { grid, extent: [-1.0, 1.0, -1.0, 1.0], range: None, sampling: Sampling::Nearest, z: -2.0 }
+    }
+
+    /// The values at the ends of the colormap.
+    pub fn range(&self) -> [f32; 2] {
+        self.range.unwrap_or_else(|| colormap::extent(&self.grid.values))
+    }
+
+    /// The cell under `p`, in axis coordinates, if the heatmap covers it.
+    pub fn cell_at(&self, [x, y]: [f32; 2]) -> Option<Cell> {
+        let [left, right, bottom, top] = self.extent;
+        let (u, v) = ((x - left) / (right - left), (top - y) / (top - bottom));
+        if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+            return None;
+        }
+        let column = ((u * self.grid.columns as f32) as usize).min(self.grid.columns - 1);
+        let row = ((v * self.grid.rows as f32) as usize).min(self.grid.rows - 1);
+        Some(Cell { row, column, value: self.grid.get(row, column) })
+    }
+}
+
+/// Rows of numbers separated by commas, or by whitespace or semicolons. Blank lines and lines
+/// starting with `#` are skipped, and so is a first row without any numbers, taken as a header.
+/// Empty cells and `nan` are missing values.
+pub fn parse_csv(text: &str) -> Result<Grid, String> {
+    let mut rows: Vec<Vec<f32>> = Vec::new();
+    let mut columns = 0;
+    let mut first = true;
+    for (i, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let cells: Vec<&str> = if line.contains(',') {
+            line.split(',').map(str::trim).collect()
+        } else {
+            line.split(|c: char| c == ';' || c.is_whitespace()).filter(|s| !s.is_empty()).collect()
+        };
+        let parsed: Vec<Option<f32>> =
+            cells.iter().map(|cell| if cell.is_empty() { Some(f32::NAN) } else { cell.parse().ok() }).collect();
+        let header = parsed.iter().zip(&cells).all(|(v, cell)| v.is_none() || cell.is_empty());
+        if std::mem::take(&mut first) && header {
+            continue;
+        }
+        let row = parsed
+            .iter()
+            .zip(&cells)
+            .map(|(v, cell)| v.ok_or_else(|| format!("line {i}: '{cell}' is not a number")))
+            .collect::<Result<Vec<f32>, String>>()?;
+        if rows.is_empty() {
+            columns = row.len();
+        } else if row.len() != columns {
+            return Err(format!("line {i}: {} values, but the first row has {columns}", row.len()));
+        }
+        rows.push(row);
+    }
+    if rows.is_empty() {
+        return Err("no rows of values".to_owned());
+    }
+    Ok(Grid { columns, rows: rows.len(), values: rows.concat() })
+}
+
+/// A NumPy `.npy` array of one or two dimensions, of floats, integers or booleans. A 1D
+/// array is a single row.
+pub fn parse_npy(bytes: &[u8]) -> Result<Grid, String> {
+    let rest = bytes.strip_prefix(b"\x93NUMPY").ok_or("not a .npy file")?;
+    let (header, data) = match rest {
+        [1, _, a, b, rest @ ..] => rest.split_at_checked(u16::from_le_bytes([*a, *b]) as usize),
+        [2 | 3, _, a, b, c, d, rest @ ..] => rest.split_at_checked(u32::from_le_bytes([*a, *b, *c, *d]) as usize),
+        _ => return Err("unsupported .npy version".to_owned()),
+    }
+    .ok_or("truncated .npy header")?;
+    let header = String::from_utf8_lossy(header);
+
+    let descr = header_value(&header, "descr").ok_or("missing 'descr' in the .npy header")?;
+    let descr = descr.trim_matches(|c| c == '\'' || c == '"');
+    let (big_endian, kind) = match descr.split_at_checked(1) {
+        Some((">", kind)) => (true, kind),
+        Some(("<" | "|" | "=", kind)) => (false, kind),
+        _ => (false, descr),
+    };
+    let decode = decoder(kind).ok_or_else(|| format!("unsupported .npy element type '{descr}'"))?;
+    let size = kind[1..].parse::<usize>().unwrap();
+
+    let fortran_order = header_value(&header, "fortran_order").is_some_and(|v| v == "True");
+    let shape = header_value(&header, "shape").ok_or("missing 'shape' in the .npy header")?;
+    let shape = shape
+        .trim_matches(|c| c == '(' || c == ')')
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<usize>().map_err(|_| format!("invalid .npy shape '{shape}'")))
+        .collect::<Result<Vec<_>, String>>()?;
+    let (rows, columns) = match shape[..] {
+        [columns] => (1, columns),
+        [rows, columns] => (rows, columns),
+        _ => return Err(format!("expected a 1D or 2D array, got {} dimensions", shape.len())),
+    };
+    if rows == 0 || columns == 0 {
+        return Err("the array is empty".to_owned());
+    }
+    // A shape too large to address can only come from a corrupt header
+    let too_large = || format!("the .npy shape {rows} x {columns} is too large");
+    let count = rows.checked_mul(columns).ok_or_else(too_large)?;
+    let bytes = count.checked_mul(size).ok_or_else(too_large)?;
+    let data = data.get(..bytes).ok_or(".npy data is shorter than its shape")?;
+    let stored: Vec<f32> = data.chunks_exact(size).map(|b| decode(b, big_endian)).collect();
+    let values = if fortran_order {
+        (0..count).map(|i| stored[(i % columns) * rows + i / columns]).collect()
+    } else {
+        stored
+    };
+    Ok(Grid { columns, rows, values })
+}
+
+// The value of `key` in the Python dict literal of a .npy header, up to the next top-level comma
+fn header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let start = header.find(&format!("'{key}'"))? + key.len() + 2;
+    let rest = header[start..].trim_start().strip_prefix(':')?.trim_start();
+    let mut depth = 0;
+    let end = rest
+        .char_indices()
+        .find(|&(_, c)| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            depth == 0 && (c == ',' || c == '}')
+        })
+        .map_or(rest.len(), |(i, _)| i);
+    Some(rest[..end].trim())
+}
+
+// Reads one element of a .npy type such as `f8` or `u2`, given its endianness
+fn decoder(kind: &str) -> Option<fn(&[u8], bool) -> f32> {
+    macro_rules! decode {
+        ($t:ty) => {
+            |b: &[u8], big: bool| {
+                let b = b.try_into().unwrap();
+                (if big { <$t>::from_be_bytes(b) } else { <$t>::from_le_bytes(b) }) as f32
+            }
+        };
+    }
+    Some(match kind {
+        "f4" => decode!(f32),
+        "f8" => decode!(f64),
+        "i1" => decode!(i8),
+        "i2" => decode!(i16),
+        "i4" => decode!(i32),
+        "i8" => decode!(i64),
+        "u1" | "b1" => decode!(u8),
+        "u2" => decode!(u16),
+        "u4" => decode!(u32),
+        "u8" => decode!(u64),
+        _ => return None,
+    })
+}
+
+/// Uniform block consumed by `heatmap_shader.wgsl`. Layout must match `HeatmapParams` there.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct HeatmapParams {
+    extent: [f32; 4],
+    /// The extent in NDC, clamped to the view
+    quad: [f32; 4],
+    range: [f32; 2],
+    /// 0 = nearest, 1 = bilinear
+    sampling: u32,
+    _pad: u32,
+    x_scale: AxisScale,
+    y_scale: AxisScale,
+}
+
+/// `AxisScale` in `heatmap_shader.wgsl`: a `Scale` the shader maps pixels back to values through.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct AxisScale {
+    /// 0 = linear, 1 = log, 2 = symlog
+    kind: u32,
+    threshold: f32,
+    /// The scale's range, transformed
+    range: [f32; 2],
+}
+
+impl AxisScale {
+    fn new(scale: &Scale) -> AxisScale {
+        let kind = match scale.kind {
+            ScaleKind::Linear => 0,
+            ScaleKind::Log => 1,
+            ScaleKind::Symlog => 2,
+        };
+        AxisScale { kind, threshold: SYMLOG_THRESHOLD, range: scale.transformed_range() }
+    }
+}
+
+/// A heatmap drawn as one quad over the axes.
+pub struct HeatmapLayer {
+    heatmap: Heatmap,
+    pipeline_layout: PipelineLayout,
+    pipeline: RenderPipeline,
+    bind_group: BindGroup,
+    _params_buffer: Buffer,
+}
+
+impl HeatmapLayer {
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        format: TextureFormat,
+        heatmap: Heatmap,
+        colormap: &Colormap,
+        scales: &Scales,
+        sources: &ShaderSources,
+    ) -> Result<HeatmapLayer, String> {
+        let Grid { columns, rows, .. } = heatmap.grid;
+        let max = device.limits().max_texture_dimension_2d as usize;
+        if columns > max || rows > max {
+            return Err(format!("the heatmap is {columns}x{rows}, but this GPU takes at most {max}x{max}"));
+        }
+
+        // Edges off a log axis, at zero or below, are taken to be beyond the low end of the view
+        let [left, right, bottom, top] = heatmap.extent;
+        let ndc = |scale: &Scale, v: f32| match scale.to_ndc(v) {
+            n if n.is_nan() => -1.0,
+            n => n.clamp(-1.0, 1.0),
+        };
+        let quad = [ndc(&scales.x, left), ndc(&scales.x, right), ndc(&scales.y, bottom), ndc(&scales.y, top)];
+        let params = HeatmapParams {
+            extent: heatmap.extent,
+            quad,
+            range: heatmap.range(),
+            sampling: match heatmap.sampling {
+                Sampling::Nearest => 0,
+                Sampling::Bilinear => 1,
+            },
+            _pad: 0,
+            x_scale: AxisScale::new(&scales.x),
+            y_scale: AxisScale::new(&scales.y),
+        };
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let values = bytemuck::cast_slice(&heatmap.grid.values);
+        let values = texture::upload(device, queue, columns as u32, rows as u32, TextureFormat::R32Float, values);
+        // Colormap colors are used as they are, like every other color, so not decoded from sRGB
+        let lut: Vec<u8> = (0..LUT_SIZE)
+            .map(|i| colormap.sample(i as f32 / (LUT_SIZE - 1) as f32))
+            .flat_map(|c| c.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+            .collect();
+        let lut = texture::upload(device, queue, LUT_SIZE, 1, TextureFormat::Rgba8Unorm, &lut);
+        let sampler = texture::create_sampler(device, texture::Filter::Linear, texture::Wrap::Clamp);
+
+        let bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor { label: None, entries: &BIND_GROUP });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: params_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&values) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::TextureView(&lut) },
+                wgpu::BindGroupEntry { binding: 3, resource: wgpu::BindingResource::Sampler(&sampler) },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        // A quad generated from the vertex index; no vertex buffer. Translucent, since missing
+        // values leave holes and colormaps may have alpha
+        let files = [&shaders::HEATMAP];
+        let pipeline = pipeline::load(device, sources, &files, &pipeline_layout, &[], format, Depth::Translucent);
+
+        Ok(HeatmapLayer { heatmap, pipeline_layout, pipeline, bind_group, _params_buffer: params_buffer })
+    }
+
+    pub fn heatmap(&self) -> &Heatmap {
+        &self.heatmap
+    }
+
+    pub fn pipeline_layout(&self) -> &PipelineLayout {
+        &self.pipeline_layout
+    }
+
+    /// Swaps in a rebuilt pipeline, e.g. after a shader reload.
+    pub fn set_pipeline(&mut self, pipeline: RenderPipeline) {
+        self.pipeline = pipeline;
+    }
+
+    pub fn draw(&self, rpass: &mut RenderPass) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.draw(0..6, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv() {
+        let grid = parse_csv("# temperature\na,b,c\n1, 2, 3\n\n4,,nan\n").unwrap();
+        assert_eq!((grid.columns, grid.rows), (3, 2));
+        assert_eq!(grid.values[..4], [1.0, 2.0, 3.0, 4.0]);
+        assert!(grid.get(1, 1).is_nan() && grid.get(1, 2).is_nan());
+        // Whitespace-separated
+        let grid = parse_csv("1 2\n3\t4\n").unwrap();
+        assert_eq!(grid.values, [1.0, 2.0, 3.0, 4.0]);
+
+        assert!(parse_csv("1,2\n3\n").unwrap_err().starts_with("line 2: 1 values"));
+        assert!(parse_csv("1,2\n3,x\n").unwrap_err().contains("'x' is not a number"));
+        assert!(parse_csv("# nothing\n").is_err());
+    }
+
+    fn npy(descr: &str, fortran_order: bool, shape: &str, data: &[u8]) -> Vec<u8> {
+        let order = if fortran_order { "True" } else { "False" };
+        let mut header = format!("{{'descr': '{descr}', 'fortran_order': {order}, 'shape': {shape}, }}");
+        while (10 + header.len() + 1) % 64 != 0 {
+            header.push(' ');
+        }
+        header.push('\n');
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend((header.len() as u16).to_le_bytes());
+        bytes.extend(header.as_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    #[test]
+    fn npy_arrays() {
+        let data: Vec<u8> = [1.0f64, 2.0, 3.0, 4.0, 5.0, 6.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let grid = parse_npy(&npy("<f8", false, "(2, 3)", &data)).unwrap();
+        assert_eq!((grid.columns, grid.rows), (3, 2));
+        assert_eq!(grid.values, [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        // Column-major storage is transposed into rows
+        let grid = parse_npy(&npy("<f8", true, "(2, 3)", &data)).unwrap();
+        assert_eq!(grid.values, [1.0, 3.0, 5.0, 2.0, 4.0, 6.0]);
+
+        let data: Vec<u8> = [-1i16, 300].iter().flat_map(|v| v.to_be_bytes()).collect();
+        let grid = parse_npy(&npy(">i2", false, "(2,)", &data)).unwrap();
+        assert_eq!((grid.columns, grid.rows, grid.values), (2, 1, vec![-1.0, 300.0]));
+
+        assert!(parse_npy(&npy("<c16", false, "(1,)", &[0; 16])).unwrap_err().contains("element type"));
+        assert!(parse_npy(&npy("<f4", false, "(2, 2, 2)", &[0; 32])).unwrap_err().contains("3 dimensions"));
+        assert!(parse_npy(&npy("<f4", false, "(2, 2)", &[0; 12])).unwrap_err().contains("shorter"));
+        let huge = format!("({}, 2)", usize::MAX / 2 + 1);
+        assert!(parse_npy(&npy("<f4", false, &huge, &[0; 8])).unwrap_err().contains("too large"));
+        let huge = format!("({},)", usize::MAX / 2);
+        assert!(parse_npy(&npy("<f4", false, &huge, &[0; 8])).unwrap_err().contains("too large"));
+        assert!(parse_npy(b"PK\x03\x04").is_err());
+    }
+
+    #[test]
+    fn cells_under_points() {
+        let mut heatmap = Heatmap::new(parse_csv("1,2\n3,4\n5,6\n").unwrap());
+        heatmap.extent = [0.0, 1.0, 0.0, 0.6];
+        assert_eq!(heatmap.range(), [1.0, 6.0]);
+        // The first row is at the top
+        assert_eq!(heatmap.cell_at([0.1, 0.5]), Some(Cell { row: 0, column: 0, value: 1.0 }));
+        assert_eq!(heatmap.cell_at([0.9, 0.1]), Some(Cell { row: 2, column: 1, value: 6.0 }));
+        assert_eq!(heatmap.cell_at([1.1, 0.1]), None);
+        assert_eq!(heatmap.cell_at([0.5, -0.1]), None);
+    }
+}
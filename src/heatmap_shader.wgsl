@@ -0,0 +1,90 @@
+// Heatmap — a grid of values drawn as a quad over the axes, colored through a colormap.
+
+// An axis scale, as in scale.rs: values are spaced linearly once transformed
+struct AxisScale {
+    kind: u32,         // 0 = linear, 1 = log, 2 = symlog
+    threshold: f32,    // where symlog turns linear
+    range: vec2<f32>,  // transformed values at the low and high ends of the view
+}
+
+struct HeatmapParams {
+    extent: vec4<f32>, // left, right, bottom, top in axis coordinates
+    quad: vec4<f32>,   // the extent in NDC, clamped to the view
+    range: vec2<f32>,  // values at the low and high ends of the colormap
+    sampling: u32,     // 0 = nearest, 1 = bilinear
+    _pad: u32,
+    x_scale: AxisScale,
+    y_scale: AxisScale,
+}
+
+@group(0) @binding(0) var<uniform> u_heatmap: HeatmapParams;
+@group(0) @binding(1) var t_values: texture_2d<f32>;
+@group(0) @binding(2) var t_colormap: texture_2d<f32>;
+@group(0) @binding(3) var s_colormap: sampler;
+
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+}
+
+// Two triangles covering the extent
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 0.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 1.0), vec2<f32>(0.0, 1.0),
+    );
+    let c = corners[i];
+    let q = u_heatmap.quad;
+    var out: VertexOutput;
+    out.ndc = vec2<f32>(mix(q.x, q.y, c.x), mix(q.z, q.w, c.y));
+    out.pos = vec4<f32>(out.ndc, 0.0, 1.0);
+    return out;
+}
+
+// The value at `n` in NDC along an axis; Scale::from_ndc
+fn from_ndc(n: f32, s: AxisScale) -> f32 {
+    let t = (n * (s.range.y - s.range.x) + (s.range.x + s.range.y)) * 0.5;
+    switch s.kind {
+        case 1u: { return pow(10.0, t); }
+        case 2u: { return sign(t) * s.threshold * (pow(10.0, abs(t)) - 1.0); }
+        default: { return t; }
+    }
+}
+
+fn value(texel: vec2<i32>) -> f32 {
+    let last = vec2<i32>(textureDimensions(t_values)) - 1;
+    return textureLoad(t_values, clamp(texel, vec2<i32>(0), last), 0).r;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // 0..1 across the grid, from the top left. Cells are even in axis coordinates, which on
+    // a log axis are not even on screen
+    let x = from_ndc(in.ndc.x, u_heatmap.x_scale);
+    let y = from_ndc(in.ndc.y, u_heatmap.y_scale);
+    let e = u_heatmap.extent;
+    let uv = vec2<f32>((x - e.x) / (e.y - e.x), (e.w - y) / (e.w - e.z));
+    let p = uv * vec2<f32>(textureDimensions(t_values));
+    var v: f32;
+    if u_heatmap.sampling == 0u {
+        v = value(vec2<i32>(floor(p)));
+    } else {
+        // Between the four nearest cell centres
+        let q = p - 0.5;
+        let i = vec2<i32>(floor(q));
+        let f = fract(q);
+        let top = mix(value(i), value(i + vec2<i32>(1, 0)), f.x);
+        let bottom = mix(value(i + vec2<i32>(0, 1)), value(i + vec2<i32>(1, 1)), f.x);
+        v = mix(top, bottom, f.y);
+    }
+    // Missing values (NaN) leave a hole; compared by bits, which no optimizer folds away
+    if (bitcast<u32>(v) & 0x7fffffffu) > 0x7f800000u {
+        discard;
+    }
+    let span = u_heatmap.range.y - u_heatmap.range.x;
+    let t = select(0.5, clamp((v - u_heatmap.range.x) / span, 0.0, 1.0), span != 0.0);
+    // Texel centres of the lookup texture span 0..1
+    let n = f32(textureDimensions(t_colormap).x);
+    return textureSampleLevel(t_colormap, s_colormap, vec2<f32>((t * (n - 1.0) + 0.5) / n, 0.5), 0.0);
+}
@@ -0,0 +1,207 @@
+// Implicit curves such as `x^2 + y^2 = 0.25`, traced by marching squares.
+//
+// The difference of the equation's two sides is sampled on a grid of points over the view.
+// Every grid cell whose corners lie on both sides of a contour level contributes one segment,
+// or two at a saddle, with its ends placed on the cell's edges by linear interpolation. Cells
+// that share an edge share the point on it, so segments are chained into polylines through
+// the edges they cross.
+
+use std::collections::BTreeMap;
+
+use crate::expr::Expr;
+use crate::scale::Scales;
+use crate::tessellate::Contour;
+
+/// Distance between grid points the curve is sampled at, in physical pixels.
+pub const CELL_PX: u32 = 4;
+
+/// An equation of the axis coordinates `x` and `y`, or an expression whose level sets are drawn.
+#[derive(Clone, Debug)]
+pub struct Implicit {
+    sides: Vec<Expr>,
+    /// Values of the left side minus the right side to draw contours at
+    pub levels: Vec<f32>,
+}
+
+impl Implicit {
+    /// Parses `f(x, y) = g(x, y)`, or `f(x, y)` alone for `f(x, y) = 0`.
+    pub fn parse(s: &str) -> Result<Implicit, String> {
+        let sides = s
+            .split('=')
+            .map(|side| Expr::parse(side, &["x", "y"]))
+            .collect::<Result<Vec<_>, String>>()
+            .map_err(|e| format!("implicit curve: {e}"))?;
+        if sides.len() > 2 {
+            return Err(format!("implicit curve: expected at most one '=', got {}", sides.len() - 1));
+        }
+        Ok(Implicit { sides, levels: vec![0.0] })
+    }
+
+    /// The left side minus the right side at `p`.
+    pub fn at(&self, [x, y]: [f32; 2]) -> f32 {
+        let values = [f64::from(x), f64::from(y)];
+        let value = match &self.sides[..] {
+            [f, g] => f.eval(&values) - g.eval(&values),
+            [f] => f.eval(&values),
+            _ => unreachable!(),
+        };
+        value as f32
+    }
+
+    /// The contours at every level over the view, in NDC, sampled on an even grid of `cells`
+    /// columns and rows on screen whose points are placed in axis coordinates by `scales`, with
+    /// whether each contour is closed.
+    pub fn contours(&self, scales: &Scales, [columns, rows]: [usize; 2]) -> (Vec<Contour>, Vec<bool>) {
+        let point = |[i, j]: [f32; 2]| [2.0 * i / columns as f32 - 1.0, 2.0 * j / rows as f32 - 1.0];
+        let values: Vec<f32> = (0..=rows)
+            .flat_map(|j| (0..=columns).map(move |i| [i as f32, j as f32]))
+            .map(|p| self.at(scales.from_ndc(point(p))))
+            .collect();
+        let (mut contours, mut closed) = (Vec::new(), Vec::new());
+        for &level in &self.levels {
+            let (lines, flags) = march(&values, columns + 1, rows + 1, level);
+            contours.extend(lines.into_iter().map(|line| line.into_iter().map(point).collect()));
+            closed.extend(flags);
+        }
+        (contours, closed)
+    }
+}
+
+/// Where `values`, `columns` x `rows` samples stored row by row, cross `level`, with whether
+/// each contour is closed. Points are in grid units: sample `j * columns + i` is at (i, j).
+/// Contours running off the grid, or into samples that are NaN, are open.
+pub fn march(values: &[f32], columns: usize, rows: usize, level: f32) -> (Vec<Contour>, Vec<bool>) {
+    // Grid edges are numbered by the sample they start at: even ones run to the next sample
+    // in the row, odd ones to the sample above
+    let horizontal = |i: usize, j: usize| 2 * (j * columns + i);
+    let vertical = |i: usize, j: usize| 2 * (j * columns + i) + 1;
+    let crossing = |edge: usize| {
+        let (i, j) = ((edge / 2) % columns, (edge / 2) / columns);
+        let (di, dj) = if edge.is_multiple_of(2) { (1, 0) } else { (0, 1) };
+        let (a, b) = (values[j * columns + i], values[(j + dj) * columns + i + di]);
+        let t = (level - a) / (b - a);
+        [i as f32 + t * di as f32, j as f32 + t * dj as f32]
+    };
+
+    let mut neighbours: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for j in 0..rows.saturating_sub(1) {
+        for i in 0..columns.saturating_sub(1) {
+            // Counter-clockwise from the bottom left
+            let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)].map(|(i, j)| values[j * columns + i]);
+            if corners.iter().any(|v| v.is_nan()) {
+                continue;
+            }
+            let case = corners.iter().enumerate().fold(0, |case, (k, &v)| case | (usize::from(v > level) << k));
+            let (bottom, right) = (horizontal(i, j), vertical(i + 1, j));
+            let (top, left) = (horizontal(i, j + 1), vertical(i, j));
+            // At a saddle the centre, taken as the corners' mean, decides which corners connect
+            let centre_above = corners.iter().sum::<f32>() / 4.0 > level;
+            let segments: &[(usize, usize)] = match case {
+                1 | 14 => &[(left, bottom)],
+                2 | 13 => &[(bottom, right)],
+                3 | 12 => &[(left, right)],
+                4 | 11 => &[(right, top)],
+                6 | 9 => &[(bottom, top)],
+                7 | 8 => &[(left, top)],
+                5 if centre_above => &[(bottom, right), (top, left)],
+                5 => &[(left, bottom), (right, top)],
+                10 if centre_above => &[(left, bottom), (right, top)],
+                10 => &[(bottom, right), (top, left)],
+                _ => &[],
+            };
+            for &(a, b) in segments {
+                neighbours.entry(a).or_default().push(b);
+                neighbours.entry(b).or_default().push(a);
+            }
+        }
+    }
+
+    // Each crossed edge is shared by at most two segments, so contours are simple chains: open
+    // ones are walked from an end, and whatever is left after them are loops
+    let ends = neighbours.iter().filter(|(_, n)| n.len() == 1).map(|(&edge, _)| edge);
+    let starts: Vec<usize> = ends.chain(neighbours.keys().copied()).collect();
+    let mut visited = vec![false; 2 * columns * rows];
+    let (mut contours, mut closed) = (Vec::new(), Vec::new());
+    for start in starts {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut chain = vec![start];
+        let mut current = start;
+        while let Some(&next) = neighbours[&current].iter().find(|&&n| !visited[n]) {
+            visited[next] = true;
+            chain.push(next);
+            current = next;
+        }
+        closed.push(neighbours[&start].len() == 2 && chain.len() > 2);
+        contours.push(chain.into_iter().map(crossing).collect());
+    }
+    (contours, closed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::scale::{Scale, ScaleKind};
+
+    #[test]
+    fn circle() {
+        let curve = Implicit::parse("x^2 + y^2 = 0.25").unwrap();
+        let (contours, closed) = curve.contours(&Scales::default(), [64, 64]);
+        assert_eq!(closed, [true]);
+        // Every point is on the circle, up to the error of interpolating across a cell
+        assert!(contours[0].iter().all(|&[x, y]| (x.hypot(y) - 0.5).abs() < 2e-3));
+        assert!(contours[0].len() > 64);
+    }
+
+    #[test]
+    fn levels() {
+        let mut curve = Implicit::parse("x^2 + y^2").unwrap();
+        curve.levels = vec![0.04, 0.36, 4.0];
+        let (contours, closed) = curve.contours(&Scales::default(), [50, 50]);
+        // The third circle is outside the view
+        assert_eq!(closed, [true, true]);
+        for (contour, r) in contours.iter().zip([0.2, 0.6]) {
+            assert!(contour.iter().all(|&[x, y]| (x.hypot(y) - r).abs() < 5e-3));
+        }
+    }
+
+    #[test]
+    fn lines_run_off_the_grid() {
+        let (contours, closed) = Implicit::parse("y = 2x + 0.1").unwrap().contours(&Scales::default(), [20, 20]);
+        assert_eq!(closed, [false]);
+        // A straight line is interpolated exactly
+        assert!(contours[0].iter().all(|&[x, y]| (y - 2.0 * x - 0.1).abs() < 1e-5));
+        let ends = [contours[0][0], *contours[0].last().unwrap()];
+        assert!(ends.iter().all(|&[x, y]| x.abs() == 1.0 || y.abs() == 1.0));
+
+        // Two crossing lines meet at a saddle and are split into two corners there
+        let (contours, closed) = Implicit::parse("x y").unwrap().contours(&Scales::default(), [9, 9]);
+        assert_eq!(closed, [false, false]);
+        assert!(contours.iter().flatten().all(|&[x, y]| x.abs() < 1e-6 || y.abs() < 1e-6));
+    }
+
+    #[test]
+    fn log_axes() {
+        // y = x^2 over two decades of x and four of y is the diagonal of the view
+        let scales = Scales {
+            x: Scale::new(ScaleKind::Log, Some([0.1, 10.0])).unwrap(),
+            y: Scale::new(ScaleKind::Log, Some([0.01, 100.0])).unwrap(),
+        };
+        let (contours, closed) = Implicit::parse("y = x^2").unwrap().contours(&scales, [30, 30]);
+        assert_eq!(closed, [false]);
+        assert!(contours[0].iter().all(|&[x, y]| (x - y).abs() < 1e-2));
+    }
+
+    #[test]
+    fn gaps_where_undefined() {
+        // sqrt is NaN for x < 0, so the circle is cut open there
+        let curve = Implicit::parse("x^2 + y^2 + 0 sqrt(x) = 0.25").unwrap();
+        let (_, closed) = curve.contours(&Scales::default(), [40, 40]);
+        assert_eq!(closed, [false]);
+        assert!(Implicit::parse("x = y = 1").unwrap_err().contains("at most one '='"));
+        assert!(Implicit::parse("x = z").is_err());
+    }
+}
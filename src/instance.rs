@@ -0,0 +1,42 @@
+use wgpu::VertexBufferLayout;
+
+/// One copy of the shape in an instanced draw. Layout must match `InstanceInput` in
+/// `instanced_shader.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShapeInstance {
+    /// Offset in NDC, added to the shape position like `--pos`
+    pub position: [f32; 2],
+    /// Multiplies the shape size
+    pub scale: f32,
+    /// Radians, counter-clockwise
+    pub rotation: f32,
+    /// Multiplies the fill color; white leaves the fill unchanged
+    pub color: [f32; 4],
+}
+
+impl Default for ShapeInstance {
+    fn default() -> ShapeInstance {
+        ShapeInstance { position: [0.0, 0.0], scale: 1.0, rotation: 0.0, color: [1.0; 4] }
+    }
+}
+
+/// Instance data: [x, y, scale, rotation, r, g, b, a] — stride 32 bytes, one step per instance
+pub const INSTANCE_VERTEX_LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+    array_stride: 32,
+    step_mode: wgpu::VertexStepMode::Instance,
+    attributes: &wgpu::vertex_attr_array![2 => Float32x2, 3 => Float32x2, 4 => Float32x4],
+};
+
+/// `columns` x `rows` instances evenly spread over NDC [-0.9, 0.9] on both axes.
+pub fn grid(columns: u32, rows: u32) -> Vec<ShapeInstance> {
+    let coord = |i: u32, n: u32| if n > 1 { -0.9 + 1.8 * i as f32 / (n - 1) as f32 } else { 0.0 };
+    (0..rows)
+        .flat_map(|row| {
+            (0..columns).map(move |col| ShapeInstance {
+                position: [coord(col, columns), coord(row, rows)],
+                ..Default::default()
+            })
+        })
+        .collect()
+}
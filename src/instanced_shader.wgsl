@@ -0,0 +1,38 @@
+// Instanced mesh shader: one shape mesh drawn once per instance. Expects `fill.wgsl` to be
+// prepended for `fill_color`. The instance color multiplies the fill, so white leaves it as-is.
+
+@group(0) @binding(1) var<uniform> u_aspect: vec4<f32>; // x = aspect ratio (width / height)
+@group(0) @binding(2) var<uniform> u_pos: vec4<f32>;    // x, y = offset applied to every instance
+
+struct InstanceInput {
+    @location(2) offset: vec2<f32>,
+    @location(3) scale_rotation: vec2<f32>, // x = scale, y = rotation in radians counter-clockwise
+    @location(4) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) local: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@location(0) position: vec2<f32>, @location(1) uv: vec2<f32>, instance: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+    let c = cos(instance.scale_rotation.y);
+    let s = sin(instance.scale_rotation.y);
+    // Rotate and scale in shape units, then aspect-correct; offsets are in NDC like --pos
+    let p = mat2x2<f32>(c, s, -s, c) * position * instance.scale_rotation.x;
+    let corrected = vec2<f32>(p.x / u_aspect.x, p.y) + instance.offset + u_pos.xy;
+    out.pos = vec4<f32>(corrected, 0.0, 1.0);
+    out.local = position;
+    out.uv = uv;
+    out.color = instance.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return fill_color(in.local, in.uv) * in.color;
+}
@@ -0,0 +1,28 @@
+pub mod adapter;
+pub mod axis;
+pub mod background;
+pub mod batch;
+pub mod check;
+pub mod color;
+pub mod colormap;
+pub mod expr;
+pub mod field;
+pub mod fill;
+pub mod frag;
+pub mod graph;
+pub mod heatmap;
+pub mod implicit;
+pub mod instance;
+pub mod pacing;
+pub mod parametric;
+pub mod path;
+pub mod pipeline;
+pub mod scale;
+pub mod scene;
+pub mod shaders;
+pub mod shape;
+pub mod state;
+pub mod stats;
+pub mod stroke;
+pub mod tessellate;
+pub mod texture;
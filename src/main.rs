@@ -1,10 +1,23 @@
-mod axis;
-mod shape;
-mod state;
-
-use clap::Parser;
-use shape::{Shape, ShapeConfig};
-use state::State;
+use clap::{Parser, Subcommand};
+use wgpu_sandbox::adapter::{self, AdapterOptions, Backend, PowerPreference};
+use wgpu_sandbox::background::{Background, Fit};
+use wgpu_sandbox::colormap::Colormap;
+use wgpu_sandbox::field::{self, VectorField};
+use wgpu_sandbox::fill::{self, Fill};
+use wgpu_sandbox::graph::SceneGraph;
+use wgpu_sandbox::heatmap::{Grid, Heatmap, Sampling};
+use wgpu_sandbox::implicit::Implicit;
+use wgpu_sandbox::pacing::{self, FramePacer, PresentMode};
+use wgpu_sandbox::parametric::{self, Parametric};
+use wgpu_sandbox::path;
+use wgpu_sandbox::scale::{Scale, ScaleKind, Scales};
+use wgpu_sandbox::scene::Scene;
+use wgpu_sandbox::shape::{self, RenderMode, Shape, ShapeConfig, Stroke, StrokeUnits};
+use wgpu_sandbox::state::State;
+use wgpu_sandbox::tessellate::{self, Contour, FillRule};
+use wgpu_sandbox::texture::{self, Filter, TextureConfig, Wrap};
+use wgpu_sandbox::{check, color, frag, pipeline};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use winit::{
@@ -17,12 +30,58 @@ use winit::{
 #[derive(Parser)]
 #[command(name = "wgpu-sandbox")]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(long, value_enum, default_value = "triangle")]
     shape: Shape,
 
     #[arg(long, default_value = "red")]
     color: String,
 
+    /// Fill: a color, `none` to draw only the stroke, or a gradient such as
+    /// "linear(0deg, red, blue 70%, white)", "radial(at 0 0, ...)" or "conic(from 90deg, ...)".
+    /// Defaults to --color
+    #[arg(long, value_parser = fill::parse_fill)]
+    fill: Option<Fill>,
+
+    /// Fill the shape with an image (PNG or JPEG) stretched over its bounds
+    #[arg(long)]
+    texture: Option<String>,
+
+    /// Texture sampling filter
+    #[arg(long = "texture-filter", value_enum, default_value = "linear")]
+    texture_filter: Filter,
+
+    /// How UVs outside the image are sampled
+    #[arg(long = "texture-wrap", value_enum, default_value = "repeat")]
+    texture_wrap: Wrap,
+
+    /// Number of times the image repeats across the shape
+    #[arg(long = "texture-scale", default_value_t = 1.0)]
+    texture_scale: f32,
+
+    /// Multiply the texture by the fill color; white with --fill none
+    #[arg(long)]
+    tint: bool,
+
+    /// WGSL file defining `fn shade(uv: vec2f, time: f32, resolution: vec2f) -> vec4f`,
+    /// used to color the shape instead of the fill. Reloaded when the file changes
+    #[arg(long)]
+    frag: Option<PathBuf>,
+
+    /// Draw an outline of this color along the shape boundary
+    #[arg(long)]
+    stroke: Option<String>,
+
+    /// Stroke width, in --stroke-units
+    #[arg(long = "stroke-width", default_value_t = 2.0)]
+    stroke_width: f32,
+
+    /// Units of --stroke-width: screen pixels or NDC Y units (the units of --size)
+    #[arg(long = "stroke-units", value_enum, default_value = "px")]
+    stroke_units: StrokeUnits,
+
     /// Size of the shape (default 0.5) or arm length of axes (default 1.0)
     #[arg(long)]
     size: Option<f32>,
@@ -30,6 +89,246 @@ struct Cli {
     #[arg(long, num_args = 2, value_names = ["X", "Y"])]
     pos: Option<Vec<f32>>,
 
+    /// Layer of the shape: higher layers are drawn in front, and opaque shapes hide lower ones
+    /// through the depth buffer whatever order they are drawn in
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    z: f32,
+
+    /// Draw the shape as tessellated triangles (mesh) or as one quad with a distance function (sdf)
+    #[arg(long = "render-mode", value_enum, default_value = "mesh")]
+    render_mode: RenderMode,
+
+    /// Corner radii of a square or rect, in the same units as --size: one for every corner,
+    /// or up to four as in CSS `border-radius` (clockwise from the top left)
+    #[arg(long = "corner-radius", num_args = 1..=4, value_name = "RADIUS")]
+    corner_radius: Vec<f32>,
+
+    /// Width of --shape rect, in the same units as --size. Defaults to twice --size
+    #[arg(long)]
+    width: Option<f32>,
+
+    /// Height of --shape rect. Defaults to twice --size
+    #[arg(long)]
+    height: Option<f32>,
+
+    /// Horizontal radius of --shape ellipse. Defaults to --size
+    #[arg(long)]
+    rx: Option<f32>,
+
+    /// Vertical radius of --shape ellipse. Defaults to --size
+    #[arg(long)]
+    ry: Option<f32>,
+
+    /// Inner radius of a ring or sector as a fraction of its outer radius
+    #[arg(long = "inner-ratio", default_value_t = 0.6)]
+    inner_ratio: f32,
+
+    /// Inner radius of a ring or sector, in the same units as --size; overrides --inner-ratio
+    #[arg(long = "inner-radius")]
+    inner_radius: Option<f32>,
+
+    /// Outer radius of a ring or sector. Defaults to --size
+    #[arg(long = "outer-radius")]
+    outer_radius: Option<f32>,
+
+    /// A contour of --shape polygon as "X,Y X,Y X,Y ...", in units of --size (so 1,1 is the
+    /// top-right corner of a square). Repeat for holes and further outlines
+    #[arg(long, value_parser = tessellate::parse_points, allow_hyphen_values = true)]
+    points: Vec<Contour>,
+
+    /// Which areas of overlapping polygon contours are filled: where they wind around
+    /// (non-zero, so holes must run against their outline) or an odd number of times (even-odd)
+    #[arg(long = "fill-rule", value_enum, default_value = "non-zero")]
+    fill_rule: FillRule,
+
+    /// Control points of --shape quad-bezier (3) or cubic-bezier (4) as "X,Y X,Y X,Y ...",
+    /// in units of --size. Defaults to an arch across the shape's bounds
+    #[arg(long = "control-points", value_parser = tessellate::parse_points, allow_hyphen_values = true)]
+    control_points: Option<Contour>,
+
+    /// Start of --shape arc, pie or sector, degrees counter-clockwise from +X
+    #[arg(long = "start-angle", default_value_t = 0.0, allow_negative_numbers = true)]
+    start_angle: f32,
+
+    /// End of --shape arc, pie or sector; below --start-angle the arc runs clockwise
+    #[arg(long = "end-angle", default_value_t = 270.0, allow_negative_numbers = true)]
+    end_angle: f32,
+
+    /// Largest distance in pixels between a Bézier or arc shape, or a --param curve, and the
+    /// chords it is drawn with; curves are split more finely where they bend more
+    #[arg(long = "curve-tolerance", default_value_t = 0.25)]
+    curve_tolerance: f32,
+
+    /// Mark the control points of a Bézier shape, or the ends and centre of an arc
+    #[arg(long = "show-controls")]
+    show_controls: bool,
+
+    /// Tail of --shape arrow, in units of --size
+    #[arg(long, num_args = 2, value_names = ["X", "Y"], allow_negative_numbers = true)]
+    from: Option<Vec<f32>>,
+
+    /// Tip of --shape arrow, in units of --size
+    #[arg(long, num_args = 2, value_names = ["X", "Y"], allow_negative_numbers = true)]
+    to: Option<Vec<f32>>,
+
+    /// Length of the head of --shape arrow, in units of --size; the head is as wide as it is long
+    #[arg(long = "head-size", default_value_t = 0.6)]
+    head_size: f32,
+
+    /// Draw a vector field of the axis coordinates, such as "(-y, x)", as arrows on the axis
+    /// grid. Arrow length and color follow the magnitude, relative to the largest one shown
+    #[arg(long = "vector-field", allow_hyphen_values = true, value_parser = VectorField::parse)]
+    vector_field: Option<VectorField>,
+
+    /// Colormap for data-driven colors, such as --vector-field magnitudes: viridis, magma,
+    /// inferno, plasma, cividis, the diverging coolwarm and rdbu, or a file with one color per
+    /// line from low to high, each as for --color or as "R G B" from 0 to 1
+    #[arg(long, value_parser = Colormap::parse, default_value = "viridis")]
+    colormap: Colormap,
+
+    /// Draw a legend of --colormap beside the axes, low values at the bottom
+    #[arg(long)]
+    colorbar: bool,
+
+    /// Draw a 2D array of values as an image colored through --colormap: a .npy file or CSV
+    /// (rows of numbers, first row at the top; empty cells and `nan` are left transparent).
+    /// Hovering over it shows the cell's value in the title bar
+    #[arg(long)]
+    heatmap: Option<PathBuf>,
+
+    /// Left, right, bottom and top edges of --heatmap in axis coordinates
+    #[arg(
+        long = "heatmap-extent",
+        num_args = 4,
+        value_names = ["LEFT", "RIGHT", "BOTTOM", "TOP"],
+        allow_negative_numbers = true,
+        default_values_t = [-1.0, 1.0, -1.0, 1.0]
+    )]
+    heatmap_extent: Vec<f32>,
+
+    /// Values at the low and high ends of --colormap for --heatmap; defaults to the data's extent
+    #[arg(long = "heatmap-range", num_args = 2, value_names = ["MIN", "MAX"], allow_negative_numbers = true)]
+    heatmap_range: Option<Vec<f32>>,
+
+    /// How --heatmap is sampled between cell centres
+    #[arg(long = "heatmap-sampling", value_enum, default_value = "nearest")]
+    heatmap_sampling: Sampling,
+
+    /// Layer of --heatmap; the axes are on layer --axis-z
+    #[arg(long = "heatmap-z", default_value_t = -2.0, allow_negative_numbers = true)]
+    heatmap_z: f32,
+
+    /// Plot the curves where an equation of the axis coordinates holds, such as
+    /// "x^2 + y^2 = 0.25", traced over the view by marching squares
+    #[arg(long, allow_hyphen_values = true, value_parser = Implicit::parse)]
+    implicit: Option<Implicit>,
+
+    /// Draw --implicit where its left side minus its right side equals each of these values
+    /// instead of 0, e.g. the level sets of "x^2 + y^2" with --levels 0.1 0.4 0.9
+    #[arg(long, num_args = 1.., requires = "implicit", allow_negative_numbers = true)]
+    levels: Option<Vec<f32>>,
+
+    /// Plot a parametric curve of `t` in axis coordinates, such as "cos(3t), sin(2t)"
+    #[arg(long, allow_hyphen_values = true, value_parser = Parametric::parse)]
+    param: Option<Parametric>,
+
+    /// Range of `t` for --param, such as 0..6.28 or -pi..pi [default: 0..2pi]
+    #[arg(
+        long,
+        requires = "param",
+        value_name = "START..END",
+        allow_hyphen_values = true,
+        value_parser = parametric::parse_range
+    )]
+    t: Option<[f32; 2]>,
+
+    /// Animate a dot tracing --param from the start of its range to the end, once every
+    /// SECONDS
+    #[arg(long, requires = "param", num_args = 0..=1, value_name = "SECONDS", default_missing_value = "4")]
+    pen: Option<f32>,
+
+    /// Color of plotted curves
+    #[arg(long = "plot-color", default_value = "orange")]
+    plot_color: String,
+
+    /// Width of plotted curves in pixels
+    #[arg(long = "plot-width", default_value_t = 2.0)]
+    plot_width: f32,
+
+    /// Draw SVG path data, as in a `d` attribute (e.g. "M0 0 h10 q0 10 -10 10 z"), fitted
+    /// into --size. Curves and arcs are flattened within --path-tolerance
+    #[arg(long, allow_hyphen_values = true, conflicts_with_all = ["points", "svg"])]
+    path: Option<String>,
+
+    /// Draw the <path> elements of an SVG file, like --path. A `fill-rule` attribute
+    /// overrides --fill-rule
+    #[arg(long, conflicts_with = "points")]
+    svg: Option<PathBuf>,
+
+    /// Largest distance between a curve of --path or --svg and its flattened outline, as a
+    /// fraction of the path's size
+    #[arg(long = "path-tolerance", default_value_t = 0.001)]
+    path_tolerance: f32,
+
+    /// Background: a color, `checkerboard` (for inspecting alpha) or an image path
+    #[arg(long, default_value = "white")]
+    background: String,
+
+    /// How a background image is scaled: show all of it (fit) or cover the window (fill)
+    #[arg(long = "background-fit", value_enum, default_value = "fit")]
+    background_fit: Fit,
+
+    /// JSON scene file: `shape`, `size`, `fill`, `points` (a list of contours, each a list of
+    /// [x, y] points), `fill_rule`, `control_points`, `start_angle` and `end_angle` as on the
+    /// command line (overriding them),
+    /// and `instances` to draw the shape many times, either as a list of
+    /// `{"position": [x, y], "scale": s, "rotation": degrees, "color": c, "value": v}`, where
+    /// `value` picks the color from `colormap` (a name or file, as for --colormap) over the
+    /// values' extent or `value_range` [min, max], or as
+    /// `{"grid": {"columns": n, "rows": m}}`. `nodes` adds a tree of further shapes, each
+    /// `{"shape", "size", "points", "fill_rule", "control_points", "start_angle", "end_angle",
+    /// "color", "position", "scale", "rotation", "z", "children": [...]}`
+    #[arg(long)]
+    scene: Option<PathBuf>,
+
+    /// Draw the scene file's opaque nodes in a single batched draw call; translucent ones are still drawn one by one
+    #[arg(long)]
+    batch: bool,
+
+    /// Load shaders from this directory (falling back to the built-in copy of any missing
+    /// file) and rebuild pipelines whenever a file changes
+    #[arg(long = "shader-dir")]
+    shader_dir: Option<PathBuf>,
+
+    /// Graphics API to use. Defaults to any available
+    #[arg(long, value_enum, global = true)]
+    backend: Option<Backend>,
+
+    /// Which GPU to prefer when several are available
+    #[arg(long = "power-preference", value_enum, default_value = "none", global = true)]
+    power_preference: PowerPreference,
+
+    /// Use a software adapter instead of a GPU
+    #[arg(long = "force-fallback-adapter", global = true)]
+    force_fallback_adapter: bool,
+
+    /// Swap chain present mode; falls back to a supported mode if unavailable
+    #[arg(long = "present-mode", value_enum, default_value = "auto")]
+    present_mode: PresentMode,
+
+    /// Cap the frame rate of continuous rendering
+    #[arg(long = "max-fps", conflicts_with = "on_demand", value_parser = pacing::parse_max_fps)]
+    max_fps: Option<f32>,
+
+    /// Redraw only when something changes (resize, shader reload) instead of continuously.
+    /// A --frag shader or --pen animates, so with either every frame is still drawn
+    #[arg(long = "on-demand")]
+    on_demand: bool,
+
+    /// Write frame statistics (FPS, CPU and GPU frame time percentiles) to this JSON file on exit
+    #[arg(long = "stats-json")]
+    stats_json: Option<PathBuf>,
+
     /// Draw X/Y axes with tick marks
     #[arg(long)]
     axis: bool,
@@ -37,45 +336,104 @@ struct Cli {
     /// Draw X/Y axes with a full grid
     #[arg(long = "axis-grid")]
     axis_grid: bool,
+
+    /// How values are spaced along the X axis. The axes, --implicit, --param, --vector-field
+    /// and --heatmap all follow it; log axes get ticks at each decade and minor ticks between
+    #[arg(long = "x-scale", value_enum, default_value = "linear")]
+    x_scale: ScaleKind,
+
+    /// Values at the left and right edges of the view [default: -1 1, 0.01 100 on a log scale,
+    /// -100 100 on a symlog one]
+    #[arg(long = "x-range", num_args = 2, value_names = ["MIN", "MAX"], allow_negative_numbers = true)]
+    x_range: Option<Vec<f32>>,
+
+    /// How values are spaced along the Y axis, as for --x-scale
+    #[arg(long = "y-scale", value_enum, default_value = "linear")]
+    y_scale: ScaleKind,
+
+    /// Values at the bottom and top edges of the view, as for --x-range
+    #[arg(long = "y-range", num_args = 2, value_names = ["MIN", "MAX"], allow_negative_numbers = true)]
+    y_range: Option<Vec<f32>>,
+
+    /// How axis tick dots are drawn
+    #[arg(long = "axis-render-mode", value_enum, default_value = "mesh")]
+    axis_render_mode: RenderMode,
+
+    /// Layer of the axes and grid; the shape is on layer --z
+    #[arg(long = "axis-z", default_value_t = -1.0, allow_negative_numbers = true)]
+    axis_z: f32,
+
+    /// Draw the axes and grid in front of everything else
+    #[arg(long = "axis-on-top", conflicts_with = "axis_z")]
+    axis_on_top: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Validate the built-in shaders and any given WGSL files with naga, including their
+    /// vertex inputs and bindings, without opening a window. Exits non-zero on failure
+    CheckShaders {
+        /// WGSL files to check: `--frag` files, replacements for built-in shaders (checked
+        /// by file name in the pipelines they belong to) or standalone modules
+        files: Vec<PathBuf>,
+
+        /// Check the built-in shaders as loaded with this --shader-dir
+        #[arg(long = "shader-dir")]
+        shader_dir: Option<PathBuf>,
+
+        /// Print results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// List every GPU adapter with its features, limits and supported surface formats
+    Adapters,
 }
 
 fn parse_color(s: &str) -> [f32; 4] {
-    match s.to_lowercase().as_str() {
-        "red"     => [1.0, 0.0, 0.0, 1.0],
-        "green"   => [0.0, 1.0, 0.0, 1.0],
-        "blue"    => [0.0, 0.0, 1.0, 1.0],
-        "white"   => [1.0, 1.0, 1.0, 1.0],
-        "black"   => [0.0, 0.0, 0.0, 1.0],
-        "yellow"  => [1.0, 1.0, 0.0, 1.0],
-        "cyan"    => [0.0, 1.0, 1.0, 1.0],
-        "magenta" => [1.0, 0.0, 1.0, 1.0],
-        "orange"  => [1.0, 0.5, 0.0, 1.0],
-        "purple"  => [0.5, 0.0, 0.5, 1.0],
-        _         => [1.0, 0.0, 0.0, 1.0],
-    }
+    color::parse(s).unwrap_or([1.0, 0.0, 0.0, 1.0])
 }
 
+const TITLE: &str = "Cheesecake";
+
 struct App {
     state: Option<State>,
     config: Option<ShapeConfig>,
+    pacer: FramePacer,
+    stats_json: Option<PathBuf>,
+    // Shown in the title bar after the frame statistics
+    hover: Option<String>,
+    summary: Option<String>,
 }
 
 impl App {
-    fn new(config: ShapeConfig) -> Self {
-        Self { state: None, config: Some(config) }
+    fn new(config: ShapeConfig, pacer: FramePacer, stats_json: Option<PathBuf>) -> Self {
+        Self { state: None, config: Some(config), pacer, stats_json, hover: None, summary: None }
     }
 }
 
+fn set_title(window: &Window, summary: Option<&str>, hover: Option<&str>) {
+    let parts = [TITLE].into_iter().chain(summary).chain(hover);
+    window.set_title(&parts.collect::<Vec<_>>().join(" | "));
+}
+
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         let window = Arc::new(
             event_loop
-                .create_window(Window::default_attributes().with_title("Cheesecake"))
+                .create_window(Window::default_attributes().with_title(TITLE))
                 .unwrap(),
         );
 
         let config = self.config.take().unwrap();
-        let state = pollster::block_on(State::new(window.clone(), config));
+        let mut state = pollster::block_on(State::new(window.clone(), config)).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        });
+        let info = state.adapter_info();
+        log::info!("Using {} ({:?})", info.name, info.backend);
+        if self.stats_json.is_some() {
+            state.stats_mut().keep_history();
+        }
         self.state = Some(state);
 
         window.request_redraw();
@@ -91,37 +449,288 @@ impl ApplicationHandler for App {
         match event {
             WindowEvent::CloseRequested => {
                 println!("The close button was pressed; stopping");
+                if let Some(path) = &self.stats_json {
+                    match state.write_stats(path) {
+                        Ok(()) => println!("Wrote frame statistics to {}", path.display()),
+                        Err(e) => eprintln!("{e}"),
+                    }
+                }
                 event_loop.exit();
             }
             WindowEvent::RedrawRequested => {
                 state.render();
-                state.get_window().request_redraw();
+                self.pacer.frame_presented();
+                if let Some(summary) = state.stats_mut().summary() {
+                    self.summary = Some(summary);
+                    set_title(state.get_window().unwrap(), self.summary.as_deref(), self.hover.as_deref());
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let affects_frame = state.set_mouse(position.x as f32, position.y as f32);
+                let hover = state
+                    .hovered_cell()
+                    .map(|cell| format!("row {}, column {}: {}", cell.row, cell.column, cell.value));
+                if hover != self.hover {
+                    self.hover = hover;
+                    set_title(state.get_window().unwrap(), self.summary.as_deref(), self.hover.as_deref());
+                }
+                if affects_frame {
+                    state.get_window().unwrap().request_redraw();
+                }
             }
             WindowEvent::Resized(size) => {
                 state.resize(size);
+                state.get_window().unwrap().request_redraw();
             }
             _ => (),
         }
     }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(state) = self.state.as_mut() else { return };
+        let reloaded = state.reload_shaders();
+        let (redraw, flow) = self.pacer.schedule(state.watching(), state.animating());
+        if redraw || reloaded {
+            state.get_window().unwrap().request_redraw();
+        }
+        event_loop.set_control_flow(flow);
+    }
 }
 
 fn main() {
     env_logger::init();
 
     let cli = Cli::parse();
-    let config = ShapeConfig {
+    let adapter = AdapterOptions {
+        backend: cli.backend,
+        power_preference: cli.power_preference,
+        force_fallback_adapter: cli.force_fallback_adapter,
+    };
+    match cli.command {
+        Some(Command::CheckShaders { files, shader_dir, json }) => {
+            let ok = check::run(&files, shader_dir, json);
+            std::process::exit(if ok { 0 } else { 1 });
+        }
+        Some(Command::Adapters) => {
+            adapter::list(adapter);
+            return;
+        }
+        None => {}
+    }
+    let background = Background::parse(&cli.background, cli.background_fit).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    let texture = cli.texture.as_deref().map(|path| {
+        let image = texture::load_image(path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        });
+        TextureConfig {
+            image,
+            filter: cli.texture_filter,
+            wrap: cli.texture_wrap,
+            scale: cli.texture_scale,
+            tint: cli.tint,
+        }
+    });
+    let frag = cli.frag.as_deref().map(|path| {
+        frag::UserFrag::load(path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        })
+    });
+    let outline = match (&cli.path, &cli.svg) {
+        (Some(d), _) => Some(path::parse(d).map(|subpaths| (subpaths, None))),
+        (None, Some(file)) => Some(path::load_svg(file)),
+        (None, None) => None,
+    }
+    .map(|loaded| {
+        loaded
+            .and_then(|(subpaths, rule)| Ok((path::outline(&subpaths, cli.path_tolerance)?, rule)))
+            .unwrap_or_else(|e| {
+                eprintln!("{e}");
+                std::process::exit(1);
+            })
+    });
+    let scale = |kind, range: Option<Vec<f32>>, flag: &str| {
+        Scale::new(kind, range.map(|r| [r[0], r[1]])).unwrap_or_else(|e| {
+            eprintln!("{flag}: {e}");
+            std::process::exit(1);
+        })
+    };
+    let scales = Scales {
+        x: scale(cli.x_scale, cli.x_range, "--x-range"),
+        y: scale(cli.y_scale, cli.y_range, "--y-range"),
+    };
+    let mut config = ShapeConfig {
         shape: cli.shape,
-        color: parse_color(&cli.color),
+        fill: cli.fill.unwrap_or_else(|| Fill::Solid(parse_color(&cli.color))),
+        texture,
+        frag,
+        stroke: cli.stroke.as_deref().map(|color| Stroke {
+            color: parse_color(color),
+            width: cli.stroke_width,
+            units: cli.stroke_units,
+        }),
         size: cli.size.unwrap_or(0.5),
         position: cli.pos.map(|p| [p[0], p[1]]).unwrap_or([0.0, 0.0]),
+        z: cli.z,
+        render_mode: cli.render_mode,
+        corner_radii: shape::corner_radii(&cli.corner_radius).unwrap_or_else(|e| {
+            eprintln!("--corner-radius: {e}");
+            std::process::exit(1);
+        }),
+        width: cli.width,
+        height: cli.height,
+        rx: cli.rx,
+        ry: cli.ry,
+        inner_ratio: cli.inner_ratio,
+        inner_radius: cli.inner_radius,
+        outer_radius: cli.outer_radius,
+        contours: cli.points,
+        closed: Vec::new(),
+        fill_rule: cli.fill_rule,
+        control_points: cli.control_points.unwrap_or_default(),
+        start_angle: cli.start_angle,
+        end_angle: cli.end_angle,
+        curve_tolerance: cli.curve_tolerance,
+        show_controls: cli.show_controls,
+        from: cli.from.map(|p| [p[0], p[1]]).unwrap_or([-1.0, 0.0]),
+        to: cli.to.map(|p| [p[0], p[1]]).unwrap_or([1.0, 0.0]),
+        head_size: cli.head_size,
+        vector_field: None,
+        colormap: cli.colormap,
+        colorbar: cli.colorbar,
+        heatmap: cli.heatmap.as_deref().map(|path| {
+            let grid = Grid::load(path).unwrap_or_else(|e| {
+                eprintln!("{e}");
+                std::process::exit(1);
+            });
+            let e = &cli.heatmap_extent;
+            Heatmap {
+                grid,
+                extent: [e[0], e[1], e[2], e[3]],
+                range: cli.heatmap_range.map(|r| [r[0], r[1]]),
+                sampling: cli.heatmap_sampling,
+                z: cli.heatmap_z,
+            }
+        }),
+        implicit: cli.implicit.map(|mut curve| {
+            curve.levels = cli.levels.unwrap_or(curve.levels);
+            curve
+        }),
+        parametric: cli.param.map(|mut curve| {
+            curve.range = cli.t.unwrap_or(curve.range);
+            curve
+        }),
+        pen_period: cli.pen,
+        plot_color: parse_color(&cli.plot_color),
+        plot_width: cli.plot_width,
+        background,
+        shader_dir: cli.shader_dir,
+        adapter,
+        present_mode: cli.present_mode,
         axis: cli.axis,
         axis_grid: cli.axis_grid,
+        scales,
         axis_arm_len: cli.size.unwrap_or(1.0),
+        axis_render_mode: cli.axis_render_mode,
+        axis_z: if cli.axis_on_top { pipeline::TOP_LAYER } else { cli.axis_z },
+        instances: None,
+        graph: SceneGraph::new(),
+        batch: cli.batch,
     };
+    // A path is drawn as a polygon of its flattened subpaths
+    if let Some(((contours, closed), rule)) = outline {
+        config.shape = Shape::Polygon;
+        config.contours = contours;
+        config.closed = closed;
+        config.fill_rule = rule.unwrap_or(config.fill_rule);
+    }
+    // A field is drawn as instances of an arrow along +x, colored by the colormap alone
+    if let Some(field) = cli.vector_field {
+        config.vector_field = Some(field);
+        config.shape = Shape::Arrow;
+        config.from = [-1.0, 0.0];
+        config.to = [1.0, 0.0];
+        config.size = cli.size.unwrap_or(field::ARROW_SIZE);
+        config.fill = Fill::Solid([1.0; 4]);
+    }
+    if let Some(path) = &cli.scene {
+        Scene::load(path).and_then(|scene| scene.apply(&mut config)).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        });
+    }
+    if let Some(field) = &mut config.vector_field {
+        field.colormap = config.colormap.clone();
+        // Which samples are drawn does not depend on the aspect ratio
+        if field.instances(&config.scales, 1.0).is_empty() {
+            eprintln!("--vector-field: the field has no finite non-zero samples in view, so no arrows are drawn");
+        }
+    }
+    if config.pen_period.is_some_and(|period| period.is_nan() || period <= 0.0) {
+        eprintln!("--pen: the period must be a positive number of seconds");
+        std::process::exit(1);
+    }
+    config.check_control_points().unwrap_or_else(|e| {
+        eprintln!("--control-points: {e}");
+        std::process::exit(1);
+    });
+    config.check_dimensions().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    config.check_sweep().unwrap_or_else(|e| {
+        eprintln!("--start-angle, --end-angle: {e}");
+        std::process::exit(1);
+    });
+    if config.fill == Fill::None
+        && config.texture.is_none()
+        && config.frag.is_none()
+        && config.stroke.is_none()
+        && config.graph.is_empty()
+    {
+        eprintln!("--fill none without --stroke leaves nothing to draw");
+    }
+    if matches!(config.shape, Shape::Polygon) && config.contours.is_empty() {
+        eprintln!("--shape polygon without --points leaves nothing to draw");
+    }
+    // Points in a line, or a path of lines alone or doubling back on itself, only show when
+    // stroked
+    if matches!(config.shape, Shape::Polygon)
+        && !config.contours.is_empty()
+        && config.stroke.is_none()
+        && config.vertices(1).is_empty()
+    {
+        let flag = match (&cli.path, &cli.svg) {
+            (Some(_), _) => "--path",
+            (None, Some(_)) => "--svg",
+            (None, None) => "--points",
+        };
+        eprintln!("{flag}: the outline has no area, so there is nothing to fill; add --stroke to draw it");
+        std::process::exit(1);
+    }
+    if config.show_controls && config.control_polygon().is_empty() {
+        eprintln!("--show-controls only marks Bézier and arc shapes");
+    }
+    if config.render_mode != config.effective_render_mode() {
+        match config.instances {
+            Some(_) => eprintln!("Instanced shapes are always drawn as meshes"),
+            None => eprintln!("This shape has no SDF form; drawing it as a mesh"),
+        }
+    }
+    if config.instances.is_some() && config.stroke.is_some() {
+        eprintln!("--stroke is not drawn for instanced shapes");
+    }
+    if cli.on_demand && config.animated() {
+        eprintln!("--on-demand: --frag and --pen animate, so frames are still drawn continuously");
+    }
 
     let event_loop = EventLoop::new().unwrap();
-    event_loop.set_control_flow(ControlFlow::Poll);
+    event_loop.set_control_flow(ControlFlow::Wait);
 
-    let mut app = App::new(config);
+    let mut app = App::new(config, FramePacer::new(cli.max_fps, cli.on_demand), cli.stats_json);
     event_loop.run_app(&mut app).unwrap();
 }
@@ -0,0 +1,124 @@
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use winit::event_loop::ControlFlow;
+
+/// How often shader directories are checked for changes while idle in on-demand mode.
+const RELOAD_POLL: Duration = Duration::from_millis(200);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum PresentMode {
+    /// Vsync, using adaptive vsync where available
+    #[default]
+    Auto,
+    /// Vsync; always supported
+    Fifo,
+    /// Vsync without blocking: the newest frame replaces the queued one
+    Mailbox,
+    /// No vsync; may tear
+    Immediate,
+}
+
+impl PresentMode {
+    /// The mode to configure given what the surface supports, with a warning when the
+    /// requested one is unavailable.
+    pub fn choose(self, supported: &[wgpu::PresentMode]) -> (wgpu::PresentMode, Option<String>) {
+        let requested = match self {
+            // wgpu resolves these to the best supported mode itself
+            PresentMode::Auto => return (wgpu::PresentMode::AutoVsync, None),
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+        };
+        if supported.contains(&requested) {
+            return (requested, None);
+        }
+        // Keep the intent: a non-blocking mode if there is one, else vsync
+        let fallback = match self {
+            PresentMode::Immediate => wgpu::PresentMode::AutoNoVsync,
+            _ => wgpu::PresentMode::Fifo,
+        };
+        (fallback, Some(format!("Present mode {requested:?} is not supported by this surface; using {fallback:?}")))
+    }
+}
+
+/// Parses `--max-fps`: a positive frame rate whose frame interval a `Duration` can hold.
+pub fn parse_max_fps(s: &str) -> Result<f32, String> {
+    let fps: f32 = s.trim().parse().map_err(|_| format!("expected a frame rate such as 60, got '{s}'"))?;
+    if fps.is_nan() || fps <= 0.0 {
+        return Err(format!("the frame rate must be positive, got '{s}'"));
+    }
+    match Duration::try_from_secs_f32(1.0 / fps) {
+        Ok(_) => Ok(fps),
+        Err(_) => Err(format!("the frame rate '{s}' is too low")),
+    }
+}
+
+/// Decides when the event loop draws and how long it may sleep in between.
+pub struct FramePacer {
+    on_demand: bool,
+    min_interval: Option<Duration>,
+    next_frame: Instant,
+}
+
+impl FramePacer {
+    /// `max_fps` caps continuous redraws; `on_demand` draws only when something changed.
+    pub fn new(max_fps: Option<f32>, on_demand: bool) -> FramePacer {
+        FramePacer {
+            on_demand,
+            min_interval: max_fps.and_then(|fps| Duration::try_from_secs_f32(1.0 / fps).ok()),
+            next_frame: Instant::now(),
+        }
+    }
+
+    pub fn frame_presented(&mut self) {
+        let now = Instant::now();
+        if let Some(interval) = self.min_interval {
+            // Stay on the cadence, but don't try to catch up after a stall
+            self.next_frame = (self.next_frame + interval).max(now);
+        }
+    }
+
+    /// Whether to request a redraw now, and the control flow until the next wake-up.
+    /// `watching` keeps an on-demand loop waking up to poll shader watchers; `animating`
+    /// makes it redraw continuously, since nothing else would show the animation.
+    pub fn schedule(&self, watching: bool, animating: bool) -> (bool, ControlFlow) {
+        let now = Instant::now();
+        if self.on_demand && !animating {
+            let flow = if watching { ControlFlow::WaitUntil(now + RELOAD_POLL) } else { ControlFlow::Wait };
+            return (false, flow);
+        }
+        if now >= self.next_frame {
+            // The redraw request wakes the loop again
+            (true, ControlFlow::Wait)
+        } else {
+            (false, ControlFlow::WaitUntil(self.next_frame))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parametric::Parametric;
+    use crate::shape::ShapeConfig;
+
+    #[test]
+    fn on_demand_redraws_only_while_animating() {
+        let pacer = FramePacer::new(None, true);
+        assert_eq!(pacer.schedule(false, false), (false, ControlFlow::Wait));
+        assert!(matches!(pacer.schedule(true, false), (false, ControlFlow::WaitUntil(_))));
+
+        let curve = Parametric::parse("cos(t), sin(t)").unwrap();
+        let pen = ShapeConfig { parametric: Some(curve), pen_period: Some(2.0), ..Default::default() };
+        assert!(pen.animated());
+        // Every wake-up draws the next frame
+        for _ in 0..3 {
+            assert_eq!(pacer.schedule(false, pen.animated()), (true, ControlFlow::Wait));
+        }
+
+        // A curve without a pen is drawn once
+        let still = ShapeConfig { pen_period: None, ..pen };
+        assert!(!still.animated());
+    }
+}
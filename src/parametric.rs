@@ -0,0 +1,234 @@
+// Parametric curves such as `cos(3t), sin(2t)`, sampled where they bend.
+//
+// The parameter range starts out as a fixed number of even steps, and a step is halved
+// while the curve's point at its middle strays from the chord between its ends by more than
+// the tolerance, measured on screen. Straight stretches stay coarse while tight turns get as
+// many points as their curvature needs. A step still bending at the depth limit is taken to
+// jump, as at a pole of `tan(t)`, and the curve is broken there instead of joined across.
+
+use crate::expr::Expr;
+use crate::scale::Scales;
+use crate::tessellate::Contour;
+
+// Even steps sampled before refining, enough not to step over a whole loop of most curves
+const INITIAL_STEPS: usize = 64;
+const MAX_DEPTH: u32 = 12;
+
+/// A curve `(x(t), y(t))` in axis coordinates over a range of `t`.
+#[derive(Clone, Debug)]
+pub struct Parametric {
+    components: [Expr; 2],
+    pub range: [f32; 2],
+}
+
+impl Parametric {
+    /// Parses two expressions of `t`, such as `cos(3t), sin(2t)`, over 0..2π.
+    pub fn parse(s: &str) -> Result<Parametric, String> {
+        let list = Expr::parse_list(s, &["t"]).map_err(|e| format!("parametric curve: {e}"))?;
+        let components: [Expr; 2] = list
+            .try_into()
+            .map_err(|list: Vec<Expr>| format!("parametric curve: expected 2 components, got {}", list.len()))?;
+        Ok(Parametric { components, range: [0.0, std::f32::consts::TAU] })
+    }
+
+    /// The curve's point at `t`.
+    pub fn at(&self, t: f32) -> [f32; 2] {
+        self.components.each_ref().map(|c| c.eval(&[f64::from(t)]) as f32)
+    }
+
+    /// The point `fraction` of the way through the range of `t`.
+    pub fn at_fraction(&self, fraction: f32) -> [f32; 2] {
+        let [start, end] = self.range;
+        self.at(start + (end - start) * fraction)
+    }
+
+    /// The curve as polylines in NDC within `tolerance` of it, in NDC Y units on a surface
+    /// `aspect` (width / height) wide, with whether each is closed. Points are placed by
+    /// `scales`, and the curve is split where it is undefined, off a log axis, or jumps.
+    pub fn polylines(&self, scales: &Scales, aspect: f32, tolerance: f32) -> (Vec<Contour>, Vec<bool>) {
+        let point = |t: f32| scales.to_ndc(self.at(t));
+        let [start, end] = self.range;
+        let step = (end - start) / INITIAL_STEPS as f32;
+        let mut samples = vec![point(start)];
+        for k in 0..INITIAL_STEPS {
+            let ta = start + step * k as f32;
+            let tb = if k + 1 == INITIAL_STEPS { end } else { ta + step };
+            let pa = *samples.last().unwrap();
+            refine(&point, (ta, pa), (tb, point(tb)), 0, aspect, tolerance, &mut samples);
+        }
+
+        let mut polylines: Vec<Contour> = Vec::new();
+        let mut current = Vec::new();
+        for p in samples {
+            if p.iter().all(|c| c.is_finite()) {
+                current.push(p);
+            } else if current.len() > 1 {
+                polylines.push(std::mem::take(&mut current));
+            } else {
+                current.clear();
+            }
+        }
+        if current.len() > 1 {
+            polylines.push(current);
+        }
+
+        // A curve that returns to where it started is closed, so that its ends are joined
+        let mut closed = vec![false; polylines.len()];
+        if let [line] = &mut polylines[..]
+            && line.len() > 2
+            && screen_distance(line[0], *line.last().unwrap(), aspect) <= tolerance
+        {
+            line.pop();
+            closed[0] = true;
+        }
+        (polylines, closed)
+    }
+}
+
+// Appends the points after `pa` up to and including `pb`, the curve's `point` at `ta` and `tb`,
+// halving the step between them until it is flat. A break in the curve is marked with a NaN point
+fn refine(
+    point: &impl Fn(f32) -> [f32; 2],
+    (ta, pa): (f32, [f32; 2]),
+    (tb, pb): (f32, [f32; 2]),
+    depth: u32,
+    aspect: f32,
+    tolerance: f32,
+    out: &mut Vec<[f32; 2]>,
+) {
+    let tm = 0.5 * (ta + tb);
+    let pm = point(tm);
+    let finite = [pa, pm, pb].map(|p| p.iter().all(|c| c.is_finite()));
+    let flat = match finite {
+        [true, true, true] => distance_to_segment(pm, pa, pb, aspect) <= tolerance,
+        // Nothing to draw, or the edge of where the curve is defined, which is found by
+        // halving like a bend
+        [false, false, false] => true,
+        _ => false,
+    };
+    if flat {
+        out.push(pb);
+    } else if depth == MAX_DEPTH {
+        if finite[0] && finite[2] {
+            out.push([f32::NAN; 2]);
+        }
+        out.push(pb);
+    } else {
+        refine(point, (ta, pa), (tm, pm), depth + 1, aspect, tolerance, out);
+        refine(point, (tm, pm), (tb, pb), depth + 1, aspect, tolerance, out);
+    }
+}
+
+/// Parses a range of `t` such as `0..6.28` or `-pi..pi`. Either end may be an expression.
+pub fn parse_range(s: &str) -> Result<[f32; 2], String> {
+    let (start, end) = s
+        .split_once("..")
+        .ok_or_else(|| format!("expected a range START..END such as 0..6.28, got '{s}'"))?;
+    let bound = |side: &str, which: &str| {
+        let expr = Expr::parse(side, &[]).map_err(|e| format!("range {which} '{}': {e}", side.trim()))?;
+        let value = expr.eval(&[]) as f32;
+        if value.is_finite() {
+            Ok(value)
+        } else {
+            Err(format!("range {which} '{}' is not a finite number", side.trim()))
+        }
+    };
+    let (start, end) = (bound(start, "start")?, bound(end, "end")?);
+    if start >= end {
+        return Err(format!("empty range {s}: the start must be less than the end"));
+    }
+    Ok([start, end])
+}
+
+fn screen_distance(a: [f32; 2], b: [f32; 2], aspect: f32) -> f32 {
+    ((b[0] - a[0]) * aspect).hypot(b[1] - a[1])
+}
+
+// Distance on screen from p to the segment from a to b
+fn distance_to_segment(p: [f32; 2], a: [f32; 2], b: [f32; 2], aspect: f32) -> f32 {
+    let (dx, dy) = ((b[0] - a[0]) * aspect, b[1] - a[1]);
+    let (px, py) = ((p[0] - a[0]) * aspect, p[1] - a[1]);
+    let len2 = dx * dx + dy * dy;
+    let s = if len2 > 0.0 { ((px * dx + py * dy) / len2).clamp(0.0, 1.0) } else { 0.0 };
+    (px - s * dx).hypot(py - s * dy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scale::{Scale, ScaleKind};
+
+    #[test]
+    fn circle_is_closed_and_within_tolerance() {
+        let curve = Parametric::parse("(0.5 cos(t), 0.5 sin(t))").unwrap();
+        let (lines, closed) = curve.polylines(&Scales::default(), 1.0, 1e-3);
+        assert_eq!(closed, [true]);
+        // Chords stay within the tolerance of the arc they cut off
+        let line = &lines[0];
+        for pair in line.windows(2) {
+            let half = screen_distance(pair[0], pair[1], 1.0) / 2.0;
+            assert!(0.5 - (0.25 - half * half).sqrt() <= 1e-3 + 1e-6);
+        }
+        assert!(line.iter().all(|&[x, y]| (x.hypot(y) - 0.5).abs() < 1e-5));
+    }
+
+    #[test]
+    fn samples_gather_where_the_curve_bends() {
+        // A parabola bends most at its vertex, t = 0
+        let mut curve = Parametric::parse("t, 10t^2").unwrap();
+        curve.range = [-1.0, 1.0];
+        let (lines, closed) = curve.polylines(&Scales::default(), 1.0, 1e-4);
+        assert_eq!(closed, [false]);
+        let lengths = |keep: fn(f32) -> bool| {
+            let pairs = lines[0].windows(2).filter(move |pair| keep(pair[0][0]));
+            pairs.map(|pair| screen_distance(pair[0], pair[1], 1.0)).collect::<Vec<f32>>()
+        };
+        let near = lengths(|x| x.abs() < 0.05).into_iter().fold(0.0, f32::max);
+        let far = lengths(|x| x > 0.8).into_iter().fold(f32::INFINITY, f32::min);
+        assert!(near < 0.25 * far, "chords of {near} near the vertex, {far} far from it");
+        // A line needs only the initial steps
+        let (lines, _) = Parametric::parse("t, 2t").unwrap().polylines(&Scales::default(), 1.0, 1e-4);
+        assert_eq!(lines[0].len(), INITIAL_STEPS + 1);
+    }
+
+    #[test]
+    fn breaks_at_poles_and_gaps() {
+        let mut curve = Parametric::parse("t, tan(t)").unwrap();
+        curve.range = [-3.0, 3.0];
+        // Poles at ±π/2 split the curve in three
+        let (lines, _) = curve.polylines(&Scales::default(), 1.0, 1e-3);
+        assert_eq!(lines.len(), 3);
+        let mut curve = Parametric::parse("t, sqrt(t)").unwrap();
+        curve.range = [-1.0, 1.0];
+        let (lines, _) = curve.polylines(&Scales::default(), 1.0, 1e-3);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0][0][0] < 1e-3);
+    }
+
+    #[test]
+    fn log_axes() {
+        // y = x is the diagonal of log axes with the same range, and needs no refining, while
+        // its points at t <= 0 are not on the axes at all
+        let log = Scale::new(ScaleKind::Log, None).unwrap();
+        let mut curve = Parametric::parse("t, t").unwrap();
+        curve.range = [0.01, 100.0];
+        let (lines, _) = curve.polylines(&Scales { x: log, y: log }, 1.0, 1e-4);
+        assert_eq!(lines[0].len(), INITIAL_STEPS + 1);
+        assert!(lines[0].iter().all(|&[x, y]| (x - y).abs() < 1e-5));
+        curve.range = [-1.0, 100.0];
+        let (lines, _) = curve.polylines(&Scales { x: log, y: log }, 1.0, 1e-4);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0][0][0] < -0.9);
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(parse_range("0..6.5").unwrap(), [0.0, 6.5]);
+        assert_eq!(parse_range("-pi..pi").unwrap(), [-std::f32::consts::PI, std::f32::consts::PI]);
+        assert!(parse_range("0-6").unwrap_err().contains("START..END"));
+        assert!(parse_range("1..0").unwrap_err().contains("empty range"));
+        assert!(parse_range("0..1/0").unwrap_err().contains("not a finite number"));
+        assert!(parse_range("0..q").unwrap_err().contains("range end 'q'"));
+        assert!(Parametric::parse("cos(t)").unwrap_err().contains("expected 2 components"));
+    }
+}
@@ -0,0 +1,576 @@
+// SVG path data (the `d` attribute) and curve flattening.
+//
+// Paths are parsed into subpaths of lines, Béziers and elliptical arcs in SVG coordinates
+// (y down), then flattened into polylines whose distance from the true curve stays under a
+// tolerance, ready for `tessellate` and `stroke_path`.
+
+use std::f32::consts::{PI, TAU};
+use std::path::Path;
+
+use crate::tessellate::{Contour, FillRule};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment {
+    Line([f32; 2]),
+    /// Control point and end
+    Quad([f32; 2], [f32; 2]),
+    /// Two control points and end
+    Cubic([f32; 2], [f32; 2], [f32; 2]),
+    Arc(Arc),
+}
+
+/// An elliptical arc in centre form.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Arc {
+    pub center: [f32; 2],
+    pub radii: [f32; 2],
+    /// Rotation of the ellipse's x axis, radians
+    pub rotation: f32,
+    /// Radians, before `rotation`
+    pub start: f32,
+    /// Signed angle swept from `start`
+    pub sweep: f32,
+}
+
+impl Arc {
+    pub fn point(&self, angle: f32) -> [f32; 2] {
+        let (s, c) = self.rotation.sin_cos();
+        let (x, y) = (self.radii[0] * angle.cos(), self.radii[1] * angle.sin());
+        [self.center[0] + c * x - s * y, self.center[1] + s * x + c * y]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subpath {
+    pub start: [f32; 2],
+    pub segments: Vec<Segment>,
+    /// Ended with Z: the stroke joins back to `start`. Fills always close.
+    pub closed: bool,
+}
+
+impl Subpath {
+    /// The outline as a polyline within `tolerance` of every curve, starting at `start`.
+    pub fn flatten(&self, tolerance: f32) -> Contour {
+        let mut points = vec![self.start];
+        for segment in &self.segments {
+            let from = *points.last().unwrap();
+            match *segment {
+                Segment::Line(p) => points.push(p),
+                Segment::Quad(c, p) => flatten_quad(from, c, p, tolerance, &mut points),
+                Segment::Cubic(c1, c2, p) => flatten_cubic(from, c1, c2, p, tolerance, &mut points),
+                Segment::Arc(arc) => flatten_arc(&arc, tolerance, &mut points),
+            }
+        }
+        // Z draws the closing edge itself
+        if self.closed && points.len() > 1 && points.last() == Some(&self.start) {
+            points.pop();
+        }
+        points
+    }
+
+    // Every point the outline can reach lies within the hull of these
+    fn hull_points(&self) -> Vec<[f32; 2]> {
+        let mut points = vec![self.start];
+        for segment in &self.segments {
+            match *segment {
+                Segment::Line(p) => points.push(p),
+                Segment::Quad(c, p) => points.extend([c, p]),
+                Segment::Cubic(c1, c2, p) => points.extend([c1, c2, p]),
+                Segment::Arc(arc) => {
+                    let r = arc.radii[0].max(arc.radii[1]);
+                    let [x, y] = arc.center;
+                    points.extend([[x - r, y - r], [x + r, y + r]]);
+                }
+            }
+        }
+        points
+    }
+}
+
+/// Appends the points after `p0` of a quadratic Bézier, with chords within `tolerance` of the curve.
+pub fn flatten_quad(p0: [f32; 2], c: [f32; 2], p1: [f32; 2], tolerance: f32, out: &mut Vec<[f32; 2]>) {
+    // The same curve as a cubic
+    let c1 = std::array::from_fn(|k| p0[k] + 2.0 / 3.0 * (c[k] - p0[k]));
+    let c2 = std::array::from_fn(|k| p1[k] + 2.0 / 3.0 * (c[k] - p1[k]));
+    flatten_cubic(p0, c1, c2, p1, tolerance, out);
+}
+
+/// Appends the points after `p0` of a cubic Bézier, with chords within `tolerance` of the curve.
+/// Halves the curve until each piece is flat enough, so points gather where it bends most.
+pub fn flatten_cubic(
+    p0: [f32; 2],
+    c1: [f32; 2],
+    c2: [f32; 2],
+    p1: [f32; 2],
+    tolerance: f32,
+    out: &mut Vec<[f32; 2]>,
+) {
+    subdivide([p0, c1, c2, p1], tolerance, MAX_DEPTH, out);
+}
+
+// Deep enough for 65536 chords, and stops runaway recursion on non-finite input
+const MAX_DEPTH: u32 = 16;
+
+fn subdivide([p0, c1, c2, p1]: [[f32; 2]; 4], tolerance: f32, depth: u32, out: &mut Vec<[f32; 2]>) {
+    // A cubic strays at most 3/4 of its control points' distance from the chord
+    if depth == 0 || 0.75 * distance_to_chord(c1, p0, p1).max(distance_to_chord(c2, p0, p1)) <= tolerance {
+        out.push(p1);
+        return;
+    }
+    // de Casteljau at t = 0.5
+    let mid = |a: [f32; 2], b: [f32; 2]| [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0];
+    let (a, b, c) = (mid(p0, c1), mid(c1, c2), mid(c2, p1));
+    let (d, e) = (mid(a, b), mid(b, c));
+    let m = mid(d, e);
+    subdivide([p0, a, d, m], tolerance, depth - 1, out);
+    subdivide([m, e, c, p1], tolerance, depth - 1, out);
+}
+
+fn distance_to_chord(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let len = length([dx, dy]);
+    if len == 0.0 {
+        return length([p[0] - a[0], p[1] - a[1]]);
+    }
+    ((p[0] - a[0]) * dy - (p[1] - a[1]) * dx).abs() / len
+}
+
+/// Appends the points after the start of `arc`, with chords within `tolerance` of the curve.
+pub fn flatten_arc(arc: &Arc, tolerance: f32, out: &mut Vec<[f32; 2]>) {
+    // A chord spanning angle θ on a circle of radius r deviates r (1 - cos(θ / 2))
+    let r = arc.radii[0].max(arc.radii[1]);
+    let step = if tolerance < r { 2.0 * (1.0 - tolerance / r).acos() } else { PI };
+    let n = segments(arc.sweep.abs() / step);
+    out.extend((1..=n).map(|i| arc.point(arc.start + arc.sweep * i as f32 / n as f32)));
+}
+
+// Chord count for an arc needing `estimate` chords, capped so that a huge arc cannot
+// exhaust memory
+fn segments(estimate: f32) -> u32 {
+    if estimate.is_finite() { (estimate.ceil() as u32).clamp(1, 4096) } else { 1 }
+}
+
+fn length([x, y]: [f32; 2]) -> f32 {
+    (x * x + y * y).sqrt()
+}
+
+/// Parses SVG path data: M, L, H, V, C, S, Q, T, A and Z, absolute (upper case) or
+/// relative (lower case), with implicitly repeated commands.
+pub fn parse(d: &str) -> Result<Vec<Subpath>, String> {
+    let mut p = Parser { s: d.as_bytes(), i: 0 };
+    let mut subpaths = Vec::new();
+    let mut current: Option<Subpath> = None;
+    let mut pos = [0.0f32; 2];
+    let mut start = [0.0f32; 2];
+    // Control point to reflect for S (after C/S) or T (after Q/T)
+    let mut last_cubic: Option<[f32; 2]> = None;
+    let mut last_quad: Option<[f32; 2]> = None;
+    let mut command = None;
+
+    loop {
+        p.skip_separators();
+        let Some(&next) = p.s.get(p.i) else { break };
+        if next.is_ascii_alphabetic() {
+            if command.is_none() && !matches!(next, b'M' | b'm') {
+                return Err(p.error("path data must start with M"));
+            }
+            command = Some(next);
+            p.i += 1;
+        } else {
+            command = match command {
+                None => return Err(p.error("path data must start with M")),
+                Some(b'Z' | b'z') => return Err(p.error("unexpected number after Z")),
+                // Coordinates after a move are implicit line-tos
+                Some(b'M') => Some(b'L'),
+                Some(b'm') => Some(b'l'),
+                c => c,
+            };
+        }
+        let c = command.unwrap();
+        let relative = c.is_ascii_lowercase();
+        let origin = if relative { pos } else { [0.0, 0.0] };
+        let at = |p: [f32; 2]| [origin[0] + p[0], origin[1] + p[1]];
+
+        let (segment, end) = match c.to_ascii_uppercase() {
+            b'M' => {
+                let to = at(p.point()?);
+                subpaths.extend(current.take());
+                current = Some(Subpath { start: to, segments: Vec::new(), closed: false });
+                (pos, start) = (to, to);
+                (last_cubic, last_quad) = (None, None);
+                continue;
+            }
+            b'Z' => {
+                if let Some(mut subpath) = current.take() {
+                    subpath.closed = true;
+                    subpaths.push(subpath);
+                }
+                pos = start;
+                (last_cubic, last_quad) = (None, None);
+                continue;
+            }
+            b'L' => line(at(p.point()?)),
+            b'H' => line([origin[0] + p.number()?, pos[1]]),
+            b'V' => line([pos[0], origin[1] + p.number()?]),
+            b'C' => {
+                let (c1, c2, to) = (at(p.point()?), at(p.point()?), at(p.point()?));
+                (Segment::Cubic(c1, c2, to), to)
+            }
+            b'S' => {
+                let c1 = last_cubic.map_or(pos, |c| reflect(c, pos));
+                let (c2, to) = (at(p.point()?), at(p.point()?));
+                (Segment::Cubic(c1, c2, to), to)
+            }
+            b'Q' => {
+                let (c, to) = (at(p.point()?), at(p.point()?));
+                (Segment::Quad(c, to), to)
+            }
+            b'T' => {
+                let to = at(p.point()?);
+                (Segment::Quad(last_quad.map_or(pos, |c| reflect(c, pos)), to), to)
+            }
+            b'A' => {
+                let radii = [p.number()?, p.number()?];
+                let rotation = p.number()?.to_radians();
+                let (large, sweep) = (p.flag()?, p.flag()?);
+                let to = at(p.point()?);
+                (arc_segment(pos, radii, rotation, large, sweep, to), to)
+            }
+            _ => return Err(format!("path: unknown command '{}' at offset {}", c as char, p.i - 1)),
+        };
+
+        (last_cubic, last_quad) = match segment {
+            Segment::Cubic(_, c2, _) => (Some(c2), None),
+            Segment::Quad(c, _) => (None, Some(c)),
+            _ => (None, None),
+        };
+        // Drawing after Z continues from the closed subpath's start
+        current.get_or_insert_with(|| Subpath { start, segments: Vec::new(), closed: false }).segments.push(segment);
+        pos = end;
+    }
+    subpaths.extend(current);
+    Ok(subpaths)
+}
+
+fn line(to: [f32; 2]) -> (Segment, [f32; 2]) {
+    (Segment::Line(to), to)
+}
+
+fn reflect(c: [f32; 2], about: [f32; 2]) -> [f32; 2] {
+    [2.0 * about[0] - c[0], 2.0 * about[1] - c[1]]
+}
+
+// Converts the endpoint form of an SVG arc to centre form (SVG 1.1, appendix F.6.5)
+fn arc_segment(from: [f32; 2], radii: [f32; 2], rotation: f32, large: bool, sweep: bool, to: [f32; 2]) -> Segment {
+    let (mut rx, mut ry) = (radii[0].abs(), radii[1].abs());
+    if rx == 0.0 || ry == 0.0 || from == to {
+        return Segment::Line(to);
+    }
+    let (s, c) = rotation.sin_cos();
+    let (hx, hy) = ((from[0] - to[0]) / 2.0, (from[1] - to[1]) / 2.0);
+    let (x1, y1) = (c * hx + s * hy, -s * hx + c * hy);
+    // Radii too small to reach are scaled up until the arc just fits
+    let fit = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if fit > 1.0 {
+        (rx, ry) = (rx * fit.sqrt(), ry * fit.sqrt());
+    }
+    let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut k = (num / den).max(0.0).sqrt();
+    if large == sweep {
+        k = -k;
+    }
+    let (cx1, cy1) = (k * rx * y1 / ry, -k * ry * x1 / rx);
+    let center = [c * cx1 - s * cy1 + (from[0] + to[0]) / 2.0, s * cx1 + c * cy1 + (from[1] + to[1]) / 2.0];
+    let start = ((y1 - cy1) / ry).atan2((x1 - cx1) / rx);
+    let end = ((-y1 - cy1) / ry).atan2((-x1 - cx1) / rx);
+    let mut delta = end - start;
+    if sweep && delta < 0.0 {
+        delta += TAU;
+    } else if !sweep && delta > 0.0 {
+        delta -= TAU;
+    }
+    Segment::Arc(Arc { center, radii: [rx, ry], rotation, start, sweep: delta })
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+    i: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> String {
+        format!("path: {message} at offset {}", self.i)
+    }
+
+    fn skip_separators(&mut self) {
+        while self.s.get(self.i).is_some_and(|b| b.is_ascii_whitespace() || *b == b',') {
+            self.i += 1;
+        }
+    }
+
+    fn number(&mut self) -> Result<f32, String> {
+        self.skip_separators();
+        let start = self.i;
+        let digits = |p: &mut Self| {
+            let from = p.i;
+            while p.s.get(p.i).is_some_and(u8::is_ascii_digit) {
+                p.i += 1;
+            }
+            p.i > from
+        };
+        if matches!(self.s.get(self.i), Some(b'+' | b'-')) {
+            self.i += 1;
+        }
+        let mut any = digits(self);
+        // A second '.' starts the next number, as in "0.5.5"
+        if self.s.get(self.i) == Some(&b'.') {
+            self.i += 1;
+            any |= digits(self);
+        }
+        if any && matches!(self.s.get(self.i), Some(b'e' | b'E')) {
+            let mantissa_end = self.i;
+            self.i += 1;
+            if matches!(self.s.get(self.i), Some(b'+' | b'-')) {
+                self.i += 1;
+            }
+            if !digits(self) {
+                self.i = mantissa_end;
+            }
+        }
+        if !any {
+            self.i = start;
+            return Err(self.error("expected a number"));
+        }
+        let text = std::str::from_utf8(&self.s[start..self.i]).unwrap();
+        text.parse().map_err(|_| self.error("invalid number"))
+    }
+
+    fn point(&mut self) -> Result<[f32; 2], String> {
+        Ok([self.number()?, self.number()?])
+    }
+
+    // Arc flags are single digits that need no separator, as in "a1 1 0 00 1 1"
+    fn flag(&mut self) -> Result<bool, String> {
+        self.skip_separators();
+        let flag = match self.s.get(self.i) {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => return Err(self.error("expected a flag (0 or 1)")),
+        };
+        self.i += 1;
+        Ok(flag)
+    }
+}
+
+/// Flattens `subpaths` and fits them into ±1 with y up, as `ShapeConfig::contours`. Returns
+/// the contours and whether each is closed. `tolerance` is a fraction of the larger half-extent.
+pub fn outline(subpaths: &[Subpath], tolerance: f32) -> Result<(Vec<Contour>, Vec<bool>), String> {
+    let hull: Vec<[f32; 2]> = subpaths.iter().flat_map(Subpath::hull_points).collect();
+    let Some((min, max)) = bounds(&hull) else { return Err("path: nothing to draw".to_owned()) };
+    let half = ((max[0] - min[0]).max(max[1] - min[1]) / 2.0).max(f32::MIN_POSITIVE);
+    let contours: Vec<Contour> = subpaths.iter().map(|s| s.flatten(tolerance * half)).collect();
+
+    // Centre on the flattened outline itself, which may not reach the hull
+    let (min, max) = bounds(&contours.concat()).unwrap();
+    let half = ((max[0] - min[0]).max(max[1] - min[1]) / 2.0).max(f32::MIN_POSITIVE);
+    let center = [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0];
+    let contours = contours
+        .into_iter()
+        .map(|contour| contour.iter().map(|&[x, y]| [(x - center[0]) / half, (center[1] - y) / half]).collect())
+        .collect();
+    Ok((contours, subpaths.iter().map(|s| s.closed).collect()))
+}
+
+fn bounds(points: &[[f32; 2]]) -> Option<([f32; 2], [f32; 2])> {
+    let first = *points.first()?;
+    Some(points.iter().fold((first, first), |(min, max), &[x, y]| {
+        ([min[0].min(x), min[1].min(y)], [max[0].max(x), max[1].max(y)])
+    }))
+}
+
+/// Reads the `<path>` elements of an SVG file: their subpaths, and the first `fill-rule`
+/// attribute among them. Transforms, styles and other elements are ignored.
+pub fn load_svg(file: &Path) -> Result<(Vec<Subpath>, Option<FillRule>), String> {
+    let text = std::fs::read_to_string(file).map_err(|e| format!("failed to read {}: {e}", file.display()))?;
+    let mut subpaths = Vec::new();
+    let mut rule = None;
+    let mut rest = text.as_str();
+    while let Some(at) = rest.find("<path") {
+        rest = &rest[at + "<path".len()..];
+        let tag = &rest[..rest.find('>').unwrap_or(rest.len())];
+        if let Some(d) = attribute(tag, "d") {
+            subpaths.extend(parse(d).map_err(|e| format!("{}: {e}", file.display()))?);
+        }
+        rule = rule.or(match attribute(tag, "fill-rule") {
+            Some("evenodd") => Some(FillRule::EvenOdd),
+            Some("nonzero") => Some(FillRule::NonZero),
+            _ => None,
+        });
+    }
+    if subpaths.is_empty() {
+        return Err(format!("{}: no <path> elements with a d attribute", file.display()));
+    }
+    Ok((subpaths, rule))
+}
+
+// Value of `name="..."` (or single-quoted) among a tag's attributes
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag;
+    while let Some(at) = rest.find(name) {
+        let before = rest[..at].chars().next_back();
+        let after = rest[at + name.len()..].trim_start();
+        rest = &rest[at + name.len()..];
+        if !before.is_some_and(char::is_whitespace) {
+            continue;
+        }
+        let Some(value) = after.strip_prefix('=').map(str::trim_start) else { continue };
+        let quote = value.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            continue;
+        }
+        return value[1..].split(quote).next();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(d: &str) -> Vec<Contour> {
+        parse(d).unwrap().iter().map(|s| s.flatten(1e-3)).collect()
+    }
+
+    fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+        (a[0] - b[0]).abs() < 1e-4 && (a[1] - b[1]).abs() < 1e-4
+    }
+
+    #[test]
+    fn absolute_and_relative_commands_agree() {
+        let square = vec![vec![[10.0, 10.0], [20.0, 10.0], [20.0, 20.0], [10.0, 20.0]]];
+        assert_eq!(points("M10 10 L20 10 L20 20 L10 20 Z"), square);
+        assert_eq!(points("m10,10 l10,0 l0,10 l-10,0 z"), square);
+        assert_eq!(points("M10 10 H20 V20 H10 Z"), square);
+        assert_eq!(points("m10 10 h10 v10 h-10 z"), square);
+    }
+
+    #[test]
+    fn implicit_line_tos_and_compact_numbers() {
+        assert_eq!(points("M0,0 10-5.5.5 1e1"), vec![vec![[0.0, 0.0], [10.0, -5.5], [0.5, 1e1]]]);
+        let subpaths = parse("m1 1 2 2").unwrap();
+        assert_eq!(subpaths[0].segments, vec![Segment::Line([3.0, 3.0])]);
+    }
+
+    #[test]
+    fn subpaths_and_closing() {
+        let subpaths = parse("M0 0 L1 0 L1 1 Z L0 1 M5 5 L6 6").unwrap();
+        assert_eq!(subpaths.len(), 3);
+        assert!(subpaths[0].closed);
+        // Drawing on after Z starts from the closed subpath's start
+        assert_eq!(subpaths[1].start, [0.0, 0.0]);
+        assert!(!subpaths[1].closed);
+        assert_eq!(subpaths[2].start, [5.0, 5.0]);
+    }
+
+    #[test]
+    fn smooth_curves_reflect_the_previous_control_point() {
+        let subpaths = parse("M0 0 C0 1 1 1 1 0 S2 -1 2 0 Q3 1 4 0 T6 0").unwrap();
+        assert_eq!(subpaths[0].segments[1], Segment::Cubic([1.0, -1.0], [2.0, -1.0], [2.0, 0.0]));
+        assert_eq!(subpaths[0].segments[3], Segment::Quad([5.0, -1.0], [6.0, 0.0]));
+        // Without a previous curve the control point is the current point
+        assert_eq!(parse("M0 0 T2 0").unwrap()[0].segments[0], Segment::Quad([0.0, 0.0], [2.0, 0.0]));
+    }
+
+    #[test]
+    fn arcs() {
+        // The sweep flag picks the positive-angle direction, which is clockwise with y down
+        let arc = &points("M0 0 A1 1 0 0 1 2 0")[0];
+        assert!(arc.iter().all(|&[x, y]| ((x - 1.0).hypot(y) - 1.0).abs() < 1e-3));
+        assert!(arc.iter().any(|&p| close(p, [1.0, -1.0])));
+        assert!(close(*arc.last().unwrap(), [2.0, 0.0]));
+        // Radii too small to span the end points are scaled up
+        let Segment::Arc(arc) = parse("M0 0 A0.1 0.1 0 0 1 2 0").unwrap()[0].segments[0] else { panic!() };
+        assert!(close(arc.radii, [1.0, 1.0]));
+        // Flags need no separators; zero radii draw a line
+        let segments = &parse("M0 0 a1 1 0 102 0 a0 1 0 0 0 1 1").unwrap()[0].segments;
+        assert!(matches!(segments[0], Segment::Arc(Arc { sweep, .. }) if sweep < 0.0));
+        assert_eq!(segments[1], Segment::Line([3.0, 1.0]));
+    }
+
+    #[test]
+    fn flattening_stays_within_tolerance() {
+        let arc = Arc { center: [0.0, 0.0], radii: [10.0, 10.0], rotation: 0.0, start: 0.0, sweep: TAU };
+        for tolerance in [1.0, 0.1, 0.01] {
+            let mut points = vec![arc.point(0.0)];
+            flatten_arc(&arc, tolerance, &mut points);
+            for pair in points.windows(2) {
+                let mid = [(pair[0][0] + pair[1][0]) / 2.0, (pair[0][1] + pair[1][1]) / 2.0];
+                assert!(10.0 - length(mid) <= tolerance * 1.01);
+            }
+        }
+        let mut coarse = Vec::new();
+        let mut fine = Vec::new();
+        flatten_cubic([0.0, 0.0], [0.0, 1.0], [1.0, 1.0], [1.0, 0.0], 0.1, &mut coarse);
+        flatten_cubic([0.0, 0.0], [0.0, 1.0], [1.0, 1.0], [1.0, 0.0], 0.001, &mut fine);
+        assert!(fine.len() > coarse.len());
+        assert_eq!(fine.last(), Some(&[1.0, 0.0]));
+    }
+
+    #[test]
+    fn bezier_chords_gather_where_the_curve_bends() {
+        // Nearly straight along its first half, then turning sharply near (1, 0)
+        let (p0, c1, c2, p1) = ([-1.0, 0.0], [1.0, 0.0], [1.0, 0.0], [1.0, 1.0]);
+        let curve = |t: f32| {
+            let u = 1.0 - t;
+            let w = [u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t];
+            [0, 1].map(|k| w[0] * p0[k] + w[1] * c1[k] + w[2] * c2[k] + w[3] * p1[k])
+        };
+        let tolerance = 0.001;
+        let mut points = vec![p0];
+        flatten_cubic(p0, c1, c2, p1, tolerance, &mut points);
+        let samples: Vec<[f32; 2]> = (0..=20000).map(|i| curve(i as f32 / 20000.0)).collect();
+        for pair in points.windows(2) {
+            let mid = [(pair[0][0] + pair[1][0]) / 2.0, (pair[0][1] + pair[1][1]) / 2.0];
+            let nearest = samples.iter().map(|&[x, y]| (x - mid[0]).hypot(y - mid[1])).fold(f32::MAX, f32::min);
+            assert!(nearest <= tolerance * 1.1, "chord midpoint {nearest} from the curve");
+        }
+        let chord = |i: usize| length([points[i + 1][0] - points[i][0], points[i + 1][1] - points[i][1]]);
+        let (first, shortest) = (chord(0), (0..points.len() - 1).map(chord).fold(f32::MAX, f32::min));
+        assert!(first > 4.0 * shortest, "{first} vs {shortest}");
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse("L0 0").is_err());
+        assert!(parse("10 10").is_err());
+        assert!(parse("M0 0 L1").is_err());
+        assert!(parse("M0 0 Z 1 1").is_err());
+        assert!(parse("M0 0 A1 1 0 2 0 1 1").is_err());
+        assert!(parse("M0 0 X1 1").is_err());
+        assert_eq!(parse("").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn outline_fits_unit_square_with_y_up() {
+        let (contours, closed) = outline(&parse("M10 10 h20 v10 h-20 z M0 0 l1 1").unwrap(), 1e-3).unwrap();
+        assert_eq!(closed, vec![true, false]);
+        // 30 x 20 units, centred on (15, 10) and scaled by 1 / 15
+        let expected = [
+            vec![[-1.0 / 3.0, 0.0], [1.0, 0.0], [1.0, -2.0 / 3.0], [-1.0 / 3.0, -2.0 / 3.0]],
+            vec![[-1.0, 2.0 / 3.0], [-14.0 / 15.0, 0.6]],
+        ];
+        for (contour, expected) in contours.iter().zip(&expected) {
+            assert_eq!(contour.len(), expected.len());
+            assert!(contour.iter().zip(expected).all(|(&a, &b)| close(a, b)), "{contour:?}");
+        }
+    }
+
+    #[test]
+    fn svg_attributes() {
+        let tag = r#" id="logo" d="M0 0 L1 0" fill-rule = 'evenodd' /"#;
+        assert_eq!(attribute(tag, "d"), Some("M0 0 L1 0"));
+        assert_eq!(attribute(tag, "fill-rule"), Some("evenodd"));
+        assert_eq!(attribute(tag, "fill"), None);
+    }
+}
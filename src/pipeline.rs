@@ -0,0 +1,172 @@
+use wgpu::{BindGroupLayoutEntry, Device, PipelineLayout, RenderPipeline, ShaderStages, TextureFormat, VertexBufferLayout};
+
+use crate::shaders::{self, ShaderFile, ShaderSources};
+
+/// A uniform buffer binding, the kind every parameter block in the sandbox uses.
+pub const fn uniform_entry(binding: u32, visibility: ShaderStages) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+/// The highest layer that gets its own depth; `--axis-on-top` puts the axes here.
+pub const TOP_LAYER: f32 = 1024.0;
+
+// Depth between layers with equal `z` but a different `Tie`
+const TIE_STEP: f32 = 2e-7;
+
+/// Order of layers with equal `z`, farthest first: the order they were drawn in before
+/// layers had a `z`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tie {
+    /// Images such as heatmaps, under the axes on their layer
+    Heatmap,
+    Axis,
+    /// Plotted curves, over the axes they are plotted on
+    Plot,
+    Shape,
+    Stroke,
+    /// Control point markers, in front of the shape they belong to
+    Controls,
+    Graph,
+    /// Legends such as the colorbar, in front of everything else on their layer
+    Legend,
+}
+
+/// Depth buffer value for layer `z`: higher layers are nearer, and layers from -`TOP_LAYER`
+/// to `TOP_LAYER` at least 0.01 apart are distinguished.
+pub fn layer_depth(z: f32, tie: Tie) -> f32 {
+    (0.5 - z / (4.0 * TOP_LAYER) - tie as u32 as f32 * TIE_STEP).clamp(0.0, 1.0)
+}
+
+/// How a pipeline uses the depth buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Depth {
+    /// Drawn everywhere, leaving depth untouched (backgrounds)
+    Ignore,
+    /// Depth-tested and written: geometry that hides whatever is behind it
+    Opaque,
+    /// Depth-tested only, drawn back to front after opaque geometry
+    Translucent,
+    /// Translucent geometry whose own triangles overlap (strokes): depth is written and equal
+    /// depths fail, so each pixel is blended once
+    Once,
+}
+
+impl Depth {
+    fn state(self) -> wgpu::DepthStencilState {
+        wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: matches!(self, Depth::Opaque | Depth::Once),
+            // Equal depths pass so that the later of two draws at the same depth wins, as without a depth buffer
+            depth_compare: match self {
+                Depth::Ignore => wgpu::CompareFunction::Always,
+                Depth::Opaque | Depth::Translucent => wgpu::CompareFunction::LessEqual,
+                Depth::Once => wgpu::CompareFunction::Less,
+            },
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }
+    }
+}
+
+/// Builds a TriangleList pipeline with `vs_main`/`fs_main` entry points and alpha blending,
+/// the setup every pass in the sandbox shares.
+///
+/// The WGSL is validated with naga first, and wgpu validation errors (e.g. bindings that do
+/// not match `layout`) are captured rather than panicking, so a broken shader can be
+/// reported while the caller keeps its previous pipeline.
+pub fn build(
+    device: &Device,
+    label: &str,
+    layout: &PipelineLayout,
+    source: &str,
+    buffers: &[VertexBufferLayout],
+    format: TextureFormat,
+    depth: Depth,
+) -> Result<RenderPipeline, String> {
+    shaders::validate(source, label)?;
+
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &module,
+            entry_point: Some("vs_main"),
+            compilation_options: Default::default(),
+            buffers,
+        },
+        primitive: wgpu::PrimitiveState::default(), // TriangleList
+        depth_stencil: Some(depth.state()),
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: &module,
+            entry_point: Some("fs_main"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+        cache: None,
+    });
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(err) => Err(format!("{label}: {err}")),
+        None => Ok(pipeline),
+    }
+}
+
+/// Builds a pipeline from `sources`, falling back to the compiled-in shaders when a file
+/// from `--shader-dir` does not validate.
+pub fn load(
+    device: &Device,
+    sources: &ShaderSources,
+    files: &[&ShaderFile],
+    layout: &PipelineLayout,
+    buffers: &[VertexBufferLayout],
+    format: TextureFormat,
+    depth: Depth,
+) -> RenderPipeline {
+    let label = shaders::label(files);
+    sources
+        .compose(files)
+        .and_then(|source| build(device, &label, layout, &source, buffers, format, depth))
+        .unwrap_or_else(|e| {
+            eprintln!("{e}\nUsing the built-in {label} instead");
+            let source = ShaderSources::default().compose(files).unwrap();
+            build(device, &label, layout, &source, buffers, format, depth)
+                .expect("built-in shaders are valid")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_translucent_triangles_blend_once() {
+        // The first triangle at a pixel writes the layer's depth, which the next one then fails
+        let once = Depth::Once.state();
+        assert!(once.depth_write_enabled);
+        assert!(once.depth_compare == wgpu::CompareFunction::Less);
+        // Other translucent layers, drawn later and nearer, still pass over it
+        assert!(layer_depth(0.0, Tie::Controls) < layer_depth(0.0, Tie::Stroke));
+        assert!(!Depth::Translucent.state().depth_write_enabled);
+    }
+}
@@ -0,0 +1,257 @@
+// Axis scales: how values along an axis are laid out over the view.
+//
+// A scale transforms values (unchanged, by log10, or by a symmetric log that is linear around
+// zero) and stretches the transformed range linearly across NDC -1..1. Everything placed in
+// axis coordinates — ticks, grid lines, plotted curves, field arrows, heatmaps and what the
+// cursor points at — goes through the same `to_ndc` and `from_ndc`, so they always agree.
+
+/// How values are spaced along an axis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ScaleKind {
+    /// Equal steps in value are equal distances
+    Linear,
+    /// Equal ratios are equal distances; positive values only
+    Log,
+    /// Logarithmic away from zero in both directions, linear within ±1
+    Symlog,
+}
+
+/// Where the symmetric log scale turns linear.
+pub const SYMLOG_THRESHOLD: f32 = 1.0;
+// Roughly how many ticks a linear axis gets across the view
+const LINEAR_TICKS: f64 = 20.0;
+// Log axes spanning more decades than this get no minor ticks
+const MAX_MINOR_DECADES: i32 = 10;
+
+/// A tick on an axis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tick {
+    pub value: f32,
+    /// Decades on log axes; minor ticks are the multiples 2 to 9 between them
+    pub major: bool,
+}
+
+/// One axis: its kind and the values at the left or bottom and the right or top of the view.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scale {
+    pub kind: ScaleKind,
+    pub range: [f32; 2],
+}
+
+impl Default for Scale {
+    /// Linear over -1..1, where axis coordinates are NDC.
+    fn default() -> Scale {
+        Scale { kind: ScaleKind::Linear, range: [-1.0, 1.0] }
+    }
+}
+
+impl Scale {
+    /// A scale over `range`, or a default range for its kind: -1..1, 0.01..100 on log scales
+    /// and -100..100 on symlog ones.
+    pub fn new(kind: ScaleKind, range: Option<[f32; 2]>) -> Result<Scale, String> {
+        let [min, max] = range.unwrap_or(match kind {
+            ScaleKind::Linear => [-1.0, 1.0],
+            ScaleKind::Log => [0.01, 100.0],
+            ScaleKind::Symlog => [-100.0, 100.0],
+        });
+        if !min.is_finite() || !max.is_finite() || min >= max {
+            return Err(format!("expected MIN < MAX, got {min} {max}"));
+        }
+        if kind == ScaleKind::Log && min <= 0.0 {
+            return Err(format!("a log scale needs positive values, got {min} {max}"));
+        }
+        Ok(Scale { kind, range: [min, max] })
+    }
+
+    /// The value as the scale spaces it: linearly in the result. Values a log scale cannot
+    /// show become NaN or -inf.
+    pub fn transform(&self, v: f32) -> f32 {
+        match self.kind {
+            ScaleKind::Linear => v,
+            ScaleKind::Log => v.log10(),
+            ScaleKind::Symlog => v.signum() * (1.0 + v.abs() / SYMLOG_THRESHOLD).log10(),
+        }
+    }
+
+    /// The inverse of `transform`.
+    pub fn inverse(&self, t: f32) -> f32 {
+        match self.kind {
+            ScaleKind::Linear => t,
+            ScaleKind::Log => 10f32.powf(t),
+            ScaleKind::Symlog => t.signum() * SYMLOG_THRESHOLD * (10f32.powf(t.abs()) - 1.0),
+        }
+    }
+
+    /// `range` transformed.
+    pub fn transformed_range(&self) -> [f32; 2] {
+        self.range.map(|v| self.transform(v))
+    }
+
+    /// Where `v` is in NDC. Written so the default scale maps every value to itself exactly.
+    pub fn to_ndc(&self, v: f32) -> f32 {
+        let [a, b] = self.transformed_range();
+        (2.0 * self.transform(v) - (a + b)) / (b - a)
+    }
+
+    /// The value at `n` in NDC.
+    pub fn from_ndc(&self, n: f32) -> f32 {
+        let [a, b] = self.transformed_range();
+        self.inverse((n * (b - a) + (a + b)) / 2.0)
+    }
+
+    /// NDC per unit of value at `v`, to carry directions and lengths in values onto the view.
+    pub fn slope(&self, v: f32) -> f32 {
+        let [a, b] = self.transformed_range();
+        let derivative = match self.kind {
+            ScaleKind::Linear => 1.0,
+            ScaleKind::Log => 1.0 / (v * std::f32::consts::LN_10),
+            ScaleKind::Symlog => 1.0 / ((SYMLOG_THRESHOLD + v.abs()) * std::f32::consts::LN_10),
+        };
+        2.0 * derivative / (b - a)
+    }
+
+    /// Where the other axis crosses this one, in NDC: at zero, or the nearest edge of the view
+    /// to it. Log axes have no zero and are crossed at their low end.
+    pub fn origin_ndc(&self) -> f32 {
+        match self.kind {
+            ScaleKind::Log => -1.0,
+            _ => self.to_ndc(0.0).clamp(-1.0, 1.0),
+        }
+    }
+
+    /// Ticks within the range, from low to high: evenly spaced round values on linear axes,
+    /// and decades with the multiples 2 to 9 between them otherwise.
+    pub fn ticks(&self) -> Vec<Tick> {
+        let [min, max] = self.range.map(f64::from);
+        let mut ticks: Vec<Tick> = match self.kind {
+            ScaleKind::Linear => {
+                let step = nice_step((max - min) / LINEAR_TICKS);
+                let (first, last) = ((min / step).ceil() as i64, (max / step).floor() as i64);
+                (first..=last).map(|k| Tick { value: (k as f64 * step) as f32, major: true }).collect()
+            }
+            ScaleKind::Log => decades(min.log10().floor() as i32, max.log10().ceil() as i32),
+            ScaleKind::Symlog => {
+                let largest = min.abs().max(max.abs()).max(1.0).log10().ceil() as i32;
+                let positive = decades(SYMLOG_THRESHOLD.log10() as i32, largest);
+                let negative = positive.iter().map(|tick| Tick { value: -tick.value, ..*tick });
+                let mut ticks: Vec<Tick> = negative.chain(positive.iter().copied()).collect();
+                ticks.push(Tick { value: 0.0, major: true });
+                ticks.sort_by(|a, b| a.value.total_cmp(&b.value));
+                ticks
+            }
+        };
+        ticks.retain(|tick| self.to_ndc(tick.value).abs() <= 1.0 + 1e-4);
+        ticks
+    }
+}
+
+// 1, 2 or 5 times a power of ten, the smallest at least `raw`
+fn nice_step(raw: f64) -> f64 {
+    let base = 10f64.powf(raw.log10().floor());
+    [1.0, 2.0, 5.0, 10.0].into_iter().map(|m| m * base).find(|&step| step >= raw * (1.0 - 1e-9)).unwrap()
+}
+
+// Ticks at the decades from 10^low to 10^high, with minor ones between them unless that is
+// too many
+fn decades(low: i32, high: i32) -> Vec<Tick> {
+    let minor = high - low <= MAX_MINOR_DECADES;
+    (low..=high)
+        .flat_map(|e| {
+            let multiples = if minor && e < high { 1..10 } else { 1..2 };
+            multiples.map(move |m| Tick { value: (f64::from(m) * 10f64.powi(e)) as f32, major: m == 1 })
+        })
+        .collect()
+}
+
+/// The scales of both axes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Scales {
+    pub x: Scale,
+    pub y: Scale,
+}
+
+impl Scales {
+    /// Where the point `p`, in axis coordinates, is in NDC.
+    pub fn to_ndc(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        [self.x.to_ndc(x), self.y.to_ndc(y)]
+    }
+
+    /// The point in axis coordinates at `n` in NDC.
+    pub fn from_ndc(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        [self.x.from_ndc(x), self.y.from_ndc(y)]
+    }
+
+    /// The direction `v` in axis coordinates at `p`, carried into NDC.
+    pub fn direction_to_ndc(&self, p: [f32; 2], v: [f32; 2]) -> [f32; 2] {
+        [v[0] * self.x.slope(p[0]), v[1] * self.y.slope(p[1])]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() <= 1e-5 * a.abs().max(1.0)
+    }
+
+    #[test]
+    fn linear() {
+        let scale = Scale::default();
+        for v in [-1.0, -0.3, 0.0, 0.1, 0.7, 1.0] {
+            assert_eq!(scale.to_ndc(v), v);
+            assert_eq!(scale.from_ndc(v), v);
+        }
+        assert_eq!(scale.slope(0.4), 1.0);
+        let ticks = scale.ticks();
+        assert_eq!(ticks.len(), 21);
+        assert!(close(ticks[11].value, 0.1) && ticks.iter().all(|t| t.major));
+
+        let scale = Scale::new(ScaleKind::Linear, Some([2.0, 7.0])).unwrap();
+        assert_eq!(scale.to_ndc(2.0), -1.0);
+        assert!(close(scale.from_ndc(0.0), 4.5));
+        // Steps of 0.25 would give 21 ticks, so they are rounded to 0.5
+        assert_eq!(scale.ticks().len(), 11);
+        assert_eq!(scale.origin_ndc(), -1.0);
+    }
+
+    #[test]
+    fn log() {
+        let scale = Scale::new(ScaleKind::Log, None).unwrap();
+        assert!(close(scale.to_ndc(0.01), -1.0) && close(scale.to_ndc(1.0), 0.0) && close(scale.to_ndc(100.0), 1.0));
+        assert!(close(scale.from_ndc(0.5), 10.0));
+        assert!(scale.to_ndc(-1.0).is_nan());
+        assert_eq!(scale.origin_ndc(), -1.0);
+        // Five decades with eight minor ticks in each of the four gaps between them
+        let ticks = scale.ticks();
+        assert_eq!(ticks.len(), 5 + 4 * 8);
+        let majors: Vec<f32> = ticks.iter().filter(|t| t.major).map(|t| t.value).collect();
+        assert!(majors.iter().zip([0.01, 0.1, 1.0, 10.0, 100.0]).all(|(&a, b)| close(a, b)));
+        assert!(close(ticks[1].value, 0.02) && !ticks[1].major);
+
+        // Slopes match the mapping
+        let v = 3.0;
+        let numeric = (scale.to_ndc(v + 1e-3) - scale.to_ndc(v - 1e-3)) / 2e-3;
+        assert!((scale.slope(v) - numeric).abs() < 1e-3 * numeric);
+    }
+
+    #[test]
+    fn symlog() {
+        let scale = Scale::new(ScaleKind::Symlog, None).unwrap();
+        assert_eq!(scale.to_ndc(0.0), 0.0);
+        assert!(close(scale.to_ndc(-100.0), -1.0) && close(scale.to_ndc(100.0), 1.0));
+        assert!(close(scale.to_ndc(-5.0), -scale.to_ndc(5.0)));
+        for v in [-40.0, -0.5, 0.25, 9.0] {
+            assert!(close(scale.from_ndc(scale.to_ndc(v)), v));
+        }
+        let majors: Vec<f32> = scale.ticks().into_iter().filter(|t| t.major).map(|t| t.value).collect();
+        assert_eq!(majors, [-100.0, -10.0, -1.0, 0.0, 1.0, 10.0, 100.0]);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Scale::new(ScaleKind::Log, Some([0.0, 10.0])).unwrap_err().contains("positive"));
+        assert!(Scale::new(ScaleKind::Linear, Some([1.0, 1.0])).unwrap_err().contains("MIN < MAX"));
+        assert!(Scale::new(ScaleKind::Symlog, Some([f32::NAN, 1.0])).is_err());
+    }
+}
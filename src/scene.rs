@@ -0,0 +1,229 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::color;
+use crate::colormap::{self, Colormap};
+use crate::fill;
+use crate::graph::{NodeId, SceneGraph, Transform};
+use crate::instance::{self, ShapeInstance};
+use crate::shape::{Shape, ShapeConfig, REFERENCE_HEIGHT_PX};
+use crate::tessellate::{Contour, FillRule};
+
+/// A JSON scene file. Values given here override the command line.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scene {
+    /// Shape name, as for `--shape`
+    pub shape: Option<String>,
+    pub size: Option<f32>,
+    /// Fill syntax of `--fill`
+    pub fill: Option<String>,
+    /// Polygon contours as `[[x, y], ...]` lists, as for `--points`
+    pub points: Option<Vec<Contour>>,
+    /// `non-zero` or `even-odd`
+    pub fill_rule: Option<String>,
+    /// Colormap name or file, as for `--colormap`
+    pub colormap: Option<String>,
+    /// Values mapped to the ends of the colormap; the extent of the instance values by default
+    pub value_range: Option<[f32; 2]>,
+    /// Bézier control points as `[x, y]`, as for `--control-points`
+    pub control_points: Option<Vec<[f32; 2]>>,
+    /// Degrees, as for `--start-angle` and `--end-angle`
+    pub start_angle: Option<f32>,
+    pub end_angle: Option<f32>,
+    pub instances: Option<Instances>,
+    /// Scene graph drawn over the shape
+    #[serde(default)]
+    pub nodes: Vec<NodeSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Instances {
+    List(Vec<InstanceSpec>),
+    Grid { grid: Grid },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InstanceSpec {
+    #[serde(default)]
+    pub position: [f32; 2],
+    #[serde(default = "one")]
+    pub scale: f32,
+    /// Degrees, counter-clockwise
+    #[serde(default)]
+    pub rotation: f32,
+    /// Multiplies the fill; white when absent
+    pub color: Option<String>,
+    /// Colors the instance through the colormap instead of `color`
+    pub value: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Grid {
+    pub columns: u32,
+    pub rows: u32,
+}
+
+/// A scene graph node: a shape when `shape` is given, otherwise a group. Transforms and
+/// colors apply to the children.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeSpec {
+    pub shape: Option<String>,
+    pub size: Option<f32>,
+    /// Contours of a polygon shape
+    #[serde(default)]
+    pub points: Vec<Contour>,
+    pub fill_rule: Option<String>,
+    /// Control points of a Bézier shape
+    #[serde(default)]
+    pub control_points: Vec<[f32; 2]>,
+    /// Degrees, bounding an arc, pie or sector shape
+    pub start_angle: Option<f32>,
+    pub end_angle: Option<f32>,
+    pub color: Option<String>,
+    /// NDC Y units on both axes
+    #[serde(default)]
+    pub position: [f32; 2],
+    #[serde(default = "one")]
+    pub scale: f32,
+    /// Degrees, counter-clockwise
+    #[serde(default)]
+    pub rotation: f32,
+    /// Layers above the parent; the main shape is on layer `--z`
+    #[serde(default)]
+    pub z: f32,
+    #[serde(default)]
+    pub children: Vec<NodeSpec>,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn fill_rule(name: &str) -> Result<FillRule, String> {
+    FillRule::from_str(name, true).map_err(|_| format!("scene: unknown fill rule '{name}'"))
+}
+
+impl Scene {
+    pub fn load(path: &Path) -> Result<Scene, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        serde_json::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn apply(self, config: &mut ShapeConfig) -> Result<(), String> {
+        if let Some(name) = &self.shape {
+            config.shape = Shape::from_str(name, true).map_err(|_| format!("scene: unknown shape '{name}'"))?;
+        }
+        if let Some(size) = self.size {
+            config.size = size;
+        }
+        if let Some(fill) = &self.fill {
+            config.fill = fill::parse_fill(fill).map_err(|e| format!("scene: {e}"))?;
+        }
+        if let Some(points) = self.points {
+            config.contours = points;
+        }
+        if let Some(rule) = &self.fill_rule {
+            config.fill_rule = fill_rule(rule)?;
+        }
+        if let Some(points) = self.control_points {
+            config.control_points = points;
+        }
+        config.start_angle = self.start_angle.unwrap_or(config.start_angle);
+        config.end_angle = self.end_angle.unwrap_or(config.end_angle);
+        if let Some(name) = &self.colormap {
+            config.colormap = Colormap::parse(name).map_err(|e| format!("scene: {e}"))?;
+        }
+        if let Some(instances) = self.instances {
+            config.instances = Some(instances.build(&config.colormap, self.value_range)?);
+        }
+        for node in &self.nodes {
+            node.add_to(&mut config.graph, None)?;
+        }
+        Ok(())
+    }
+}
+
+impl NodeSpec {
+    fn add_to(&self, graph: &mut SceneGraph, parent: Option<NodeId>) -> Result<(), String> {
+        let transform =
+            Transform { translation: self.position, rotation: self.rotation.to_radians(), scale: self.scale };
+        let color = match &self.color {
+            Some(c) => color::parse(c).ok_or_else(|| format!("scene: invalid color '{c}'"))?,
+            None => [1.0; 4],
+        };
+        let id = match &self.shape {
+            Some(name) => {
+                let shape = Shape::from_str(name, true).map_err(|_| format!("scene: unknown shape '{name}'"))?;
+                let mut mesh = ShapeConfig {
+                    shape,
+                    contours: self.points.clone(),
+                    control_points: self.control_points.clone(),
+                    ..Default::default()
+                };
+                mesh.size = self.size.unwrap_or(mesh.size);
+                mesh.start_angle = self.start_angle.unwrap_or(mesh.start_angle);
+                mesh.end_angle = self.end_angle.unwrap_or(mesh.end_angle);
+                if let Some(rule) = &self.fill_rule {
+                    mesh.fill_rule = fill_rule(rule)?;
+                }
+                mesh.check_control_points().map_err(|e| format!("scene: {e}"))?;
+                mesh.check_sweep().map_err(|e| format!("scene: {e}"))?;
+                mesh.check_dimensions().map_err(|e| format!("scene: {e}"))?;
+                graph.add_shape(parent, &mesh.vertices(REFERENCE_HEIGHT_PX), color, transform)?
+            }
+            None => {
+                let id = graph.add_group(parent, transform)?;
+                graph.set_color(id, color)?;
+                id
+            }
+        };
+        if self.z != 0.0 {
+            graph.set_z(id, self.z)?;
+        }
+        for child in &self.children {
+            child.add_to(graph, Some(id))?;
+        }
+        Ok(())
+    }
+}
+
+impl Instances {
+    /// Instance values are colored through `colormap` over `range`, or else over their extent.
+    /// Fails when there are none, which would leave nothing to draw.
+    pub fn build(self, colormap: &Colormap, range: Option<[f32; 2]>) -> Result<Vec<ShapeInstance>, String> {
+        let instances: Vec<ShapeInstance> = match self {
+            Instances::Grid { grid } => instance::grid(grid.columns, grid.rows),
+            Instances::List(specs) => {
+                let values: Vec<f32> = specs.iter().filter_map(|spec| spec.value).collect();
+                let range = range.unwrap_or_else(|| colormap::extent(&values));
+                specs
+                    .into_iter()
+                    .map(|spec| {
+                        let color = match (&spec.color, spec.value) {
+                            (_, Some(value)) => colormap.sample_range(value, range),
+                            (Some(c), None) => color::parse(c).ok_or_else(|| format!("scene: invalid color '{c}'"))?,
+                            (None, None) => [1.0; 4],
+                        };
+                        Ok(ShapeInstance {
+                            position: spec.position,
+                            scale: spec.scale,
+                            rotation: spec.rotation.to_radians(),
+                            color,
+                        })
+                    })
+                    .collect::<Result<_, String>>()?
+            }
+        };
+        if instances.is_empty() {
+            return Err("scene: instances is empty".to_owned());
+        }
+        Ok(instances)
+    }
+}
@@ -0,0 +1,88 @@
+// Analytic shape shader — the shape is a single quad and coverage comes from a
+// signed distance function evaluated in shape-local coordinates.
+// Expects `fill.wgsl` to be prepended for `u_color` and `fill_color`.
+
+struct SdfParams {
+    stroke_color: vec4<f32>,
+    half_size: vec2<f32>,
+    radius: f32,            // inner radius (ring)
+    stroke_half_width: f32, // stroke centred on the edge; 0 disables it
+    kind: u32,              // 0 = circle, 1 = rounded box, 2 = ring, 3 = ellipse
+    corner_radii: vec4<f32>, // box corners: top-left, top-right, bottom-right, bottom-left
+}
+
+@group(0) @binding(1) var<uniform> u_aspect: vec4<f32>; // x = aspect ratio (width / height)
+@group(0) @binding(2) var<uniform> u_pos: vec4<f32>;    // x, y = position offset in NDC
+@group(0) @binding(3) var<uniform> u_sdf: SdfParams;
+
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) local: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@location(0) position: vec2<f32>, @location(1) uv: vec2<f32>) -> VertexOutput {
+    var out: VertexOutput;
+    // Grow the quad so the outer half of the stroke is not clipped
+    let local = position + sign(position) * u_sdf.stroke_half_width;
+    let corrected = vec2<f32>(local.x / u_aspect.x + u_pos.x, local.y + u_pos.y);
+    out.pos = vec4<f32>(corrected, 0.0, 1.0);
+    out.local = local;
+    // UVs run 0..1 across 2 * half_size with v pointing down, so follow the quad growth
+    let grow = sign(position) * u_sdf.stroke_half_width / (2.0 * u_sdf.half_size);
+    out.uv = uv + vec2<f32>(grow.x, -grow.y);
+    return out;
+}
+
+fn sd_circle(p: vec2<f32>, r: f32) -> f32 {
+    return length(p) - r;
+}
+
+fn sd_round_box(p: vec2<f32>, b: vec2<f32>, radii: vec4<f32>) -> f32 {
+    // The radius of the corner in p's quadrant
+    let left = select(radii.w, radii.x, p.y > 0.0);
+    let right = select(radii.z, radii.y, p.y > 0.0);
+    let r = select(left, right, p.x > 0.0);
+    let q = abs(p) - b + vec2<f32>(r);
+    return length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - r;
+}
+
+fn sd_ring(p: vec2<f32>, inner: f32, outer: f32) -> f32 {
+    return abs(length(p) - 0.5 * (outer + inner)) - 0.5 * (outer - inner);
+}
+
+// Distance to an ellipse with radii r, estimated from the gradient of its implicit function:
+// exact on the curve, which is all that antialiasing and strokes look at
+fn sd_ellipse(p: vec2<f32>, r: vec2<f32>) -> f32 {
+    let k0 = length(p / r);
+    let k1 = length(p / (r * r));
+    return k0 * (k0 - 1.0) / max(k1, 1e-6);
+}
+
+fn sdf(p: vec2<f32>) -> f32 {
+    switch u_sdf.kind {
+        case 1u: { return sd_round_box(p, u_sdf.half_size, u_sdf.corner_radii); }
+        case 2u: { return sd_ring(p, u_sdf.radius, u_sdf.half_size.x); }
+        case 3u: { return sd_ellipse(p, u_sdf.half_size); }
+        default: { return sd_circle(p, u_sdf.half_size.x); }
+    }
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let d = sdf(in.local);
+    // Width of one pixel in distance units, so edges stay ~1px soft at any size
+    let aa = max(fwidth(d), 1e-6);
+    let fill = fill_color(in.local, in.uv);
+    let fill_a = fill.a * (1.0 - smoothstep(-aa, aa, d));
+    let stroke_a = u_sdf.stroke_color.a * (1.0 - smoothstep(-aa, aa, abs(d) - u_sdf.stroke_half_width));
+
+    // Stroke composited over fill
+    let a = stroke_a + fill_a * (1.0 - stroke_a);
+    if a <= 0.0 {
+        discard;
+    }
+    let rgb = (u_sdf.stroke_color.rgb * stroke_a + fill.rgb * fill_a * (1.0 - stroke_a)) / a;
+    return vec4<f32>(rgb, a);
+}
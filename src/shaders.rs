@@ -0,0 +1,139 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+use naga::valid::ModuleInfo;
+use notify::{RecursiveMode, Watcher};
+
+/// A WGSL file shipped with the crate. With `--shader-dir` a file of the same name
+/// in that directory takes precedence over the compiled-in copy.
+pub struct ShaderFile {
+    pub name: &'static str,
+    pub builtin: &'static str,
+}
+
+pub const FILL: ShaderFile = ShaderFile { name: "fill.wgsl", builtin: include_str!("fill.wgsl") };
+pub const SHAPE: ShaderFile = ShaderFile { name: "shape_shader.wgsl", builtin: include_str!("shape_shader.wgsl") };
+pub const INSTANCED: ShaderFile =
+    ShaderFile { name: "instanced_shader.wgsl", builtin: include_str!("instanced_shader.wgsl") };
+pub const SDF: ShaderFile = ShaderFile { name: "sdf_shader.wgsl", builtin: include_str!("sdf_shader.wgsl") };
+pub const STROKE: ShaderFile = ShaderFile { name: "stroke_shader.wgsl", builtin: include_str!("stroke_shader.wgsl") };
+pub const BATCH: ShaderFile = ShaderFile { name: "batch_shader.wgsl", builtin: include_str!("batch_shader.wgsl") };
+pub const AXIS: ShaderFile = ShaderFile { name: "axis_shader.wgsl", builtin: include_str!("axis_shader.wgsl") };
+pub const BACKGROUND: ShaderFile =
+    ShaderFile { name: "background_shader.wgsl", builtin: include_str!("background_shader.wgsl") };
+pub const HEATMAP: ShaderFile =
+    ShaderFile { name: "heatmap_shader.wgsl", builtin: include_str!("heatmap_shader.wgsl") };
+pub const FRAG_WRAPPER: ShaderFile =
+    ShaderFile { name: "frag_wrapper.wgsl", builtin: include_str!("frag_wrapper.wgsl") };
+pub const TRIANGLE: ShaderFile =
+    ShaderFile { name: "triangle_shader.wgsl", builtin: include_str!("triangle_shader.wgsl") };
+
+/// Where shader source is read from: the compiled-in copies, or a development directory.
+#[derive(Default)]
+pub struct ShaderSources {
+    dir: Option<PathBuf>,
+}
+
+impl ShaderSources {
+    pub fn new(dir: Option<PathBuf>) -> ShaderSources {
+        ShaderSources { dir }
+    }
+
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Source of `file`, from the shader directory when it has a copy.
+    pub fn read(&self, file: &ShaderFile) -> Result<String, String> {
+        match &self.dir {
+            Some(dir) if dir.join(file.name).exists() => {
+                let path = dir.join(file.name);
+                std::fs::read_to_string(&path).map_err(|e| format!("failed to read {}: {e}", path.display()))
+            }
+            _ => Ok(file.builtin.to_owned()),
+        }
+    }
+
+    /// Concatenates `files` into one module, e.g. the shared fill code and a shape shader.
+    pub fn compose(&self, files: &[&ShaderFile]) -> Result<String, String> {
+        let parts = files.iter().map(|f| self.read(f)).collect::<Result<Vec<_>, _>>()?;
+        Ok(parts.join("\n"))
+    }
+}
+
+/// Display name for a composed module, used in diagnostics.
+pub fn label(files: &[&ShaderFile]) -> String {
+    files.iter().map(|f| f.name).collect::<Vec<_>>().join(" + ")
+}
+
+/// A naga parse or validation failure.
+#[derive(Debug)]
+pub struct ShaderError {
+    /// Rendered diagnostic with a source snippet
+    pub rendered: String,
+    /// Where the error points in the validated source, if naga reported a span
+    pub location: Option<naga::SourceLocation>,
+}
+
+impl std::fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.rendered)
+    }
+}
+
+impl From<ShaderError> for String {
+    fn from(e: ShaderError) -> String {
+        e.rendered
+    }
+}
+
+/// Parses and validates WGSL with naga. `label` is shown as the file name in diagnostics.
+pub fn validate(source: &str, label: &str) -> Result<(naga::Module, ModuleInfo), ShaderError> {
+    let module = naga::front::wgsl::parse_str(source).map_err(|e| ShaderError {
+        rendered: e.emit_to_string_with_path(source, label),
+        location: e.location(source),
+    })?;
+    let info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::default())
+        .validate(&module)
+        .map_err(|e| ShaderError {
+            rendered: e.emit_to_string_with_path(source, label),
+            location: e.location(source),
+        })?;
+    Ok((module, info))
+}
+
+/// Watches a shader directory and reports which files changed since the last poll.
+pub struct ShaderWatcher {
+    _watcher: notify::RecommendedWatcher,
+    rx: Receiver<notify::Result<notify::Event>>,
+}
+
+impl ShaderWatcher {
+    pub fn new(dir: &Path) -> Result<ShaderWatcher, String> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx).map_err(|e| e.to_string())?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|e| format!("failed to watch {}: {e}", dir.display()))?;
+        Ok(ShaderWatcher { _watcher: watcher, rx })
+    }
+
+    /// File names modified since the last call. Never blocks.
+    pub fn changed(&self) -> HashSet<String> {
+        let mut names = HashSet::new();
+        for event in self.rx.try_iter().flatten() {
+            // Editors often save by writing a new file and renaming it over the old one
+            if !(event.kind.is_modify() || event.kind.is_create()) {
+                continue;
+            }
+            names.extend(
+                event
+                    .paths
+                    .iter()
+                    .filter_map(|p| p.file_name()?.to_str().map(str::to_owned)),
+            );
+        }
+        names
+    }
+}
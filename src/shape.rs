@@ -1,47 +1,846 @@
 use std::f32::consts::PI;
+use std::path::PathBuf;
+
+use crate::adapter::AdapterOptions;
+use crate::background::Background;
+use crate::colormap::Colormap;
+use crate::field::VectorField;
+use crate::fill::{Fill, FillParams};
+use crate::frag::UserFrag;
+use crate::graph::SceneGraph;
+use crate::heatmap::Heatmap;
+use crate::implicit::Implicit;
+use crate::instance::ShapeInstance;
+use crate::parametric::Parametric;
+use crate::scale::Scales;
+use crate::pacing::PresentMode;
+use crate::path::{self, Arc};
+use crate::stroke::{stroke_path, StrokeVertex};
+use crate::tessellate::{self, Contour, FillRule};
+use crate::texture::TextureConfig;
 
 #[derive(Clone, clap::ValueEnum)]
 pub enum Shape {
     Triangle,
     Square,
+    /// Rectangle of `width` by `height`, with optionally rounded corners
+    Rect,
     Circle,
+    /// Ellipse with radii `rx` and `ry`
+    Ellipse,
+    /// Annulus between `inner_radius` and `outer_radius`
+    Ring,
+    /// Arbitrary contours from `ShapeConfig::contours`
+    Polygon,
+    /// Quadratic Bézier through `ShapeConfig::control_points`, filled up to its chord
+    QuadBezier,
+    /// Cubic Bézier through `ShapeConfig::control_points`, filled up to its chord
+    CubicBezier,
+    /// Circular arc from `start_angle` to `end_angle`, filled up to its chord
+    Arc,
+    /// Pie slice from `start_angle` to `end_angle`
+    Pie,
+    /// Slice of a ring from `start_angle` to `end_angle`, between the ring's radii
+    Sector,
+    /// Arrow from `from` to `to` with a head `head_size` long
+    Arrow,
+}
+
+/// How a shape is turned into pixels.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum RenderMode {
+    /// CPU-tessellated triangles
+    Mesh,
+    /// A single quad with a signed distance function evaluated per fragment
+    Sdf,
+}
+
+/// Unit of a stroke width.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum StrokeUnits {
+    /// Screen pixels, kept constant across resizes
+    Px,
+    /// NDC Y units, the same units as `size`
+    Ndc,
+}
+
+#[derive(Clone, Copy)]
+pub struct Stroke {
+    pub color: [f32; 4],
+    pub width: f32,
+    pub units: StrokeUnits,
+}
+
+impl Stroke {
+    /// Half the stroke width in shape-local units (NDC Y) for a surface `height_px` tall.
+    pub fn half_width(&self, height_px: u32) -> f32 {
+        match self.units {
+            StrokeUnits::Ndc => self.width * 0.5,
+            // NDC spans 2 units over the surface height
+            StrokeUnits::Px => self.width / height_px.max(1) as f32,
+        }
+    }
 }
 
 pub struct ShapeConfig {
     pub shape: Shape,
-    pub color: [f32; 4],
+    /// Interior fill; `Fill::None` leaves only the stroke
+    pub fill: Fill,
+    /// Image drawn over the fill area, optionally tinted by the fill
+    pub texture: Option<TextureConfig>,
+    /// User fragment code that replaces the fill entirely
+    pub frag: Option<UserFrag>,
+    pub stroke: Option<Stroke>,
     pub size: f32,
     pub position: [f32; 2],
+    /// Layer of the shape and its stroke: higher layers are drawn in front
+    pub z: f32,
+    pub render_mode: RenderMode,
+    /// Corner radii of a square or rectangle: top-left, top-right, bottom-right, bottom-left.
+    /// Scaled down together where adjacent corners would overlap, as in CSS
+    pub corner_radii: [f32; 4],
+    /// Full width of a rectangle; `2 * size` when unset
+    pub width: Option<f32>,
+    /// Full height of a rectangle; `2 * size` when unset
+    pub height: Option<f32>,
+    /// Horizontal radius of an ellipse; `size` when unset
+    pub rx: Option<f32>,
+    /// Vertical radius of an ellipse; `size` when unset
+    pub ry: Option<f32>,
+    /// Inner radius of a ring as a fraction of its outer radius, unless `inner_radius` is set
+    pub inner_ratio: f32,
+    /// Inner radius of a ring or sector, clamped to the outer radius
+    pub inner_radius: Option<f32>,
+    /// Outer radius of a ring or sector; `size` when unset
+    pub outer_radius: Option<f32>,
+    /// Outline and hole contours of a polygon, in units of `size`
+    pub contours: Vec<Contour>,
+    /// Whether the stroke of each polygon contour joins back to its start; contours without
+    /// an entry are closed. Fills always close every contour.
+    pub closed: Vec<bool>,
+    /// How overlapping polygon contours are filled
+    pub fill_rule: FillRule,
+    /// Control points of a Bézier in units of `size`; empty uses an arch spanning the bounds
+    pub control_points: Vec<[f32; 2]>,
+    /// Start of an arc, pie or sector, degrees counter-clockwise from +x
+    pub start_angle: f32,
+    /// End of an arc, pie or sector; below `start_angle` it runs clockwise
+    pub end_angle: f32,
+    /// Largest distance in pixels between a curve and the chords it is drawn with
+    pub curve_tolerance: f32,
+    /// Mark the control points of curves, joined by their control polygon
+    pub show_controls: bool,
+    /// Tail of an arrow, in units of `size`
+    pub from: [f32; 2],
+    /// Tip of an arrow, in units of `size`
+    pub to: [f32; 2],
+    /// Length of an arrow's head in units of `size`, at most the whole arrow. The head is as
+    /// wide as it is long, and the shaft a third of that
+    pub head_size: f32,
+    /// Draw the shape as arrows over the axis grid showing this field, instead of at `position`
+    pub vector_field: Option<VectorField>,
+    /// Maps data to colors, such as field magnitudes and instance values
+    pub colormap: Colormap,
+    /// Draw a legend of `colormap` beside the axes
+    pub colorbar: bool,
+    /// A grid of values drawn over the axes, colored through `colormap`
+    pub heatmap: Option<Heatmap>,
+    /// An equation of the axis coordinates whose solutions are drawn as curves
+    pub implicit: Option<Implicit>,
+    /// A curve of `t` in axis coordinates
+    pub parametric: Option<Parametric>,
+    /// Seconds a dot takes to trace `parametric` once; no dot when unset
+    pub pen_period: Option<f32>,
+    /// Color of plotted curves
+    pub plot_color: [f32; 4],
+    /// Width of plotted curves in physical pixels
+    pub plot_width: f32,
+    pub background: Background,
+    /// Load WGSL from this directory instead of the compiled-in copies, and reload on change
+    pub shader_dir: Option<PathBuf>,
+    /// Backend and adapter selection
+    pub adapter: AdapterOptions,
+    pub present_mode: PresentMode,
     pub axis: bool,
     pub axis_grid: bool,
+    /// How axis coordinates are laid out over the view, for the axes and everything plotted
+    pub scales: Scales,
     pub axis_arm_len: f32,
+    pub axis_render_mode: RenderMode,
+    /// Layer of the axes and grid
+    pub axis_z: f32,
+    /// Draw the mesh once per instance instead of once at `position`; mesh mode only
+    pub instances: Option<Vec<ShapeInstance>>,
+    /// Further shapes drawn on top, editable at runtime through `State`
+    pub graph: SceneGraph,
+    /// Draw the opaque shapes of `graph` in one batched call instead of one call per shape
+    pub batch: bool,
+}
+
+/// The command line defaults: a red triangle on white, drawn as a mesh.
+impl Default for ShapeConfig {
+    fn default() -> ShapeConfig {
+        ShapeConfig {
+            shape: Shape::Triangle,
+            fill: Fill::Solid([1.0, 0.0, 0.0, 1.0]),
+            texture: None,
+            frag: None,
+            stroke: None,
+            size: 0.5,
+            position: [0.0, 0.0],
+            z: 0.0,
+            render_mode: RenderMode::Mesh,
+            corner_radii: [0.0; 4],
+            width: None,
+            height: None,
+            rx: None,
+            ry: None,
+            inner_ratio: 0.6,
+            inner_radius: None,
+            outer_radius: None,
+            contours: Vec::new(),
+            closed: Vec::new(),
+            fill_rule: FillRule::NonZero,
+            control_points: Vec::new(),
+            start_angle: 0.0,
+            end_angle: 270.0,
+            curve_tolerance: 0.25,
+            show_controls: false,
+            from: [-1.0, 0.0],
+            to: [1.0, 0.0],
+            head_size: 0.6,
+            vector_field: None,
+            colormap: Colormap::default(),
+            colorbar: false,
+            heatmap: None,
+            implicit: None,
+            parametric: None,
+            pen_period: None,
+            plot_color: [1.0, 0.5, 0.0, 1.0],
+            plot_width: 2.0,
+            background: Background::Color([1.0; 4]),
+            shader_dir: None,
+            adapter: AdapterOptions::default(),
+            present_mode: PresentMode::Auto,
+            axis: false,
+            axis_grid: false,
+            scales: Scales::default(),
+            axis_arm_len: 1.0,
+            axis_render_mode: RenderMode::Mesh,
+            axis_z: -1.0,
+            instances: None,
+            graph: SceneGraph::new(),
+            batch: false,
+        }
+    }
+}
+
+pub type ShapeVertex = [f32; 4];
+
+/// Uniform block consumed by `sdf_shader.wgsl`. Layout must match `SdfParams` there.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SdfParams {
+    pub stroke_color: [f32; 4],
+    pub half_size: [f32; 2],
+    /// Inner radius for rings
+    pub radius: f32,
+    /// Stroke centred on the edge; zero disables it
+    pub stroke_half_width: f32,
+    pub kind: u32,
+    pub _pad: [u32; 3],
+    /// Corner radii for boxes, in the order of `ShapeConfig::corner_radii`
+    pub corner_radii: [f32; 4],
 }
 
+const SDF_CIRCLE: u32 = 0;
+const SDF_BOX: u32 = 1;
+const SDF_RING: u32 = 2;
+const SDF_ELLIPSE: u32 = 3;
+
+// Extra margin around SDF quads so the anti-aliased edge is not clipped.
+// The shader additionally grows the quad by the stroke half-width.
+const SDF_QUAD_PAD: f32 = 0.01;
+const CIRCLE_SEGMENTS: u32 = 64;
+const CORNER_SEGMENTS: u32 = 16;
+
+/// Surface height that curves are flattened for when no surface exists yet, as for scene graph shapes.
+pub const REFERENCE_HEIGHT_PX: u32 = 1080;
+
 impl ShapeConfig {
-    pub fn vertices(&self) -> Vec<[f32; 2]> {
+    /// The mode actually used for drawing. Shapes without a distance function and instanced
+    /// shapes fall back to `Mesh`.
+    pub fn effective_render_mode(&self) -> RenderMode {
+        match (self.render_mode, &self.shape) {
+            (RenderMode::Sdf, _) if self.instances.is_some() => RenderMode::Mesh,
+            (RenderMode::Sdf, Shape::Square | Shape::Rect | Shape::Circle | Shape::Ellipse | Shape::Ring) => {
+                RenderMode::Sdf
+            }
+            (RenderMode::Sdf, _) => RenderMode::Mesh,
+            (mode, _) => mode,
+        }
+    }
+
+    /// Whether frames change with time alone: `--frag` shaders are given the time and frame
+    /// number, and the pen moves along the parametric curve.
+    pub fn animated(&self) -> bool {
+        self.frag.is_some() || (self.parametric.is_some() && self.pen_period.is_some())
+    }
+
+    /// Whether the shape is a curve flattened to `curve_tolerance` in pixels, so that its
+    /// vertices depend on the surface height.
+    pub fn is_curve(&self) -> bool {
+        matches!(self.shape, Shape::QuadBezier | Shape::CubicBezier | Shape::Arc | Shape::Pie | Shape::Sector)
+    }
+
+    /// A copy of what decides the shape's vertices and stroke, without its fill, image, data
+    /// or scene.
+    pub fn geometry(&self) -> ShapeConfig {
+        ShapeConfig {
+            shape: self.shape.clone(),
+            stroke: self.stroke,
+            size: self.size,
+            render_mode: self.render_mode,
+            corner_radii: self.corner_radii,
+            width: self.width,
+            height: self.height,
+            rx: self.rx,
+            ry: self.ry,
+            inner_ratio: self.inner_ratio,
+            inner_radius: self.inner_radius,
+            outer_radius: self.outer_radius,
+            contours: self.contours.clone(),
+            closed: self.closed.clone(),
+            fill_rule: self.fill_rule,
+            control_points: self.control_points.clone(),
+            start_angle: self.start_angle,
+            end_angle: self.end_angle,
+            curve_tolerance: self.curve_tolerance,
+            from: self.from,
+            to: self.to,
+            head_size: self.head_size,
+            ..ShapeConfig::default()
+        }
+    }
+
+    pub fn fill_params(&self) -> FillParams {
+        let mut params = self.fill.params(self.half_size());
+        if let Some(tex) = &self.texture {
+            params.texture = if tex.tint { 2 } else { 1 };
+            params.uv_scale = [tex.scale, tex.scale];
+        }
+        params
+    }
+
+    /// Half the width and height of the shape's local bounds, which are centred on the origin.
+    pub fn half_size(&self) -> [f32; 2] {
+        let s = self.size;
+        match self.shape {
+            Shape::Rect => [self.width.unwrap_or(2.0 * s) / 2.0, self.height.unwrap_or(2.0 * s) / 2.0],
+            Shape::Ellipse => [self.rx.unwrap_or(s), self.ry.unwrap_or(s)],
+            Shape::Ring => {
+                let (_, outer) = self.ring_radii();
+                [outer, outer]
+            }
+            _ => [s, s],
+        }
+    }
+
+    /// Inner and outer radius of a ring or sector.
+    pub fn ring_radii(&self) -> (f32, f32) {
+        let outer = self.outer_radius.unwrap_or(self.size);
+        let inner = self.inner_radius.unwrap_or(self.inner_ratio * outer);
+        (inner.max(0.0).min(outer), outer)
+    }
+
+    /// Corner radii of a square or rectangle, scaled down so that no two corners along an
+    /// edge overlap.
+    pub fn clamped_corner_radii(&self) -> [f32; 4] {
+        let [hx, hy] = self.half_size();
+        let r = self.corner_radii.map(|r| r.max(0.0));
+        let [tl, tr, br, bl] = r;
+        let scale = [(2.0 * hx, tl + tr), (2.0 * hx, bl + br), (2.0 * hy, tl + bl), (2.0 * hy, tr + br)]
+            .into_iter()
+            .filter(|&(_, sum)| sum > 0.0)
+            .fold(1.0f32, |scale, (side, sum)| scale.min(side / sum));
+        r.map(|r| r * scale)
+    }
+
+    /// `height_px` is the surface height, used to resolve pixel stroke widths.
+    pub fn sdf_params(&self, height_px: u32) -> SdfParams {
+        let (kind, radius) = match self.shape {
+            Shape::Square | Shape::Rect => (SDF_BOX, 0.0),
+            Shape::Ellipse => (SDF_ELLIPSE, 0.0),
+            Shape::Ring => (SDF_RING, self.ring_radii().0),
+            _ => (SDF_CIRCLE, 0.0),
+        };
+        SdfParams {
+            stroke_color: self.stroke.map_or([0.0; 4], |st| st.color),
+            half_size: self.half_size(),
+            radius,
+            stroke_half_width: self.stroke.map_or(0.0, |st| st.half_width(height_px)),
+            kind,
+            _pad: [0; 3],
+            corner_radii: self.clamped_corner_radii(),
+        }
+    }
+
+    /// TriangleList vertices as [x, y, u, v]. UVs span 0..1 across the local bounds
+    /// (`±half_size`), with v pointing down so images appear upright. Curves are flattened to
+    /// `curve_tolerance` on a surface `height_px` tall.
+    pub fn vertices(&self, height_px: u32) -> Vec<ShapeVertex> {
+        let [hx, hy] = self.half_size();
+        self.positions(height_px)
+            .into_iter()
+            .map(|[x, y]| [x, y, 0.5 + 0.5 * x / hx, 0.5 - 0.5 * y / hy])
+            .collect()
+    }
+
+    fn positions(&self, height_px: u32) -> Vec<[f32; 2]> {
         let s = self.size;
+        if self.effective_render_mode() == RenderMode::Sdf {
+            let [ex, ey] = self.half_size().map(|h| h + SDF_QUAD_PAD);
+            return vec![
+                [-ex, -ey], [ex, -ey], [ex,  ey],
+                [-ex, -ey], [ex,  ey], [-ex, ey],
+            ];
+        }
         match self.shape {
             Shape::Triangle => vec![
                 [-s, -s],
                 [0.0, s],
                 [s, -s],
             ],
-            Shape::Square => vec![
-                [-s, -s], [s, -s], [s,  s],
-                [-s, -s], [s,  s], [-s, s],
-            ],
-            Shape::Circle => {
-                let segments = 64u32;
-                let mut v = Vec::with_capacity((segments * 3) as usize);
-                for i in 0..segments {
-                    let a1 = 2.0 * PI * i as f32 / segments as f32;
-                    let a2 = 2.0 * PI * (i + 1) as f32 / segments as f32;
-                    v.push([0.0_f32, 0.0]);
-                    v.push([a1.cos() * s, a1.sin() * s]);
-                    v.push([a2.cos() * s, a2.sin() * s]);
+            Shape::Square | Shape::Rect if self.corner_radii.iter().any(|&r| r > 0.0) => {
+                fan(&self.boundary(height_px)[0])
+            }
+            Shape::Square | Shape::Rect => {
+                let [hx, hy] = self.half_size();
+                vec![
+                    [-hx, -hy], [hx, -hy], [hx,  hy],
+                    [-hx, -hy], [hx,  hy], [-hx, hy],
+                ]
+            }
+            Shape::Circle | Shape::Ellipse => fan(&self.boundary(height_px)[0]),
+            Shape::Ring => {
+                let (inner, s) = self.ring_radii();
+                let mut v = Vec::with_capacity((CIRCLE_SEGMENTS * 6) as usize);
+                for i in 0..CIRCLE_SEGMENTS {
+                    let a1 = 2.0 * PI * i as f32 / CIRCLE_SEGMENTS as f32;
+                    let a2 = 2.0 * PI * (i + 1) as f32 / CIRCLE_SEGMENTS as f32;
+                    let (o1, o2) = ([a1.cos() * s, a1.sin() * s], [a2.cos() * s, a2.sin() * s]);
+                    let (i1, i2) = ([a1.cos() * inner, a1.sin() * inner], [a2.cos() * inner, a2.sin() * inner]);
+                    v.extend_from_slice(&[i1, o1, o2, i1, o2, i2]);
                 }
                 v
             }
+            Shape::Polygon
+            | Shape::QuadBezier
+            | Shape::CubicBezier
+            | Shape::Arc
+            | Shape::Pie
+            | Shape::Sector
+            | Shape::Arrow => tessellate::tessellate(&self.boundary(height_px), self.fill_rule),
+        }
+    }
+
+    /// Boundary contours of the shape in local coordinates, used for stroking.
+    /// Outer contours wind counter-clockwise; holes (the inside of a ring) wind clockwise.
+    /// Polygon contours are passed through as given, and curves run from their first point
+    /// to their last, with `is_closed` telling whether the stroke joins them.
+    pub fn boundary(&self, height_px: u32) -> Vec<Vec<[f32; 2]>> {
+        let s = self.size;
+        // NDC spans 2 units over the surface height
+        let tolerance = self.curve_tolerance.max(f32::EPSILON) * 2.0 / height_px.max(1) as f32;
+        match self.shape {
+            Shape::Triangle => vec![vec![[-s, -s], [s, -s], [0.0, s]]],
+            Shape::Square | Shape::Rect => vec![rounded_rect_outline(self.half_size(), self.clamped_corner_radii())],
+            Shape::Circle => vec![circle_outline(s, CIRCLE_SEGMENTS)],
+            Shape::Ellipse => {
+                let [rx, ry] = self.half_size();
+                vec![circle_outline(1.0, CIRCLE_SEGMENTS).into_iter().map(|[x, y]| [x * rx, y * ry]).collect()]
+            }
+            Shape::Ring => {
+                let (inner, outer) = self.ring_radii();
+                let mut hole = circle_outline(inner, CIRCLE_SEGMENTS);
+                hole.reverse();
+                vec![circle_outline(outer, CIRCLE_SEGMENTS), hole]
+            }
+            Shape::Polygon => self
+                .contours
+                .iter()
+                .map(|contour| contour.iter().map(|&[x, y]| [x * s, y * s]).collect())
+                .collect(),
+            Shape::QuadBezier | Shape::CubicBezier => {
+                let p = self.curve_points();
+                let mut curve = vec![p[0]];
+                match p[..] {
+                    [p0, c, p1] => path::flatten_quad(p0, c, p1, tolerance, &mut curve),
+                    [p0, c1, c2, p1] => path::flatten_cubic(p0, c1, c2, p1, tolerance, &mut curve),
+                    _ => {}
+                }
+                vec![curve]
+            }
+            Shape::Arc => vec![arc_outline(self.arc(s), tolerance)],
+            Shape::Pie => {
+                let mut slice = vec![[0.0, 0.0]];
+                slice.extend(arc_outline(self.arc(s), tolerance));
+                vec![slice]
+            }
+            Shape::Sector => {
+                let (inner, outer) = self.ring_radii();
+                let mut slice = arc_outline(self.arc(outer), tolerance);
+                let mut back = arc_outline(self.arc(inner), tolerance);
+                back.reverse();
+                slice.extend(back);
+                vec![slice]
+            }
+            Shape::Arrow => {
+                let scaled = |[x, y]: [f32; 2]| [x * s, y * s];
+                arrow_outline(scaled(self.from), scaled(self.to), self.head_size * s).into_iter().collect()
+            }
+        }
+    }
+
+    /// Fails when a Bézier is given the wrong number of control points, or, without a stroke,
+    /// control points in a line, which enclose nothing to fill.
+    pub fn check_control_points(&self) -> Result<(), String> {
+        let (kind, count) = match self.shape {
+            Shape::QuadBezier => ("quadratic", 3),
+            Shape::CubicBezier => ("cubic", 4),
+            _ => return Ok(()),
+        };
+        match self.control_points.len() {
+            0 => {}
+            n if n == count => {}
+            n => return Err(format!("a {kind} Bézier takes {count} control points, got {n}")),
+        }
+        if self.stroke.is_none() && self.vertices(1).is_empty() {
+            return Err(format!("the {kind} Bézier's control points are in a line, so it has no area to fill"));
+        }
+        Ok(())
+    }
+
+    /// Fails when the size or any of the shape's dimensions is not a positive number.
+    pub fn check_dimensions(&self) -> Result<(), String> {
+        let dimensions = [
+            ("size", Some(self.size)),
+            ("width", self.width),
+            ("height", self.height),
+            ("rx", self.rx),
+            ("ry", self.ry),
+            ("outer radius", self.outer_radius),
+        ];
+        for (name, value) in dimensions {
+            if let Some(value) = value.filter(|&v| v.is_nan() || v <= 0.0) {
+                return Err(format!("the {name} must be a positive number, got {value}"));
+            }
+        }
+        match self.inner_radius {
+            Some(r) if r.is_nan() || r < 0.0 => Err(format!("the inner radius must not be negative, got {r}")),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails when an arc, pie or sector sweeps no angle.
+    pub fn check_sweep(&self) -> Result<(), String> {
+        let kind = match self.shape {
+            Shape::Arc => "arc",
+            Shape::Pie => "pie",
+            Shape::Sector => "sector",
+            _ => return Ok(()),
+        };
+        let sweep = self.end_angle - self.start_angle;
+        if sweep == 0.0 || !sweep.is_finite() {
+            return Err(format!("the {kind} from {}° to {}° sweeps no angle", self.start_angle, self.end_angle));
+        }
+        Ok(())
+    }
+
+    /// Whether the stroke of boundary contour `i` joins its last point back to its first.
+    pub fn is_closed(&self, i: usize) -> bool {
+        match self.shape {
+            Shape::QuadBezier | Shape::CubicBezier | Shape::Arc => false,
+            _ => self.closed.get(i).copied().unwrap_or(true),
+        }
+    }
+
+    /// Points that shape a curve in local coordinates, in the order of its control polygon:
+    /// the control points of a Bézier, or an arc's ends either side of its centre. Empty for
+    /// other shapes.
+    pub fn control_polygon(&self) -> Vec<[f32; 2]> {
+        let s = self.size;
+        match self.shape {
+            Shape::QuadBezier | Shape::CubicBezier => self.curve_points(),
+            Shape::Arc | Shape::Pie => {
+                let arc = self.arc(s);
+                vec![arc.point(arc.start), arc.center, arc.point(arc.start + arc.sweep)]
+            }
+            Shape::Sector => {
+                let arc = self.arc(self.ring_radii().1);
+                vec![arc.point(arc.start), arc.center, arc.point(arc.start + arc.sweep)]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    // Bézier control points scaled by size, defaulting to an arch over the bounds
+    fn curve_points(&self) -> Vec<[f32; 2]> {
+        let s = self.size;
+        let points = match (self.control_points.is_empty(), &self.shape) {
+            (false, _) => self.control_points.clone(),
+            (true, Shape::QuadBezier) => vec![[-1.0, -1.0], [0.0, 1.0], [1.0, -1.0]],
+            (true, _) => vec![[-1.0, -1.0], [-1.0, 1.0], [1.0, 1.0], [1.0, -1.0]],
+        };
+        points.into_iter().map(|[x, y]| [x * s, y * s]).collect()
+    }
+
+    // The arc between the angles on a circle of radius r around the origin
+    fn arc(&self, r: f32) -> Arc {
+        let start = self.start_angle.to_radians();
+        Arc { center: [0.0, 0.0], radii: [r, r], rotation: 0.0, start, sweep: self.end_angle.to_radians() - start }
+    }
+
+    /// Stroke geometry along every boundary contour, in the layout produced by `stroke::stroke_path`.
+    /// `height_px` is the surface height, as for `vertices`.
+    pub fn stroke_vertices(&self, height_px: u32) -> Vec<StrokeVertex> {
+        self.boundary(height_px)
+            .iter()
+            .enumerate()
+            .flat_map(|(i, contour)| stroke_path(contour, self.is_closed(i)))
+            .collect()
+    }
+}
+
+// Counter-clockwise points on a circle of radius r
+fn circle_outline(r: f32, segments: u32) -> Vec<[f32; 2]> {
+    (0..segments)
+        .map(|i| {
+            let a = 2.0 * PI * i as f32 / segments as f32;
+            [a.cos() * r, a.sin() * r]
+        })
+        .collect()
+}
+
+/// Expands one to four corner radii as in the CSS `border-radius` shorthand: all corners,
+/// then top-left and bottom-right / the others, then top-left / top-right and bottom-left /
+/// bottom-right, then each corner clockwise from the top left.
+pub fn corner_radii(values: &[f32]) -> Result<[f32; 4], String> {
+    match *values {
+        [] => Ok([0.0; 4]),
+        [r] => Ok([r; 4]),
+        [a, b] => Ok([a, b, a, b]),
+        [a, b, c] => Ok([a, b, c, b]),
+        [a, b, c, d] => Ok([a, b, c, d]),
+        _ => Err(format!("expected 1 to 4 corner radii, got {}", values.len())),
+    }
+}
+
+// Counter-clockwise outline of an arrow, or nothing if its ends coincide
+fn arrow_outline(from: [f32; 2], to: [f32; 2], head: f32) -> Option<Vec<[f32; 2]>> {
+    let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+    let len = (dx * dx + dy * dy).sqrt();
+    if len == 0.0 {
+        return None;
+    }
+    let head = head.clamp(0.0, len);
+    // Along the arrow and to its left, scaled to the head's length
+    let (d, n) = ([dx / len * head, dy / len * head], [-dy / len * head, dx / len * head]);
+    let neck = [to[0] - d[0], to[1] - d[1]];
+    let at = |p: [f32; 2], k: f32| [p[0] + n[0] * k, p[1] + n[1] * k];
+    let (shaft, barb) = (1.0 / 6.0, 0.5);
+    Some(vec![
+        at(from, -shaft),
+        at(neck, -shaft),
+        at(neck, -barb),
+        to,
+        at(neck, barb),
+        at(neck, shaft),
+        at(from, shaft),
+    ])
+}
+
+// Every point of an arc, both ends included
+fn arc_outline(arc: Arc, tolerance: f32) -> Vec<[f32; 2]> {
+    let mut v = vec![arc.point(arc.start)];
+    path::flatten_arc(&arc, tolerance, &mut v);
+    v
+}
+
+// Counter-clockwise outline of a rectangle with half-size [hx, hy] and quarter-circle corners
+// of radii [top-left, top-right, bottom-right, bottom-left]
+fn rounded_rect_outline([hx, hy]: [f32; 2], [tl, tr, br, bl]: [f32; 4]) -> Vec<[f32; 2]> {
+    let mut v = Vec::with_capacity((4 * (CORNER_SEGMENTS + 1)) as usize);
+    // Starting at the top-right corner, as the angles run counter-clockwise from +x
+    for (quadrant, (r, [sx, sy])) in [(tr, [1.0, 1.0]), (tl, [-1.0, 1.0]), (bl, [-1.0, -1.0]), (br, [1.0, -1.0])]
+        .into_iter()
+        .enumerate()
+    {
+        let (cx, cy) = (sx * (hx - r), sy * (hy - r));
+        // A sharp corner is a single point, since repeated points would confuse the stroke
+        if r <= 0.0 {
+            v.push([cx, cy]);
+            continue;
+        }
+        for i in 0..=CORNER_SEGMENTS {
+            let a = PI * 0.5 * (quadrant as f32 + i as f32 / CORNER_SEGMENTS as f32);
+            v.push([cx + a.cos() * r, cy + a.sin() * r]);
+        }
+    }
+    v
+}
+
+// Triangle fan around the origin for a convex closed outline
+fn fan(outline: &[[f32; 2]]) -> Vec<[f32; 2]> {
+    let mut v = Vec::with_capacity(outline.len() * 3);
+    for (i, &p) in outline.iter().enumerate() {
+        v.push([0.0_f32, 0.0]);
+        v.push(p);
+        v.push(outline[(i + 1) % outline.len()]);
+    }
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (min, max) corners of the points' bounding box
+    fn bounds(points: impl IntoIterator<Item = [f32; 2]>) -> ([f32; 2], [f32; 2]) {
+        points.into_iter().fold(([f32::MAX; 2], [f32::MIN; 2]), |(lo, hi), [x, y]| {
+            ([lo[0].min(x), lo[1].min(y)], [hi[0].max(x), hi[1].max(y)])
+        })
+    }
+
+    fn assert_close(a: [f32; 2], b: [f32; 2]) {
+        assert!((a[0] - b[0]).abs() < 1e-5 && (a[1] - b[1]).abs() < 1e-5, "{a:?} != {b:?}");
+    }
+
+    // Checks the mesh, outline and UVs all span exactly ±half
+    fn assert_bounds(config: &ShapeConfig, half: [f32; 2]) {
+        assert_close(config.half_size(), half);
+        let vertices = config.vertices(REFERENCE_HEIGHT_PX);
+        let (lo, hi) = bounds(vertices.iter().map(|v| [v[0], v[1]]));
+        assert_close(lo, [-half[0], -half[1]]);
+        assert_close(hi, half);
+        let (lo, hi) = bounds(vertices.iter().map(|v| [v[2], v[3]]));
+        assert_close(lo, [0.0, 0.0]);
+        assert_close(hi, [1.0, 1.0]);
+        let (lo, hi) = bounds(config.boundary(REFERENCE_HEIGHT_PX).concat());
+        assert_close(lo, [-half[0], -half[1]]);
+        assert_close(hi, half);
+    }
+
+    fn rect(width: f32, height: f32, corner_radii: [f32; 4]) -> ShapeConfig {
+        ShapeConfig { shape: Shape::Rect, width: Some(width), height: Some(height), corner_radii, ..Default::default() }
+    }
+
+    #[test]
+    fn rect_bounds() {
+        assert_bounds(&rect(1.2, 0.4, [0.0; 4]), [0.6, 0.2]);
+        assert_bounds(&rect(1.2, 0.4, [0.1, 0.0, 0.2, 0.05]), [0.6, 0.2]);
+        // Without a width or height a rect is the square of `size`
+        assert_bounds(&ShapeConfig { shape: Shape::Rect, size: 0.3, ..Default::default() }, [0.3, 0.3]);
+    }
+
+    #[test]
+    fn corners_are_rounded_individually() {
+        let config = rect(1.0, 1.0, [0.2, 0.0, 0.0, 0.0]);
+        let outline = &config.boundary(REFERENCE_HEIGHT_PX)[0];
+        for sharp in [[0.5, 0.5], [0.5, -0.5], [-0.5, -0.5]] {
+            assert!(outline.contains(&sharp), "{sharp:?} missing");
+        }
+        // The top-left corner is cut by the arc around (-0.3, 0.3)
+        assert!(!outline.contains(&[-0.5, 0.5]));
+        assert!(outline.iter().all(|&[x, y]| x >= -0.3 || y <= 0.3 || (x + 0.3).hypot(y - 0.3) <= 0.2 + 1e-5));
+    }
+
+    #[test]
+    fn overlapping_corner_radii_scale_down_together() {
+        // The left edge is only 0.5 tall, so the radii along it are halved
+        let config = rect(2.0, 0.5, [0.5, 0.25, 0.0, 0.5]);
+        assert_eq!(config.clamped_corner_radii(), [0.25, 0.125, 0.0, 0.25]);
+        assert_eq!(rect(2.0, 2.0, [0.5, 0.25, 0.0, 0.5]).clamped_corner_radii(), [0.5, 0.25, 0.0, 0.5]);
+        assert_eq!(rect(2.0, 2.0, [-1.0, 0.0, 0.0, 0.0]).clamped_corner_radii(), [0.0; 4]);
+    }
+
+    #[test]
+    fn css_corner_radius_shorthand() {
+        assert_eq!(corner_radii(&[0.1]), Ok([0.1; 4]));
+        assert_eq!(corner_radii(&[0.1, 0.2]), Ok([0.1, 0.2, 0.1, 0.2]));
+        assert_eq!(corner_radii(&[0.1, 0.2, 0.3]), Ok([0.1, 0.2, 0.3, 0.2]));
+        assert_eq!(corner_radii(&[0.1, 0.2, 0.3, 0.4]), Ok([0.1, 0.2, 0.3, 0.4]));
+        assert!(corner_radii(&[0.0; 5]).is_err());
+    }
+
+    #[test]
+    fn ellipse_bounds() {
+        let config = ShapeConfig { shape: Shape::Ellipse, rx: Some(0.8), ry: Some(0.3), ..Default::default() };
+        assert_bounds(&config, [0.8, 0.3]);
+        let outline = &config.boundary(REFERENCE_HEIGHT_PX)[0];
+        assert!(outline.iter().all(|&[x, y]| ((x / 0.8).hypot(y / 0.3) - 1.0).abs() < 1e-5));
+    }
+
+    #[test]
+    fn ring_bounds() {
+        let ring = |inner_radius, outer_radius| ShapeConfig {
+            shape: Shape::Ring,
+            inner_radius,
+            outer_radius,
+            ..Default::default()
+        };
+        let config = ring(Some(0.2), Some(0.7));
+        assert_bounds(&config, [0.7, 0.7]);
+        let (lo, hi) = bounds(config.boundary(REFERENCE_HEIGHT_PX)[1].iter().copied());
+        assert_close(lo, [-0.2, -0.2]);
+        assert_close(hi, [0.2, 0.2]);
+        // The inner radius defaults to `inner_ratio` of the outer one, and never exceeds it
+        assert_eq!(ring(None, Some(0.5)).ring_radii(), (0.3, 0.5));
+        assert_eq!(ring(Some(0.9), Some(0.5)).ring_radii(), (0.5, 0.5));
+        // Radii that make no ring are reported rather than drawn
+        let (inner, outer) = ring(None, Some(-0.5)).ring_radii();
+        assert!(inner <= outer);
+        assert!(ring(None, Some(-0.5)).check_dimensions().unwrap_err().contains("outer radius"));
+        assert!(ring(Some(-0.1), None).check_dimensions().unwrap_err().contains("inner radius"));
+        assert!(ring(Some(0.1), Some(f32::NAN)).check_dimensions().is_err());
+        assert!(config.check_dimensions().is_ok());
+    }
+
+    #[test]
+    fn curves_are_flattened_for_the_surface_height() {
+        let config = ShapeConfig {
+            shape: Shape::Arc,
+            fill: Fill::Solid([0.0, 0.0, 1.0, 1.0]),
+            stroke: Some(Stroke { color: [0.0; 4], width: 2.0, units: StrokeUnits::Px }),
+            ..Default::default()
+        };
+        assert!(config.is_curve() && !rect(1.0, 1.0, [0.1; 4]).is_curve());
+        // The same tolerance in pixels takes more points on a taller surface
+        let (short, tall) = (config.boundary(200)[0].len(), config.boundary(2000)[0].len());
+        assert!(tall > 2 * short, "{short} points at 200 px, {tall} at 2000 px");
+        let geometry = config.geometry();
+        assert_eq!(geometry.vertices(2000), config.vertices(2000));
+        assert_eq!(geometry.stroke_vertices(2000).len(), config.stroke_vertices(2000).len());
+    }
+
+    #[test]
+    fn sdf_quads_cover_the_bounds() {
+        for shape in [Shape::Rect, Shape::Ellipse, Shape::Ring] {
+            let config = ShapeConfig {
+                shape,
+                render_mode: RenderMode::Sdf,
+                width: Some(1.0),
+                height: Some(0.6),
+                rx: Some(0.5),
+                ry: Some(0.3),
+                outer_radius: Some(0.4),
+                ..Default::default()
+            };
+            assert!(config.effective_render_mode() == RenderMode::Sdf);
+            let [hx, hy] = config.sdf_params(REFERENCE_HEIGHT_PX).half_size;
+            let (lo, hi) = bounds(config.vertices(REFERENCE_HEIGHT_PX).iter().map(|v| [v[0], v[1]]));
+            assert_close(lo, [-hx - SDF_QUAD_PAD, -hy - SDF_QUAD_PAD]);
+            assert_close(hi, [hx + SDF_QUAD_PAD, hy + SDF_QUAD_PAD]);
         }
     }
 }
@@ -1,14 +1,25 @@
-@group(0) @binding(0) var<uniform> u_color: vec4<f32>;
+// Mesh shape shader. Expects `fill.wgsl` to be prepended for `u_color` and `fill_color`.
+
 @group(0) @binding(1) var<uniform> u_aspect: vec4<f32>; // x = aspect ratio (width / height)
 @group(0) @binding(2) var<uniform> u_pos: vec4<f32>;    // x, y = position offset in NDC
 
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) local: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+}
+
 @vertex
-fn vs_main(@location(0) position: vec2<f32>) -> @builtin(position) vec4<f32> {
+fn vs_main(@location(0) position: vec2<f32>, @location(1) uv: vec2<f32>) -> VertexOutput {
+    var out: VertexOutput;
     let corrected = vec2<f32>(position.x / u_aspect.x + u_pos.x, position.y + u_pos.y);
-    return vec4<f32>(corrected, 0.0, 1.0);
+    out.pos = vec4<f32>(corrected, 0.0, 1.0);
+    out.local = position;
+    out.uv = uv;
+    return out;
 }
 
 @fragment
-fn fs_main() -> @location(0) vec4<f32> {
-    return u_color;
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return fill_color(in.local, in.uv);
 }
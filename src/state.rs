@@ -1,59 +1,272 @@
+use std::ops::Range;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Instant;
 
 use wgpu::{
-    util::DeviceExt, Adapter, BindGroup, Buffer, Color, CommandEncoderDescriptor, Device,
-    FragmentState, Instance, InstanceDescriptor, MultisampleState, Operations,
-    PipelineLayoutDescriptor, PrimitiveState, Queue, RenderPassColorAttachment,
-    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, RequestAdapterOptionsBase,
-    ShaderModuleDescriptor, Surface, TextureFormat, TextureViewDescriptor, VertexState,
-    wgt::DeviceDescriptor,
+    util::DeviceExt, Adapter, BindGroup, BindGroupLayoutEntry, Buffer, Color, CommandEncoderDescriptor, Device,
+    Operations, PipelineLayout, PipelineLayoutDescriptor, Queue,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    ShaderStages, Surface, TextureFormat, TextureViewDescriptor, VertexBufferLayout, wgt::DeviceDescriptor,
 };
 use winit::{dpi::PhysicalSize, window::Window};
 
-use crate::shape::ShapeConfig;
+use crate::axis::AxisColors;
+use crate::background::BackgroundLayer;
+use crate::batch::{BATCH_VERTEX_LAYOUT, Batcher};
+use crate::fill::Fill;
+use crate::frag::{Globals, UserFrag};
+use crate::graph::{GraphLayer, NodeId, SceneGraph, Transform};
+use crate::field::VectorField;
+use crate::heatmap::{Cell, HeatmapLayer};
+use crate::scale::Scales;
+use crate::implicit;
+use crate::instance::{ShapeInstance, INSTANCE_VERTEX_LAYOUT};
+use crate::parametric::Parametric;
+use crate::pipeline::{self, Depth, Tie};
+use crate::shaders::{self, ShaderFile, ShaderSources, ShaderWatcher};
+use crate::shape::{RenderMode, SdfParams, ShapeConfig, ShapeVertex, Stroke};
+use crate::stats::{FrameStats, GpuTimer};
+
+/// Shape vertices: [x, y, u, v] — stride 16 bytes
+pub const SHAPE_VERTEX_LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+    array_stride: 16,
+    step_mode: wgpu::VertexStepMode::Vertex,
+    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2],
+};
+
+/// Stroke vertices: [x, y, nx, ny] — stride 16 bytes
+pub const STROKE_VERTEX_LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+    array_stride: 16,
+    step_mode: wgpu::VertexStepMode::Vertex,
+    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2],
+};
+
+/// Axis vertices: [x, y, r, g, b, a, u, v] — stride 32 bytes
+pub const AXIS_VERTEX_LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+    array_stride: 32,
+    step_mode: wgpu::VertexStepMode::Vertex,
+    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4, 2 => Float32x2],
+};
+
+/// Group 0 of the shape and stroke pipelines: color, aspect, position, SDF (or stroke)
+/// parameters and fill parameters.
+pub const SHAPE_BIND_GROUP: [BindGroupLayoutEntry; 5] = [
+    pipeline::uniform_entry(0, ShaderStages::FRAGMENT),
+    pipeline::uniform_entry(1, ShaderStages::VERTEX),
+    pipeline::uniform_entry(2, ShaderStages::VERTEX),
+    pipeline::uniform_entry(3, ShaderStages::VERTEX_FRAGMENT),
+    pipeline::uniform_entry(4, ShaderStages::FRAGMENT),
+];
+
+/// Group 2 of the shape pipeline with `--frag`: the `Globals` block.
+pub const GLOBALS_BIND_GROUP: [BindGroupLayoutEntry; 1] = [pipeline::uniform_entry(0, ShaderStages::FRAGMENT)];
+
+/// Which shape shader and vertex inputs the shape pipeline uses.
+#[derive(Clone, Copy, PartialEq)]
+pub struct ShapeVariant {
+    pub mode: RenderMode,
+    /// One mesh drawn per `ShapeInstance` (mesh mode only)
+    pub instanced: bool,
+}
+
+impl ShapeVariant {
+    pub const MESH: ShapeVariant = ShapeVariant { mode: RenderMode::Mesh, instanced: false };
+    pub const SDF: ShapeVariant = ShapeVariant { mode: RenderMode::Sdf, instanced: false };
+    pub const INSTANCED: ShapeVariant = ShapeVariant { mode: RenderMode::Mesh, instanced: true };
+    pub const ALL: [ShapeVariant; 3] = [ShapeVariant::MESH, ShapeVariant::SDF, ShapeVariant::INSTANCED];
+
+    /// Shader files making up the pipeline: the shared fill functions followed by the variant's shader.
+    pub fn files(self) -> [&'static ShaderFile; 2] {
+        match self {
+            ShapeVariant { instanced: true, .. } => [&shaders::FILL, &shaders::INSTANCED],
+            ShapeVariant { mode: RenderMode::Mesh, .. } => [&shaders::FILL, &shaders::SHAPE],
+            ShapeVariant { mode: RenderMode::Sdf, .. } => [&shaders::FILL, &shaders::SDF],
+        }
+    }
+
+    pub fn buffers(self) -> &'static [VertexBufferLayout<'static>] {
+        if self.instanced { &[SHAPE_VERTEX_LAYOUT, INSTANCE_VERTEX_LAYOUT] } else { &[SHAPE_VERTEX_LAYOUT] }
+    }
+}
+
+/// Where frames are drawn: a window's swap chain, or an offscreen texture.
+enum Target {
+    Window { window: Arc<Window>, surface: Surface<'static>, present_mode: wgpu::PresentMode },
+    Texture(wgpu::Texture),
+}
+
+// Format of headless render targets
+const OFFSCREEN_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// A layer drawn after the background, ordered by depth.
+enum Item {
+    Heatmap,
+    Axis,
+    /// Plotted curves, drawn with the axis pipeline
+    Plot,
+    Pen,
+    Shape,
+    Stroke,
+    /// Control points of the shape's curve
+    Controls,
+    Colorbar,
+    /// A scene graph shape: slot and mesh
+    Node(u32, Range<u32>),
+}
+
+/// A dot tracing a parametric curve, moved every frame.
+struct Pen {
+    curve: Parametric,
+    /// Seconds to trace the curve once
+    period: f32,
+    color: [f32; 4],
+    buffer: Buffer,
+    vertex_count: u32,
+    /// Whether the curve is defined where the dot is
+    visible: bool,
+}
 
 pub struct State {
-    window: Arc<Window>,
+    target: Target,
     adapter: Adapter,
     device: Device,
     queue: Queue,
     size: PhysicalSize<u32>,
-    surface: Surface<'static>,
     surface_format: TextureFormat,
+    // Depth buffer ordering layers by z, recreated with the target
+    depth_view: wgpu::TextureView,
+    // Shader sources, watched for changes with --shader-dir
+    shader_sources: ShaderSources,
+    shader_watcher: Option<ShaderWatcher>,
+    // Background: a clear color, plus a fullscreen pass for checkerboards and images
+    clear_color: Color,
+    background: Option<BackgroundLayer>,
+    heatmap: Option<HeatmapLayer>,
     // Shape pipeline
+    variant: ShapeVariant,
+    z: f32,
+    shape_depth: Depth,
+    pipeline_layout: PipelineLayout,
     render_pipeline: RenderPipeline,
     vertex_buffer: Buffer,
+    /// The shape again when it is a curve, flattened afresh when the surface height changes
+    curve: Option<ShapeConfig>,
     color_bind_group: BindGroup,
+    texture_bind_group: BindGroup,
     aspect_buffer: Buffer,
     vertex_count: u32,
+    draw_fill: bool,
     _position_buffer: Buffer,
+    sdf_params: SdfParams,
+    sdf_buffer: Buffer,
+    // Per-instance data for instanced shapes, grown as needed
+    instance_buffer: Option<Buffer>,
+    instance_count: u32,
+    /// Field whose arrows are the instances, resampled when the aspect ratio changes
+    vector_field: Option<VectorField>,
+    /// Where axis coordinates are on screen, for the pen and the cell under the cursor
+    scales: Scales,
+    // User --frag code linked into the shape pipeline (optional), with its uniforms in group 2
+    user_frag: Option<UserFrag>,
+    frag_watcher: Option<ShaderWatcher>,
+    start_time: Instant,
+    globals: Globals,
+    globals_buffer: Option<Buffer>,
+    globals_bind_group: Option<BindGroup>,
+    // Stroke pipeline (optional, mesh mode only — SDF shapes stroke in their own shader)
+    stroke: Option<Stroke>,
+    stroke_pipeline_layout: PipelineLayout,
+    stroke_pipeline: Option<RenderPipeline>,
+    stroke_depth: Depth,
+    stroke_vertex_buffer: Option<Buffer>,
+    stroke_bind_group: Option<BindGroup>,
+    stroke_params_buffer: Option<Buffer>,
+    stroke_vertex_count: u32,
     // Axis/grid pipeline (optional)
+    axis_pipeline_layout: PipelineLayout,
     axis_pipeline: Option<RenderPipeline>,
     axis_vertex_buffer: Option<Buffer>,
     axis_vertex_count: u32,
+    axis_z: f32,
+    plot_vertex_buffer: Option<Buffer>,
+    plot_vertex_count: u32,
+    pen: Option<Pen>,
+    /// Control point markers, drawn with the axis pipeline
+    controls_vertex_buffer: Option<Buffer>,
+    controls_vertex_count: u32,
+    /// Colormap legend, drawn with the axis pipeline
+    colorbar_vertex_buffer: Option<Buffer>,
+    colorbar_vertex_count: u32,
+    // Retained scene graph drawn over the shape, shape by shape, or with the opaque shapes
+    // batched into one draw. The renderers are created with the first node.
+    graph: SceneGraph,
+    graph_layer: Option<GraphLayer>,
+    batch_graph: bool,
+    batcher: Option<Batcher>,
+    // Frame statistics
+    stats: FrameStats,
+    gpu_timer: Option<GpuTimer>,
 }
 
 impl State {
-    pub async fn new(window: Arc<Window>, config: ShapeConfig) -> State {
-        let instance = Instance::new(&InstanceDescriptor::default());
+    pub async fn new(window: Arc<Window>, config: ShapeConfig) -> Result<State, String> {
+        let instance = config.adapter.instance();
+        let surface = instance
+            .create_surface(window.clone())
+            .map_err(|e| format!("Failed to create a surface: {e}"))?;
+        let adapter = config.adapter.request(&instance, Some(&surface)).await?;
+        let cap = surface.get_capabilities(&adapter);
+        let (present_mode, warning) = config.present_mode.choose(&cap.present_modes);
+        if let Some(warning) = warning {
+            eprintln!("{warning}");
+        }
+        let size = window.inner_size();
+        let target = Target::Window { window, surface, present_mode };
+        State::with_target(adapter, Some(target), size, cap.formats[0], config).await
+    }
 
-        let adapter = instance
-            .request_adapter(&RequestAdapterOptionsBase::default())
-            .await
-            .unwrap();
+    /// A state drawing into an offscreen texture instead of a window, e.g. for benchmarks.
+    pub async fn headless(config: ShapeConfig, width: u32, height: u32) -> Result<State, String> {
+        let instance = config.adapter.instance();
+        let adapter = config.adapter.request(&instance, None).await?;
+        State::with_target(adapter, None, PhysicalSize::new(width, height), OFFSCREEN_FORMAT, config).await
+    }
 
+    /// Builds everything for `target`, or for an offscreen texture of `size` when `None`.
+    async fn with_target(
+        adapter: Adapter,
+        target: Option<Target>,
+        size: PhysicalSize<u32>,
+        surface_format: TextureFormat,
+        mut config: ShapeConfig,
+    ) -> Result<State, String> {
+        let info = adapter.get_info();
+        // Tinting by no fill would multiply the texture by transparent black
+        if config.texture.as_ref().is_some_and(|t| t.tint) && config.fill == Fill::None {
+            config.fill = Fill::Solid([1.0; 4]);
+        }
+
+        // Pass timing is optional; request the feature only where the adapter has it
+        let timestamps = adapter.features().contains(wgpu::Features::TIMESTAMP_QUERY);
+        let required_features = if timestamps { wgpu::Features::TIMESTAMP_QUERY } else { wgpu::Features::empty() };
         let (device, queue) = adapter
-            .request_device(&DeviceDescriptor::default())
+            .request_device(&DeviceDescriptor { required_features, ..Default::default() })
             .await
-            .unwrap();
+            .map_err(|e| format!("Failed to open {}: {e}", info.name))?;
+        let gpu_timer = timestamps.then(|| GpuTimer::new(&device, &queue));
+        let target = target.unwrap_or_else(|| Target::Texture(offscreen_texture(&device, size)));
+        let depth_view = depth_view(&device, size);
 
-        let size = window.inner_size();
-        let surface = instance.create_surface(window.clone()).unwrap();
-        let cap = surface.get_capabilities(&adapter);
-        let surface_format = cap.formats[0];
+        let shader_sources = ShaderSources::new(config.shader_dir.clone());
+        let shader_watcher = shader_sources.dir().and_then(|dir| {
+            ShaderWatcher::new(dir)
+                .inspect_err(|e| eprintln!("Shader hot-reload disabled: {e}"))
+                .ok()
+        });
 
         // --- Shape vertex buffer ---
-        let vertices = config.vertices();
+        let vertices = config.vertices(size.height);
         let vertex_count = vertices.len() as u32;
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
@@ -61,10 +274,26 @@ impl State {
             usage: wgpu::BufferUsages::VERTEX,
         });
 
-        // --- Color uniform ---
+        // --- Instance buffer (instanced shapes and vector fields only) ---
+        let vector_field = config.vector_field.take();
+        if let Some(field) = &vector_field {
+            config.instances = Some(field.instances(&config.scales, size.width as f32 / size.height as f32));
+        }
+        let instances = config.instances.take();
+        let instance_count = instances.as_ref().map_or(0, |i| i.len() as u32);
+        let instance_buffer = instances.map(|instances| instance_buffer(&device, &instances));
+
+        // --- Color uniform (transparent for gradients and when the fill is disabled) ---
         let color_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
-            contents: bytemuck::cast_slice(&config.color),
+            contents: bytemuck::cast_slice(&config.fill.solid_color()),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        // --- Gradient fill uniform ---
+        let fill_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&config.fill_params()),
             usage: wgpu::BufferUsages::UNIFORM,
         });
 
@@ -85,24 +314,18 @@ impl State {
             usage: wgpu::BufferUsages::UNIFORM,
         });
 
-        let uniform_entry = |binding: u32, visibility: wgpu::ShaderStages| wgpu::BindGroupLayoutEntry {
-            binding,
-            visibility,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            count: None,
-        };
+        // --- SDF parameters uniform (read only by the SDF shader) ---
+        // Rewritten on resize because pixel stroke widths depend on the surface height.
+        let sdf_params = config.sdf_params(size.height);
+        let sdf_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&sdf_params),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
 
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: None,
-            entries: &[
-                uniform_entry(0, wgpu::ShaderStages::FRAGMENT),
-                uniform_entry(1, wgpu::ShaderStages::VERTEX),
-                uniform_entry(2, wgpu::ShaderStages::VERTEX),
-            ],
+            entries: &SHAPE_BIND_GROUP,
         });
 
         let color_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -112,171 +335,731 @@ impl State {
                 wgpu::BindGroupEntry { binding: 0, resource: color_buffer.as_entire_binding() },
                 wgpu::BindGroupEntry { binding: 1, resource: aspect_buffer.as_entire_binding() },
                 wgpu::BindGroupEntry { binding: 2, resource: position_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: sdf_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 4, resource: fill_buffer.as_entire_binding() },
             ],
         });
 
-        // --- Shape shader & pipeline ---
-        let shader = device.create_shader_module(ShaderModuleDescriptor {
+        // --- Fill texture (group 1; a white placeholder when untextured) ---
+        let texture_bind_group_layout = crate::texture::bind_group_layout(&device);
+        let texture_bind_group = crate::texture::create_bind_group(
+            &device,
+            &queue,
+            &texture_bind_group_layout,
+            config.texture.as_ref(),
+        )?;
+
+        // --- User fragment globals (group 2, only with --frag) ---
+        let user_frag = config.frag.take();
+        let globals = Globals {
+            color: config.fill.solid_color(),
+            resolution: [size.width as f32, size.height as f32],
+            ..Default::default()
+        };
+        let globals_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: None,
-            source: wgpu::ShaderSource::Wgsl(include_str!("shape_shader.wgsl").into()),
+            entries: &GLOBALS_BIND_GROUP,
         });
+        let (globals_buffer, globals_bind_group, frag_watcher) = match &user_frag {
+            Some(frag) => {
+                let globals_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: None,
+                    contents: bytemuck::bytes_of(&globals),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                });
+                let globals_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: None,
+                    layout: &globals_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry { binding: 0, resource: globals_buffer.as_entire_binding() }],
+                });
+                let dir = frag.path.parent().filter(|p| !p.as_os_str().is_empty());
+                let frag_watcher = ShaderWatcher::new(dir.unwrap_or(Path::new(".")))
+                    .inspect_err(|e| eprintln!("--frag hot-reload disabled: {e}"))
+                    .ok();
+                (Some(globals_buffer), Some(globals_bind_group), frag_watcher)
+            }
+            None => (None, None, None),
+        };
 
+        // --- Shape pipeline ---
+        // Mesh and SDF shaders share the vertex layout and bind group layout; only the fragment stage differs.
+        let variant = ShapeVariant { mode: config.effective_render_mode(), instanced: instance_buffer.is_some() };
+        let mut shape_bind_group_layouts = vec![&bind_group_layout, &texture_bind_group_layout];
+        if user_frag.is_some() {
+            shape_bind_group_layouts.push(&globals_bind_group_layout);
+        }
         let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: None,
-            bind_group_layouts: &[&bind_group_layout],
+            bind_group_layouts: &shape_bind_group_layouts,
             push_constant_ranges: &[],
         });
+        // Only flat opaque meshes hide what is behind them; antialiased SDF edges, instances,
+        // textures and user code may all leave the shape partly transparent
+        let shape_depth = if variant == ShapeVariant::MESH
+            && user_frag.is_none()
+            && config.texture.is_none()
+            && config.fill.is_opaque()
+        {
+            Depth::Opaque
+        } else {
+            Depth::Translucent
+        };
+        let render_pipeline = match &user_frag {
+            Some(frag) => build_shape_pipeline(
+                &device,
+                &shader_sources,
+                Some(frag),
+                variant,
+                &pipeline_layout,
+                surface_format,
+                shape_depth,
+            )
+            .inspect_err(|e| eprintln!("{e}\nDrawing the regular fill until {} is fixed", frag.path.display()))
+            .ok(),
+            None => None,
+        }
+        .unwrap_or_else(|| {
+            pipeline::load(
+                &device,
+                &shader_sources,
+                &variant.files(),
+                &pipeline_layout,
+                variant.buffers(),
+                surface_format,
+                shape_depth,
+            )
+        });
 
-        let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+        // --- Stroke pipeline (optional, not for instanced shapes) ---
+        // Shares the shape bind group layout: binding 0 is the stroke color and
+        // binding 3 holds the half-width instead of SDF parameters. Binding 4 is unused.
+        let stroke = config.stroke;
+        let stroke_depth = match stroke {
+            Some(stroke) if stroke.color[3] >= 1.0 => Depth::Opaque,
+            // Miters and bevels overlap the segments they join
+            _ => Depth::Once,
+        };
+        let stroke_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: None,
-            layout: Some(&pipeline_layout),
-            vertex: VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                compilation_options: Default::default(),
-                buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: 8,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &[wgpu::VertexAttribute {
-                        offset: 0,
-                        shader_location: 0,
-                        format: wgpu::VertexFormat::Float32x2,
-                    }],
-                }],
-            },
-            primitive: PrimitiveState::default(),
-            depth_stencil: None,
-            multisample: MultisampleState::default(),
-            fragment: Some(FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                compilation_options: Default::default(),
-                targets: &[Some(surface_format.into())],
-            }),
-            multiview: None,
-            cache: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
         });
+        let (stroke_pipeline, stroke_vertex_buffer, stroke_bind_group, stroke_params_buffer, stroke_vertex_count) =
+            match stroke.filter(|_| variant == ShapeVariant::MESH) {
+                Some(stroke) => {
+                    let stroke_verts = config.stroke_vertices(size.height);
+                    let stroke_vertex_count = stroke_verts.len() as u32;
 
-        // --- Axis/grid pipeline (optional) ---
-        let (axis_pipeline, axis_vertex_buffer, axis_vertex_count) =
-            if config.axis || config.axis_grid {
-                let axis_verts = crate::axis::generate_vertices(config.axis_arm_len, config.axis_grid, aspect);
-                let axis_vertex_count = axis_verts.len() as u32;
+                    let stroke_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: None,
+                        contents: bytemuck::cast_slice(&stroke_verts),
+                        usage: wgpu::BufferUsages::VERTEX,
+                    });
 
-                let axis_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: None,
-                    contents: bytemuck::cast_slice(&axis_verts),
-                    usage: wgpu::BufferUsages::VERTEX,
-                });
+                    let stroke_color_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: None,
+                        contents: bytemuck::cast_slice(&stroke.color),
+                        usage: wgpu::BufferUsages::UNIFORM,
+                    });
 
-                let axis_shader = device.create_shader_module(ShaderModuleDescriptor {
-                    label: None,
-                    source: wgpu::ShaderSource::Wgsl(include_str!("axis_shader.wgsl").into()),
-                });
+                    // vec4, only .x (half-width) used
+                    let stroke_data: [f32; 4] = [stroke.half_width(size.height), 0.0, 0.0, 0.0];
+                    let stroke_params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: None,
+                        contents: bytemuck::cast_slice(&stroke_data),
+                        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    });
 
-                let axis_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-                    label: None,
-                    bind_group_layouts: &[],
-                    push_constant_ranges: &[],
-                });
+                    let stroke_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: None,
+                        layout: &bind_group_layout,
+                        entries: &[
+                            wgpu::BindGroupEntry { binding: 0, resource: stroke_color_buffer.as_entire_binding() },
+                            wgpu::BindGroupEntry { binding: 1, resource: aspect_buffer.as_entire_binding() },
+                            wgpu::BindGroupEntry { binding: 2, resource: position_buffer.as_entire_binding() },
+                            wgpu::BindGroupEntry { binding: 3, resource: stroke_params_buffer.as_entire_binding() },
+                            wgpu::BindGroupEntry { binding: 4, resource: fill_buffer.as_entire_binding() },
+                        ],
+                    });
 
-                // Vertex layout: [x, y, r, g, b, a] — stride 24 bytes
-                let axis_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-                    label: None,
-                    layout: Some(&axis_pipeline_layout),
-                    vertex: VertexState {
-                        module: &axis_shader,
-                        entry_point: Some("vs_main"),
-                        compilation_options: Default::default(),
-                        buffers: &[wgpu::VertexBufferLayout {
-                            array_stride: 24,
-                            step_mode: wgpu::VertexStepMode::Vertex,
-                            attributes: &[
-                                wgpu::VertexAttribute {
-                                    offset: 0,
-                                    shader_location: 0,
-                                    format: wgpu::VertexFormat::Float32x2,
-                                },
-                                wgpu::VertexAttribute {
-                                    offset: 8,
-                                    shader_location: 1,
-                                    format: wgpu::VertexFormat::Float32x4,
-                                },
-                            ],
-                        }],
-                    },
-                    primitive: PrimitiveState::default(), // TriangleList
-                    depth_stencil: None,
-                    multisample: MultisampleState::default(),
-                    fragment: Some(FragmentState {
-                        module: &axis_shader,
-                        entry_point: Some("fs_main"),
-                        compilation_options: Default::default(),
-                        targets: &[Some(surface_format.into())],
-                    }),
-                    multiview: None,
-                    cache: None,
-                });
+                    let stroke_pipeline = pipeline::load(
+                        &device,
+                        &shader_sources,
+                        &[&shaders::STROKE],
+                        &stroke_pipeline_layout,
+                        &[STROKE_VERTEX_LAYOUT],
+                        surface_format,
+                        stroke_depth,
+                    );
 
-                (Some(axis_pipeline), Some(axis_vertex_buffer), axis_vertex_count)
-            } else {
-                (None, None, 0)
+                    (
+                        Some(stroke_pipeline),
+                        Some(stroke_vertex_buffer),
+                        Some(stroke_bind_group),
+                        Some(stroke_params_buffer),
+                        stroke_vertex_count,
+                    )
+                }
+                None => (None, None, None, None, 0),
             };
 
+        // --- Background ---
+        let background = BackgroundLayer::new(
+            &device,
+            &queue,
+            surface_format,
+            &config.background,
+            aspect,
+            &shader_sources,
+        )?;
+
+        // --- Heatmap ---
+        let heatmap = config
+            .heatmap
+            .take()
+            .map(|heatmap| {
+                let colormap = &config.colormap;
+                HeatmapLayer::new(&device, &queue, surface_format, heatmap, colormap, &config.scales, &shader_sources)
+            })
+            .transpose()?;
+
+        // --- Axis/grid pipeline (optional) ---
+        let axis_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let axis_colors = AxisColors::for_background(config.background.luminance());
+        let axis_verts = (config.axis || config.axis_grid).then(|| {
+            crate::axis::generate_vertices(
+                config.axis_arm_len,
+                config.axis_grid,
+                &config.scales,
+                aspect,
+                config.axis_render_mode,
+                axis_colors,
+            )
+        });
+        let controls = if config.show_controls { config.control_polygon() } else { Vec::new() };
+        let controls_verts = (!controls.is_empty()).then(|| {
+            crate::axis::control_vertices(&controls, config.position, aspect, config.axis_render_mode, axis_colors)
+        });
+        let colorbar_verts =
+            config.colorbar.then(|| crate::axis::colorbar_vertices(&config.colormap, aspect, axis_colors));
+        // Implicit curves are traced on a grid over the whole view, fine enough to look smooth at
+        // this size, and parametric ones sampled as finely as their bends need
+        let cells = [size.width, size.height].map(|px| (px / implicit::CELL_PX).max(1) as usize);
+        let (mut curves, mut closed) = match &config.implicit {
+            Some(curve) => curve.contours(&config.scales, cells),
+            None => (Vec::new(), Vec::new()),
+        };
+        if let Some(curve) = &config.parametric {
+            let tolerance = config.curve_tolerance.max(f32::EPSILON) * 2.0 / size.height.max(1) as f32;
+            let (polylines, flags) = curve.polylines(&config.scales, aspect, tolerance);
+            curves.extend(polylines);
+            closed.extend(flags);
+        }
+        let plot_verts = (!curves.is_empty()).then(|| {
+            let half_width = config.plot_width / size.height.max(1) as f32;
+            crate::axis::curve_vertices(&curves, &closed, half_width, aspect, config.plot_color)
+        });
+        let pen = config.parametric.take().zip(config.pen_period).map(|(curve, period)| {
+            let p = config.scales.to_ndc(curve.at_fraction(0.0));
+            let vertices = crate::axis::pen_vertices(p, aspect, axis_colors.axis);
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            });
+            Pen { curve, period, color: axis_colors.axis, buffer, vertex_count: vertices.len() as u32, visible: true }
+        });
+        // Curves that collapse to a point stroke to nothing, and get no buffer
+        let vertex_buffer_of = |verts: &Option<Vec<[f32; 8]>>| {
+            let verts = verts.as_ref().filter(|verts| !verts.is_empty());
+            let buffer = verts.map(|verts| {
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: None,
+                    contents: bytemuck::cast_slice(verts),
+                    usage: wgpu::BufferUsages::VERTEX,
+                })
+            });
+            (buffer, verts.map_or(0, |verts| verts.len() as u32))
+        };
+        let (axis_vertex_buffer, axis_vertex_count) = vertex_buffer_of(&axis_verts);
+        let (plot_vertex_buffer, plot_vertex_count) = vertex_buffer_of(&plot_verts);
+        let (controls_vertex_buffer, controls_vertex_count) = vertex_buffer_of(&controls_verts);
+        let (colorbar_vertex_buffer, colorbar_vertex_count) = vertex_buffer_of(&colorbar_verts);
+        let axis_items = [&axis_verts, &plot_verts, &controls_verts, &colorbar_verts];
+        let axis_pipeline = (axis_items.iter().any(|v| v.is_some()) || pen.is_some()).then(|| {
+            pipeline::load(
+                &device,
+                &shader_sources,
+                &[&shaders::AXIS],
+                &axis_pipeline_layout,
+                &[AXIS_VERTEX_LAYOUT],
+                surface_format,
+                // Antialiased lines and dots blend at their edges
+                Depth::Translucent,
+            )
+        });
+
         let state = State {
-            window,
+            target,
             adapter,
             device,
             queue,
             size,
-            surface,
             surface_format,
+            depth_view,
+            shader_sources,
+            shader_watcher,
+            clear_color: config.background.clear_color(),
+            background,
+            heatmap,
+            variant,
+            z: config.z,
+            shape_depth,
+            pipeline_layout,
             render_pipeline,
             vertex_buffer,
+            curve: config.is_curve().then(|| config.geometry()),
             color_bind_group,
+            texture_bind_group,
             aspect_buffer,
             vertex_count,
+            // SDF shapes draw their stroke in the same pass, so the quad is needed even without a fill
+            draw_fill: config.fill != Fill::None
+                || config.texture.is_some()
+                || variant.mode == RenderMode::Sdf,
             _position_buffer: position_buffer,
+            sdf_params,
+            sdf_buffer,
+            instance_buffer,
+            instance_count,
+            vector_field,
+            scales: config.scales,
+            user_frag,
+            frag_watcher,
+            start_time: Instant::now(),
+            globals,
+            globals_buffer,
+            globals_bind_group,
+            stroke,
+            stroke_pipeline_layout,
+            stroke_pipeline,
+            stroke_depth,
+            stroke_vertex_buffer,
+            stroke_bind_group,
+            stroke_params_buffer,
+            stroke_vertex_count,
+            axis_pipeline_layout,
             axis_pipeline,
             axis_vertex_buffer,
             axis_vertex_count,
+            axis_z: config.axis_z,
+            plot_vertex_buffer,
+            plot_vertex_count,
+            pen,
+            controls_vertex_buffer,
+            controls_vertex_count,
+            colorbar_vertex_buffer,
+            colorbar_vertex_count,
+            graph: std::mem::take(&mut config.graph),
+            graph_layer: None,
+            batch_graph: config.batch,
+            batcher: None,
+            stats: FrameStats::new(),
+            gpu_timer,
         };
 
         state.configure_surface();
-        state
+        Ok(state)
     }
 
-    pub fn get_window(&self) -> &Window {
-        &self.window
+    /// The window drawn to, or `None` for a headless `State`.
+    pub fn get_window(&self) -> Option<&Window> {
+        match &self.target {
+            Target::Window { window, .. } => Some(window),
+            Target::Texture(_) => None,
+        }
     }
 
     fn configure_surface(&self) {
-        let config = self
-            .surface
+        let Target::Window { surface, present_mode, .. } = &self.target else { return };
+        let mut config = surface
             .get_default_config(&self.adapter, self.size.width, self.size.height)
             .unwrap();
-        self.surface.configure(&self.device, &config);
+        // Pipelines were built against this format, so the surface must match it
+        config.format = self.surface_format;
+        config.present_mode = *present_mode;
+        surface.configure(&self.device, &config);
     }
 
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
         self.size = new_size;
         self.configure_surface();
+        if let Target::Texture(texture) = &mut self.target {
+            *texture = offscreen_texture(&self.device, new_size);
+        }
+        self.depth_view = depth_view(&self.device, new_size);
         let aspect = new_size.width as f32 / new_size.height as f32;
         let aspect_data: [f32; 4] = [aspect, 0.0, 0.0, 0.0];
         self.queue.write_buffer(&self.aspect_buffer, 0, bytemuck::cast_slice(&aspect_data));
+        if let Some(background) = &mut self.background {
+            background.resize(&self.queue, aspect);
+        }
+        if let Some(layer) = &mut self.graph_layer {
+            layer.resize(&self.queue, aspect);
+        }
+        if let Some(batcher) = &mut self.batcher {
+            batcher.resize(aspect);
+        }
+        // Arrows point along the field on screen, which depends on how far X is stretched
+        if let Some(instances) = self.vector_field.as_ref().map(|field| field.instances(&self.scales, aspect))
+            && let Err(e) = self.set_instances(&instances)
+        {
+            eprintln!("{e}");
+        }
+
+        // Curves are flattened to a tolerance in pixels, which the new height changes
+        if let Some(curve) = &self.curve {
+            let vertices = curve.vertices(new_size.height);
+            self.vertex_count = vertices.len() as u32;
+            self.vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+            if self.stroke_vertex_buffer.is_some() {
+                let vertices = curve.stroke_vertices(new_size.height);
+                self.stroke_vertex_count = vertices.len() as u32;
+                self.stroke_vertex_buffer = Some(self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: None,
+                    contents: bytemuck::cast_slice(&vertices),
+                    usage: wgpu::BufferUsages::VERTEX,
+                }));
+            }
+        }
+
+        // Pixel-sized strokes cover a different NDC width at the new height
+        if let Some(stroke) = self.stroke {
+            let half_width = stroke.half_width(new_size.height);
+            self.sdf_params.stroke_half_width = half_width;
+            self.queue.write_buffer(&self.sdf_buffer, 0, bytemuck::bytes_of(&self.sdf_params));
+            if let Some(buffer) = &self.stroke_params_buffer {
+                let stroke_data: [f32; 4] = [half_width, 0.0, 0.0, 0.0];
+                self.queue.write_buffer(buffer, 0, bytemuck::cast_slice(&stroke_data));
+            }
+        }
+    }
+
+    /// Rebuilds pipelines whose shader files changed on disk since the last call.
+    /// A shader that fails validation is reported and the last good pipeline is kept.
+    /// Returns whether anything changed, i.e. whether the frame needs redrawing.
+    pub fn reload_shaders(&mut self) -> bool {
+        let mut frag_changed = false;
+        if let (Some(watcher), Some(frag)) = (&self.frag_watcher, &mut self.user_frag)
+            && frag.file_name().is_some_and(|name| watcher.changed().contains(name))
+        {
+            match UserFrag::load(&frag.path) {
+                Ok(reloaded) => {
+                    *frag = reloaded;
+                    frag_changed = true;
+                }
+                Err(e) => eprintln!("{e}"),
+            }
+        }
+        let changed = self.shader_watcher.as_ref().map(|w| w.changed()).unwrap_or_default();
+        if changed.is_empty() && !frag_changed {
+            return false;
+        }
+        let touches = |files: &[&ShaderFile]| files.iter().any(|f| changed.contains(f.name));
+
+        let mut shape_files = self.variant.files().to_vec();
+        if self.user_frag.is_some() {
+            shape_files[0] = &shaders::FRAG_WRAPPER;
+        }
+        if frag_changed || touches(&shape_files) {
+            let result = build_shape_pipeline(
+                &self.device,
+                &self.shader_sources,
+                self.user_frag.as_ref(),
+                self.variant,
+                &self.pipeline_layout,
+                self.surface_format,
+                self.shape_depth,
+            );
+            match &self.user_frag {
+                Some(frag) => report_reload(&frag.path.display().to_string(), result, |p| self.render_pipeline = p),
+                None => report_reload(&shaders::label(&shape_files), result, |p| self.render_pipeline = p),
+            }
+        }
+        if self.stroke_pipeline.is_some() && touches(&[&shaders::STROKE]) {
+            let (layout, depth) = (&self.stroke_pipeline_layout, self.stroke_depth);
+            let result = self.rebuild(&[&shaders::STROKE], layout, &[STROKE_VERTEX_LAYOUT], depth);
+            report_reload(shaders::STROKE.name, result, |p| self.stroke_pipeline = Some(p));
+        }
+        if self.axis_pipeline.is_some() && touches(&[&shaders::AXIS]) {
+            let layout = &self.axis_pipeline_layout;
+            let result = self.rebuild(&[&shaders::AXIS], layout, &[AXIS_VERTEX_LAYOUT], Depth::Translucent);
+            report_reload(shaders::AXIS.name, result, |p| self.axis_pipeline = Some(p));
+        }
+        if let Some(background) = &self.background
+            && touches(&[&shaders::BACKGROUND])
+        {
+            let result = self.rebuild(&[&shaders::BACKGROUND], background.pipeline_layout(), &[], Depth::Ignore);
+            report_reload(shaders::BACKGROUND.name, result, |p| {
+                if let Some(background) = &mut self.background {
+                    background.set_pipeline(p);
+                }
+            });
+        }
+        if let Some(layer) = &self.heatmap
+            && touches(&[&shaders::HEATMAP])
+        {
+            let result = self.rebuild(&[&shaders::HEATMAP], layer.pipeline_layout(), &[], Depth::Translucent);
+            report_reload(shaders::HEATMAP.name, result, |p| {
+                if let Some(layer) = &mut self.heatmap {
+                    layer.set_pipeline(p);
+                }
+            });
+        }
+        let graph_files = ShapeVariant::INSTANCED.files();
+        if let Some(layer) = &self.graph_layer
+            && touches(&graph_files)
+        {
+            let buffers = ShapeVariant::INSTANCED.buffers();
+            let rebuild = |depth| self.rebuild(&graph_files, layer.pipeline_layout(), buffers, depth);
+            let result = rebuild(Depth::Opaque).and_then(|opaque| Ok((opaque, rebuild(Depth::Translucent)?)));
+            report_reload(&shaders::label(&graph_files), result, |(opaque, translucent)| {
+                if let Some(layer) = &mut self.graph_layer {
+                    layer.set_pipelines(opaque, translucent);
+                }
+            });
+        }
+        if let Some(batcher) = &self.batcher
+            && touches(&[&shaders::BATCH])
+        {
+            let layout = batcher.pipeline_layout();
+            let result = self.rebuild(&[&shaders::BATCH], layout, &[BATCH_VERTEX_LAYOUT], Depth::Opaque);
+            report_reload(shaders::BATCH.name, result, |p| {
+                if let Some(batcher) = &mut self.batcher {
+                    batcher.set_pipeline(p);
+                }
+            });
+        }
+        true
+    }
+
+    /// Whether shader files are being watched for hot-reload.
+    pub fn watching(&self) -> bool {
+        self.shader_watcher.is_some() || self.frag_watcher.is_some()
+    }
+
+    /// Whether frames change with time alone, as for `ShapeConfig::animated`.
+    pub fn animating(&self) -> bool {
+        self.user_frag.is_some() || self.pen.is_some()
+    }
+
+    fn rebuild(
+        &self,
+        files: &[&ShaderFile],
+        layout: &PipelineLayout,
+        buffers: &[VertexBufferLayout],
+        depth: Depth,
+    ) -> Result<RenderPipeline, String> {
+        let source = self.shader_sources.compose(files)?;
+        pipeline::build(&self.device, &shaders::label(files), layout, &source, buffers, self.surface_format, depth)
+    }
+
+    /// Replaces the instances of an instanced shape, reallocating the buffer when it grows.
+    pub fn set_instances(&mut self, instances: &[ShapeInstance]) -> Result<(), String> {
+        let Some(buffer) = &mut self.instance_buffer else {
+            return Err("set_instances: the shape was not created with instances".to_owned());
+        };
+        let bytes: &[u8] = bytemuck::cast_slice(instances);
+        if bytes.len() as u64 > buffer.size() {
+            *buffer = instance_buffer(&self.device, instances);
+        } else {
+            self.queue.write_buffer(buffer, 0, bytes);
+        }
+        self.instance_count = instances.len() as u32;
+        Ok(())
+    }
+
+    /// Adds a shape to the scene graph; see `SceneGraph::add_shape`.
+    pub fn add_shape(
+        &mut self,
+        parent: Option<NodeId>,
+        vertices: &[ShapeVertex],
+        color: [f32; 4],
+        transform: Transform,
+    ) -> Result<NodeId, String> {
+        self.graph.add_shape(parent, vertices, color, transform)
+    }
+
+    pub fn add_group(&mut self, parent: Option<NodeId>, transform: Transform) -> Result<NodeId, String> {
+        self.graph.add_group(parent, transform)
+    }
+
+    /// Removes a scene graph node and its descendants.
+    pub fn remove(&mut self, id: NodeId) -> Result<(), String> {
+        self.graph.remove(id)
+    }
+
+    pub fn set_transform(&mut self, id: NodeId, transform: Transform) -> Result<(), String> {
+        self.graph.set_transform(id, transform)
+    }
+
+    pub fn set_color(&mut self, id: NodeId, color: [f32; 4]) -> Result<(), String> {
+        self.graph.set_color(id, color)
+    }
+
+    pub fn set_z(&mut self, id: NodeId, z: f32) -> Result<(), String> {
+        self.graph.set_z(id, z)
+    }
+
+    pub fn graph(&self) -> &SceneGraph {
+        &self.graph
+    }
+
+    /// The adapter everything is drawn with.
+    pub fn adapter_info(&self) -> wgpu::AdapterInfo {
+        self.adapter.get_info()
+    }
+
+    /// Blocks until the GPU has finished all submitted work.
+    pub fn wait_idle(&self) {
+        let _ = self.device.poll(wgpu::PollType::wait_indefinitely());
+    }
+
+    /// Cursor position in physical pixels, exposed to `--frag` shaders as `u_globals.mouse`.
+    /// Returns whether the frame depends on it.
+    pub fn set_mouse(&mut self, x: f32, y: f32) -> bool {
+        self.globals.mouse = [x, y];
+        self.user_frag.is_some()
+    }
+
+    /// The heatmap cell under the mouse cursor.
+    pub fn hovered_cell(&self) -> Option<Cell> {
+        let [x, y] = self.globals.mouse;
+        let p = [2.0 * x / self.size.width as f32 - 1.0, 1.0 - 2.0 * y / self.size.height as f32];
+        self.heatmap.as_ref()?.heatmap().cell_at(self.scales.from_ndc(p))
+    }
+
+    pub fn stats_mut(&mut self) -> &mut FrameStats {
+        &mut self.stats
+    }
+
+    pub fn write_stats(&self, path: &Path) -> Result<(), String> {
+        self.stats.write_json(path, &self.adapter.get_info())
     }
 
     pub fn render(&mut self) {
-        let frame = self
-            .surface
-            .get_current_texture()
-            .expect("Failed to acquire next swapchain texture");
+        let cpu_start = Instant::now();
+        if let Some(buffer) = &self.globals_buffer {
+            self.globals.time = self.start_time.elapsed().as_secs_f32();
+            self.globals.resolution = [self.size.width as f32, self.size.height as f32];
+            self.queue.write_buffer(buffer, 0, bytemuck::bytes_of(&self.globals));
+            self.globals.frame += 1;
+        }
+        if let Some(pen) = &mut self.pen {
+            let fraction = (self.start_time.elapsed().as_secs_f32() / pen.period).fract();
+            let p = self.scales.to_ndc(pen.curve.at_fraction(fraction));
+            pen.visible = p.iter().all(|c| c.is_finite());
+            if pen.visible {
+                let aspect = self.size.width as f32 / self.size.height as f32;
+                let vertices = crate::axis::pen_vertices(p, aspect, pen.color);
+                self.queue.write_buffer(&pen.buffer, 0, bytemuck::cast_slice(&vertices));
+            }
+        }
+
+        if self.graph_layer.is_none() && !self.graph.is_empty() {
+            let aspect = self.size.width as f32 / self.size.height as f32;
+            let batch = self.batch_graph && Batcher::supported(&self.adapter);
+            if self.batch_graph && !batch {
+                eprintln!("This adapter cannot read storage buffers in vertex shaders; not batching the scene graph");
+            }
+            if batch {
+                self.batcher = Some(Batcher::new(&self.device, self.surface_format, aspect, &self.shader_sources));
+            }
+            // Draws every shape, or only the translucent ones when batching
+            let (device, queue) = (&self.device, &self.queue);
+            self.graph_layer = Some(GraphLayer::new(device, queue, self.surface_format, aspect, &self.shader_sources));
+        }
+        if let Some(layer) = &mut self.graph_layer {
+            let changes = self.graph.take_changes();
+            layer.sync(&self.device, &self.queue, &self.graph, &changes);
+            if let Some(batcher) = &mut self.batcher {
+                batcher.sync(&self.device, &self.queue, &self.graph, &changes);
+            }
+        }
 
-        let view = frame.texture.create_view(&TextureViewDescriptor::default());
+        let (frame, view) = match &self.target {
+            Target::Window { surface, .. } => {
+                let frame = surface.get_current_texture().expect("Failed to acquire next swapchain texture");
+                let view = frame.texture.create_view(&TextureViewDescriptor::default());
+                (Some(frame), view)
+            }
+            Target::Texture(texture) => (None, texture.create_view(&TextureViewDescriptor::default())),
+        };
 
         let mut encoder = self
             .device
             .create_command_encoder(&CommandEncoderDescriptor { label: None });
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.arm();
+        }
+        // Opaque layers are drawn front to back with depth writes, so hidden pixels are
+        // skipped; translucent ones back to front over them, each blending with what is behind
+        let mut opaque = Vec::new();
+        let mut translucent = Vec::new();
+        let mut push = |depth: Depth, z: f32, tie: Tie, item: Item| {
+            let layers = if depth == Depth::Opaque { &mut opaque } else { &mut translucent };
+            layers.push((pipeline::layer_depth(z, tie), item));
+        };
+        if let Some(layer) = &self.heatmap {
+            push(Depth::Translucent, layer.heatmap().z, Tie::Heatmap, Item::Heatmap);
+        }
+        if self.axis_vertex_buffer.is_some() {
+            push(Depth::Translucent, self.axis_z, Tie::Axis, Item::Axis);
+        }
+        if self.plot_vertex_buffer.is_some() {
+            push(Depth::Translucent, self.axis_z, Tie::Plot, Item::Plot);
+        }
+        if self.pen.as_ref().is_some_and(|pen| pen.visible) {
+            push(Depth::Translucent, self.axis_z, Tie::Plot, Item::Pen);
+        }
+        // A fill or stroke without area has no triangles, and an instanced shape may have no
+        // instances; either leaves no buffer to bind
+        let instanced_without_instances = self.instance_buffer.is_some() && self.instance_count == 0;
+        if self.draw_fill && self.vertex_count > 0 && !instanced_without_instances {
+            push(self.shape_depth, self.z, Tie::Shape, Item::Shape);
+        }
+        if self.stroke_pipeline.is_some() && self.stroke_vertex_count > 0 {
+            push(self.stroke_depth, self.z, Tie::Stroke, Item::Stroke);
+        }
+        if self.controls_vertex_buffer.is_some() {
+            push(Depth::Translucent, self.z, Tie::Controls, Item::Controls);
+        }
+        if self.graph_layer.is_some() {
+            for (slot, mesh) in self.graph.draw_list() {
+                let opaque = self.graph.opaque(*slot);
+                if opaque && self.batcher.is_some() {
+                    continue;
+                }
+                let depth = if opaque { Depth::Opaque } else { Depth::Translucent };
+                push(depth, self.graph.world_z(*slot), Tie::Graph, Item::Node(*slot, mesh.clone()));
+            }
+        }
+        if self.colorbar_vertex_buffer.is_some() {
+            push(Depth::Translucent, pipeline::TOP_LAYER, Tie::Legend, Item::Colorbar);
+        }
+        // Stable sorts, so shapes of the scene graph at equal depth keep their drawing order
+        opaque.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        translucent.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        let mut vertices = 0;
 
         {
             let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
@@ -286,31 +1069,198 @@ impl State {
                     depth_slice: None,
                     resolve_target: None,
                     ops: Operations {
-                        load: wgpu::LoadOp::Clear(Color::WHITE),
+                        load: wgpu::LoadOp::Clear(self.clear_color),
                         store: wgpu::StoreOp::Store,
                     },
                 })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(Operations { load: wgpu::LoadOp::Clear(1.0), store: wgpu::StoreOp::Discard }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: self.gpu_timer.as_ref().and_then(|t| t.timestamp_writes()),
                 occlusion_query_set: None,
             });
 
-            // Draw axes/grid first so the shape renders on top
-            if let (Some(pipeline), Some(buffer)) = (&self.axis_pipeline, &self.axis_vertex_buffer) {
-                rpass.set_pipeline(pipeline);
-                rpass.set_vertex_buffer(0, buffer.slice(..));
-                rpass.draw(0..self.axis_vertex_count, 0..1);
+            if let Some(background) = &self.background {
+                background.draw(&mut rpass);
+                vertices += 3;
             }
 
-            // Draw shape
-            rpass.set_pipeline(&self.render_pipeline);
-            rpass.set_bind_group(0, &self.color_bind_group, &[]);
-            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            rpass.draw(0..self.vertex_count, 0..1);
+            vertices += self.draw_items(&mut rpass, &opaque, true);
+            if let Some(batcher) = &self.batcher {
+                // Depth comes from each vertex
+                let (width, height) = (self.size.width as f32, self.size.height as f32);
+                rpass.set_viewport(0.0, 0.0, width, height, 0.0, 1.0);
+                vertices += batcher.draw(&mut rpass);
+            }
+            vertices += self.draw_items(&mut rpass, &translucent, false);
+        }
+        if let Some(timer) = &self.gpu_timer {
+            timer.resolve(&mut encoder);
         }
 
         self.queue.submit(Some(encoder.finish()));
-        self.window.pre_present_notify();
-        frame.present();
+        if let (Some(frame), Target::Window { window, .. }) = (frame, &self.target) {
+            window.pre_present_notify();
+            frame.present();
+        }
+
+        let gpu_ms = self.gpu_timer.as_mut().and_then(|timer| {
+            timer.submitted();
+            timer.collect(&self.device)
+        });
+        self.stats.vertices = vertices;
+        self.stats.record(cpu_start.elapsed(), gpu_ms);
+    }
+
+    /// Draws `items`, each at its depth. Returns the number of vertices drawn.
+    fn draw_items(&self, rpass: &mut wgpu::RenderPass, items: &[(f32, Item)], opaque: bool) -> u32 {
+        let (width, height) = (self.size.width as f32, self.size.height as f32);
+        let mut vertices = 0;
+        // Consecutive scene graph shapes share their pipeline and bindings
+        let mut graph_bound = false;
+        for (depth, item) in items {
+            // Shaders leave z at 0, so the viewport's depth range places the whole layer
+            rpass.set_viewport(0.0, 0.0, width, height, *depth, *depth);
+            if !matches!(item, Item::Node(..)) {
+                graph_bound = false;
+            }
+            match item {
+                Item::Heatmap => {
+                    if let Some(layer) = &self.heatmap {
+                        layer.draw(rpass);
+                        vertices += 6;
+                    }
+                }
+                // Everything drawn with the axis pipeline
+                Item::Axis | Item::Plot | Item::Pen | Item::Controls | Item::Colorbar => {
+                    let (buffer, count) = match item {
+                        Item::Axis => (self.axis_vertex_buffer.as_ref(), self.axis_vertex_count),
+                        Item::Plot => (self.plot_vertex_buffer.as_ref(), self.plot_vertex_count),
+                        Item::Pen => self.pen.as_ref().map_or((None, 0), |pen| (Some(&pen.buffer), pen.vertex_count)),
+                        Item::Controls => (self.controls_vertex_buffer.as_ref(), self.controls_vertex_count),
+                        _ => (self.colorbar_vertex_buffer.as_ref(), self.colorbar_vertex_count),
+                    };
+                    if let (Some(pipeline), Some(buffer)) = (&self.axis_pipeline, buffer) {
+                        rpass.set_pipeline(pipeline);
+                        rpass.set_vertex_buffer(0, buffer.slice(..));
+                        rpass.draw(0..count, 0..1);
+                        vertices += count;
+                    }
+                }
+                Item::Shape => {
+                    rpass.set_pipeline(&self.render_pipeline);
+                    rpass.set_bind_group(0, &self.color_bind_group, &[]);
+                    rpass.set_bind_group(1, &self.texture_bind_group, &[]);
+                    if let Some(bind_group) = &self.globals_bind_group {
+                        rpass.set_bind_group(2, bind_group, &[]);
+                    }
+                    rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                    match &self.instance_buffer {
+                        Some(instances) => {
+                            rpass.set_vertex_buffer(1, instances.slice(..));
+                            rpass.draw(0..self.vertex_count, 0..self.instance_count);
+                            vertices += self.vertex_count * self.instance_count;
+                        }
+                        None => {
+                            rpass.draw(0..self.vertex_count, 0..1);
+                            vertices += self.vertex_count;
+                        }
+                    }
+                }
+                Item::Stroke => {
+                    if let (Some(pipeline), Some(buffer), Some(bind_group)) =
+                        (&self.stroke_pipeline, &self.stroke_vertex_buffer, &self.stroke_bind_group)
+                    {
+                        rpass.set_pipeline(pipeline);
+                        rpass.set_bind_group(0, bind_group, &[]);
+                        rpass.set_vertex_buffer(0, buffer.slice(..));
+                        rpass.draw(0..self.stroke_vertex_count, 0..1);
+                        vertices += self.stroke_vertex_count;
+                    }
+                }
+                Item::Node(slot, mesh) => {
+                    if let Some(layer) = &self.graph_layer {
+                        if !graph_bound {
+                            layer.bind(rpass, opaque);
+                            graph_bound = true;
+                        }
+                        vertices += layer.draw_shape(rpass, *slot, mesh.clone());
+                    }
+                }
+            }
+        }
+        vertices
+    }
+}
+
+fn instance_buffer(device: &Device, instances: &[ShapeInstance]) -> Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("instances"),
+        contents: bytemuck::cast_slice(instances),
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+fn offscreen_texture(device: &Device, size: PhysicalSize<u32>) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("offscreen target"),
+        size: wgpu::Extent3d { width: size.width.max(1), height: size.height.max(1), depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: OFFSCREEN_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+fn depth_view(device: &Device, size: PhysicalSize<u32>) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("depth"),
+        size: wgpu::Extent3d { width: size.width.max(1), height: size.height.max(1), depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: pipeline::DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    texture.create_view(&TextureViewDescriptor::default())
+}
+
+/// Builds the shape pipeline, linking the user's `--frag` file in place of the regular fill when given.
+fn build_shape_pipeline(
+    device: &Device,
+    sources: &ShaderSources,
+    frag: Option<&UserFrag>,
+    variant: ShapeVariant,
+    layout: &PipelineLayout,
+    format: TextureFormat,
+    depth: Depth,
+) -> Result<RenderPipeline, String> {
+    let files = variant.files();
+    match frag {
+        Some(frag) => {
+            let source = frag.compose(sources, files[1])?;
+            frag.check(&source)?;
+            let label = frag.path.display().to_string();
+            pipeline::build(device, &label, layout, &source, variant.buffers(), format, depth)
+        }
+        None => {
+            let source = sources.compose(&files)?;
+            pipeline::build(device, &shaders::label(&files), layout, &source, variant.buffers(), format, depth)
+        }
+    }
+}
+
+fn report_reload<T>(label: &str, result: Result<T, String>, apply: impl FnOnce(T)) {
+    match result {
+        Ok(pipeline) => {
+            eprintln!("Reloaded {label}");
+            apply(pipeline);
+        }
+        Err(e) => eprintln!("{e}\nKeeping the previous {label} pipeline"),
     }
 }
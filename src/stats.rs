@@ -0,0 +1,274 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+
+use wgpu::{Buffer, CommandEncoder, Device, QuerySet, Queue, RenderPassTimestampWrites};
+
+/// How often the window title summary is refreshed.
+const SUMMARY_INTERVAL: Duration = Duration::from_millis(500);
+/// Most frames kept between summaries when the whole run is not kept.
+const MAX_WINDOW: usize = 4096;
+
+/// Per-frame timings collected by `State::render`.
+pub struct FrameStats {
+    start: Instant,
+    last_present: Option<Instant>,
+    /// Time between consecutive presents
+    frame_ms: Vec<f32>,
+    /// Time spent in `State::render`: uniform updates, encoding, submit and present
+    cpu_ms: Vec<f32>,
+    /// Render pass duration from timestamp queries, when supported
+    gpu_ms: Vec<f32>,
+    /// Vertices drawn in the last frame
+    pub vertices: u32,
+    /// Frames counted over the whole run
+    frames: usize,
+    last_summary: Instant,
+    /// `frames` and the first sample of `cpu_ms` at the last summary
+    summary_frames: usize,
+    summary_from: usize,
+    /// Keep every frame's timings for `write_json`, rather than only those since the last summary
+    keep_history: bool,
+}
+
+impl Default for FrameStats {
+    fn default() -> FrameStats {
+        FrameStats::new()
+    }
+}
+
+impl FrameStats {
+    pub fn new() -> FrameStats {
+        let now = Instant::now();
+        FrameStats {
+            start: now,
+            last_present: None,
+            frame_ms: Vec::new(),
+            cpu_ms: Vec::new(),
+            gpu_ms: Vec::new(),
+            vertices: 0,
+            frames: 0,
+            last_summary: now,
+            summary_frames: 0,
+            summary_from: 0,
+            keep_history: false,
+        }
+    }
+
+    /// Keeps the timings of every frame from now on, for `write_json`.
+    pub fn keep_history(&mut self) {
+        self.keep_history = true;
+    }
+
+    pub fn record(&mut self, cpu: Duration, gpu_ms: Option<f32>) {
+        let now = Instant::now();
+        if let Some(last) = self.last_present {
+            self.frame_ms.push((now - last).as_secs_f32() * 1000.0);
+        }
+        self.last_present = Some(now);
+        self.cpu_ms.push(cpu.as_secs_f32() * 1000.0);
+        self.gpu_ms.extend(gpu_ms);
+        self.frames += 1;
+        // Without a summary to empty them, the oldest frames make way
+        if !self.keep_history && self.cpu_ms.len() > MAX_WINDOW {
+            for samples in [&mut self.frame_ms, &mut self.cpu_ms, &mut self.gpu_ms] {
+                samples.drain(..samples.len().saturating_sub(MAX_WINDOW / 2));
+            }
+            self.summary_from = 0;
+        }
+    }
+
+    /// A one-line summary of the frames since the last call, at most every
+    /// `SUMMARY_INTERVAL`.
+    pub fn summary(&mut self) -> Option<String> {
+        let elapsed = self.last_summary.elapsed();
+        if elapsed < SUMMARY_INTERVAL {
+            return None;
+        }
+        let frames = self.frames - self.summary_frames;
+        self.summary_frames = self.frames;
+        let mut cpu = self.cpu_ms[self.summary_from..].to_vec();
+        self.last_summary = Instant::now();
+        self.summary_from = self.cpu_ms.len();
+        let gpu = self.gpu_ms.last().copied();
+        if !self.keep_history {
+            self.frame_ms.clear();
+            self.cpu_ms.clear();
+            self.gpu_ms.clear();
+            self.summary_from = 0;
+        }
+
+        let mut text = format!("{:.1} fps", frames as f32 / elapsed.as_secs_f32());
+        if !cpu.is_empty() {
+            cpu.sort_by(f32::total_cmp);
+            text += &format!(" | cpu {:.2} ms (p99 {:.2})", percentile(&cpu, 50.0), percentile(&cpu, 99.0));
+        }
+        if let Some(gpu) = gpu {
+            text += &format!(" | gpu {gpu:.2} ms");
+        }
+        text += &format!(" | {} vertices", self.vertices);
+        Some(text)
+    }
+
+    /// Writes totals and percentiles over the whole run as JSON; the percentiles only cover
+    /// the frames since the last summary unless `keep_history` was called first.
+    pub fn write_json(&self, path: &Path, adapter: &wgpu::AdapterInfo) -> Result<(), String> {
+        let duration = self.start.elapsed().as_secs_f32();
+        let json = serde_json::json!({
+            "adapter": adapter.name,
+            "backend": format!("{:?}", adapter.backend),
+            "frames": self.frames,
+            "duration_s": duration,
+            "fps": self.frames as f32 / duration,
+            "vertices": self.vertices,
+            "frame_ms": distribution(&self.frame_ms),
+            "cpu_ms": distribution(&self.cpu_ms),
+            "gpu_ms": distribution(&self.gpu_ms),
+        });
+        let text = serde_json::to_string_pretty(&json).unwrap();
+        std::fs::write(path, text + "\n").map_err(|e| format!("failed to write {}: {e}", path.display()))
+    }
+}
+
+/// Mean, percentiles and maximum of `samples`, or null when there are none.
+fn distribution(samples: &[f32]) -> serde_json::Value {
+    if samples.is_empty() {
+        return serde_json::Value::Null;
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    serde_json::json!({
+        "mean": sorted.iter().sum::<f32>() / sorted.len() as f32,
+        "p50": percentile(&sorted, 50.0),
+        "p90": percentile(&sorted, 90.0),
+        "p99": percentile(&sorted, 99.0),
+        "max": sorted[sorted.len() - 1],
+    })
+}
+
+/// Nearest-rank percentile of non-empty, ascending `sorted`.
+fn percentile(sorted: &[f32], p: f32) -> f32 {
+    let rank = (p / 100.0 * sorted.len() as f32).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+// Readback buffer states
+const IDLE: u8 = 0;
+const MAPPING: u8 = 1;
+const MAPPED: u8 = 2;
+
+/// Times the render pass with a pair of timestamp queries.
+///
+/// Results are read back asynchronously: while the previous frame's readback is still in
+/// flight, frames go untimed rather than stalling on the GPU.
+pub struct GpuTimer {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    readback_buffer: Buffer,
+    /// Nanoseconds per timestamp tick
+    period: f32,
+    state: Arc<AtomicU8>,
+    armed: bool,
+}
+
+impl GpuTimer {
+    pub fn new(device: &Device, queue: &Queue) -> GpuTimer {
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("pass timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let size = 2 * std::mem::size_of::<u64>() as u64;
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        GpuTimer {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period: queue.get_timestamp_period(),
+            state: Arc::new(AtomicU8::new(IDLE)),
+            armed: false,
+        }
+    }
+
+    /// Decides whether this frame is timed; call before `timestamp_writes`.
+    pub fn arm(&mut self) {
+        self.armed = self.state.load(Ordering::Acquire) == IDLE;
+    }
+
+    pub fn timestamp_writes(&self) -> Option<RenderPassTimestampWrites<'_>> {
+        self.armed.then_some(RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(0),
+            end_of_pass_write_index: Some(1),
+        })
+    }
+
+    /// Copies the timestamps to the readback buffer, after the pass ends.
+    pub fn resolve(&self, encoder: &mut CommandEncoder) {
+        if self.armed {
+            encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+            encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, None);
+        }
+    }
+
+    /// Starts reading back this frame's timestamps, after submit.
+    pub fn submitted(&mut self) {
+        if !std::mem::take(&mut self.armed) {
+            return;
+        }
+        self.state.store(MAPPING, Ordering::Release);
+        let state = self.state.clone();
+        self.readback_buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            state.store(if result.is_ok() { MAPPED } else { IDLE }, Ordering::Release);
+        });
+    }
+
+    /// The pass duration in milliseconds once a readback has completed.
+    pub fn collect(&self, device: &Device) -> Option<f32> {
+        let _ = device.poll(wgpu::PollType::Poll);
+        if self.state.load(Ordering::Acquire) != MAPPED {
+            return None;
+        }
+        let ticks: [u64; 2] = {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            bytemuck::pod_read_unaligned(&data)
+        };
+        self.readback_buffer.unmap();
+        self.state.store(IDLE, Ordering::Release);
+        Some(ticks[1].wrapping_sub(ticks[0]) as f32 * self.period / 1e6)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_recent_frames_are_kept_without_history() {
+        let mut stats = FrameStats::new();
+        for _ in 0..3 * MAX_WINDOW {
+            stats.record(Duration::from_millis(1), Some(0.5));
+        }
+        assert!(stats.cpu_ms.len() <= MAX_WINDOW && stats.gpu_ms.len() <= MAX_WINDOW);
+        assert_eq!(stats.frames, 3 * MAX_WINDOW);
+
+        let mut stats = FrameStats::new();
+        stats.keep_history();
+        for _ in 0..3 * MAX_WINDOW {
+            stats.record(Duration::from_millis(1), None);
+        }
+        assert_eq!(stats.cpu_ms.len(), 3 * MAX_WINDOW);
+    }
+}
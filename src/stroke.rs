@@ -0,0 +1,178 @@
+// Polyline stroke tessellation.
+//
+// Vertices are emitted as [x, y, nx, ny]: a point on the path plus an extrusion vector
+// for a unit half-width. The vertex shader computes `(x, y) + n * half_width`, so the
+// same geometry can be reused when the stroke width changes (e.g. pixel widths on resize).
+
+// Joins sharper than this fall back to a bevel instead of a long miter spike
+const MITER_LIMIT: f32 = 4.0;
+
+pub type StrokeVertex = [f32; 4];
+
+/// Generates TriangleList vertices for a stroke along `points`.
+/// Closed paths join the last point back to the first; open paths get butt caps.
+pub fn stroke_path(points: &[[f32; 2]], closed: bool) -> Vec<StrokeVertex> {
+    let points = dedup(points, closed);
+    let n = points.len();
+    if n < 2 {
+        return Vec::new();
+    }
+    let seg_count = if closed { n } else { n - 1 };
+    let normals: Vec<[f32; 2]> = (0..seg_count)
+        .map(|i| normal(points[i], points[(i + 1) % n]))
+        .collect();
+
+    // Offset used at the start and end of every segment; equal to the segment normal
+    // unless a miter join shares an offset between two neighbours.
+    let mut start_off = normals.clone();
+    let mut end_off = normals.clone();
+    let mut v = Vec::with_capacity(seg_count * 9);
+
+    for (i, &p) in points.iter().enumerate() {
+        let (prev, next) = match (closed, i) {
+            (false, 0) => continue,
+            (false, i) if i == n - 1 => continue,
+            (_, 0) => (seg_count - 1, 0),
+            (_, i) => (i - 1, i),
+        };
+        let (n0, n1) = (normals[prev], normals[next]);
+        let sum = [n0[0] + n1[0], n0[1] + n1[1]];
+        let len = (sum[0] * sum[0] + sum[1] * sum[1]).sqrt();
+        // Miter length for a unit half-width is 1 / cos(theta / 2) = 2 / |n0 + n1|
+        if len > 2.0 / MITER_LIMIT {
+            let m = [sum[0] * 2.0 / (len * len), sum[1] * 2.0 / (len * len)];
+            end_off[prev] = m;
+            start_off[next] = m;
+        } else {
+            // Bevel: fill the wedge on the outer side of the turn
+            let turn = n0[0] * n1[1] - n0[1] * n1[0];
+            let side = if turn > 0.0 { -1.0 } else { 1.0 };
+            v.push([p[0], p[1], 0.0, 0.0]);
+            v.push([p[0], p[1], n0[0] * side, n0[1] * side]);
+            v.push([p[0], p[1], n1[0] * side, n1[1] * side]);
+        }
+    }
+
+    for i in 0..seg_count {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        let (sa, eb) = (start_off[i], end_off[i]);
+        let a_l = [a[0], a[1], sa[0], sa[1]];
+        let a_r = [a[0], a[1], -sa[0], -sa[1]];
+        let b_l = [b[0], b[1], eb[0], eb[1]];
+        let b_r = [b[0], b[1], -eb[0], -eb[1]];
+        v.extend_from_slice(&[a_r, b_r, b_l, a_r, b_l, a_l]);
+    }
+    v
+}
+
+// Left-hand unit normal of the segment a -> b
+fn normal(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let len = (dx * dx + dy * dy).sqrt();
+    [-dy / len, dx / len]
+}
+
+// Drops zero-length segments, which have no direction to extrude along
+fn dedup(points: &[[f32; 2]], closed: bool) -> Vec<[f32; 2]> {
+    let mut out: Vec<[f32; 2]> = Vec::with_capacity(points.len());
+    for &p in points {
+        if out.last() != Some(&p) {
+            out.push(p);
+        }
+    }
+    if closed && out.len() > 1 && out.first() == out.last() {
+        out.pop();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+        (a[0] - b[0]).abs() < 1e-5 && (a[1] - b[1]).abs() < 1e-5
+    }
+
+    // Distinct extrusion vectors of the vertices at `p`
+    fn offsets_at(vertices: &[StrokeVertex], p: [f32; 2]) -> Vec<[f32; 2]> {
+        let mut offsets: Vec<[f32; 2]> = Vec::new();
+        for &[x, y, nx, ny] in vertices {
+            if [x, y] == p && !offsets.iter().any(|&o| close(o, [nx, ny])) {
+                offsets.push([nx, ny]);
+            }
+        }
+        offsets
+    }
+
+    #[test]
+    fn right_angles_are_mitered() {
+        let v = stroke_path(&[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]], false);
+        // Two quads and no bevel
+        assert_eq!(v.len(), 12);
+        // Both segments meet at the miter point, sqrt(2) half-widths out
+        let corner = offsets_at(&v, [1.0, 0.0]);
+        assert_eq!(corner.len(), 2);
+        assert!(corner.iter().any(|&o| close(o, [-1.0, 1.0])) && corner.iter().any(|&o| close(o, [1.0, -1.0])));
+        // Butt caps: the ends are extruded straight across the path, not beyond it
+        assert!(offsets_at(&v, [0.0, 0.0]).iter().all(|&o| close(o, [0.0, 1.0]) || close(o, [0.0, -1.0])));
+        assert!(offsets_at(&v, [1.0, 1.0]).iter().all(|&o| close(o, [-1.0, 0.0]) || close(o, [1.0, 0.0])));
+    }
+
+    #[test]
+    fn hairpins_are_beveled() {
+        let v = stroke_path(&[[0.0, 0.0], [1.0, 0.0], [0.0, 0.25]], false);
+        // A bevel triangle before the two quads
+        assert_eq!(v.len(), 3 + 12);
+        let n1 = normal([1.0, 0.0], [0.0, 0.25]);
+        // Centered on the turn, spanning the outer (right-hand) sides of both segments
+        assert_eq!(v[0], [1.0, 0.0, 0.0, 0.0]);
+        assert!(close([v[1][2], v[1][3]], [0.0, -1.0]));
+        assert!(close([v[2][2], v[2][3]], [-n1[0], -n1[1]]));
+        // Neither segment is extended past the turn
+        let corner = offsets_at(&v[3..], [1.0, 0.0]);
+        assert!(corner.iter().all(|&o| (o[0] * o[0] + o[1] * o[1] - 1.0).abs() < 1e-5));
+    }
+
+    #[test]
+    fn miters_never_exceed_the_limit() {
+        for degrees in (5..=175).step_by(5) {
+            let angle = (degrees as f32).to_radians();
+            let v = stroke_path(&[[-1.0, 0.0], [0.0, 0.0], [-angle.cos(), angle.sin()]], false);
+            let miter = v.iter().map(|&[_, _, nx, ny]| (nx * nx + ny * ny).sqrt()).fold(0.0, f32::max);
+            assert!(miter <= MITER_LIMIT, "{degrees}°: {miter}");
+            // Sharp turns get a bevel, others a miter exactly 1 / sin(angle / 2) long
+            let beveled = v.len() == 15;
+            assert_eq!(beveled, 1.0 / (angle / 2.0).sin() >= MITER_LIMIT, "{degrees}°");
+            if !beveled {
+                assert!((miter - 1.0 / (angle / 2.0).sin()).abs() < 1e-4, "{degrees}°");
+            }
+        }
+    }
+
+    #[test]
+    fn closed_paths_join_around() {
+        let square = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+        let v = stroke_path(&square, true);
+        // Four quads, every corner mitered, including the one closing the path
+        assert_eq!(v.len(), 24);
+        for p in square {
+            let corner = offsets_at(&v, p);
+            assert_eq!(corner.len(), 2);
+            assert!(corner.iter().all(|&o| (o[0].abs() - 1.0).abs() < 1e-5 && (o[1].abs() - 1.0).abs() < 1e-5));
+        }
+        // Repeating the first point to close the path changes nothing
+        let repeated = [square[0], square[1], square[2], square[3], square[0]];
+        assert_eq!(stroke_path(&repeated, true), v);
+    }
+
+    #[test]
+    fn repeated_points_are_skipped() {
+        let v = stroke_path(&[[0.0, 0.0], [0.0, 0.0], [1.0, 0.0], [1.0, 0.0]], false);
+        assert_eq!(v, stroke_path(&[[0.0, 0.0], [1.0, 0.0]], false));
+        assert_eq!(v.len(), 6);
+        assert!(v.iter().flatten().all(|c| c.is_finite()));
+        assert!(stroke_path(&[[0.5, 0.5]; 3], false).is_empty());
+        assert!(stroke_path(&[[0.5, 0.5]; 3], true).is_empty());
+    }
+}
@@ -0,0 +1,22 @@
+// Stroke shader — vertices come from `stroke::stroke_path` as a path point plus an
+// extrusion vector for a unit half-width, so the width can change without re-tessellating.
+
+@group(0) @binding(0) var<uniform> u_color: vec4<f32>;  // stroke color
+@group(0) @binding(1) var<uniform> u_aspect: vec4<f32>; // x = aspect ratio (width / height)
+@group(0) @binding(2) var<uniform> u_pos: vec4<f32>;    // x, y = position offset in NDC
+@group(0) @binding(3) var<uniform> u_stroke: vec4<f32>; // x = half-width in NDC Y units
+
+@vertex
+fn vs_main(
+    @location(0) position: vec2<f32>,
+    @location(1) normal: vec2<f32>,
+) -> @builtin(position) vec4<f32> {
+    let local = position + normal * u_stroke.x;
+    let corrected = vec2<f32>(local.x / u_aspect.x + u_pos.x, local.y + u_pos.y);
+    return vec4<f32>(corrected, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return u_color;
+}
@@ -0,0 +1,300 @@
+// Polygon fill tessellation by a sweep over horizontal slabs.
+//
+// The y coordinates of every vertex and every crossing of two edges cut the plane into
+// slabs inside which no edge starts, ends or crosses another, so the edges spanning a slab
+// are ordered by x. Walking a slab left to right while summing edge windings finds its
+// filled spans, each a trapezoid. This handles concave outlines, holes and overlapping or
+// self-intersecting contours alike, under either fill rule.
+
+/// A closed outline; the last point joins back to the first.
+pub type Contour = Vec<[f32; 2]>;
+
+/// Which points a set of contours encloses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FillRule {
+    /// Inside where edges wind around the point, so holes must wind against their outline (as in SVG)
+    #[default]
+    NonZero,
+    /// Inside where a ray from the point crosses an odd number of edges, whatever their direction
+    EvenOdd,
+}
+
+impl FillRule {
+    fn inside(self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+// Slabs and spans thinner than this fraction of the polygon's height are dropped, which
+// keeps every triangle clear of f32 rounding
+const EPSILON: f64 = 1e-6;
+
+struct Edge {
+    /// Lower end
+    lo: [f64; 2],
+    hi: [f64; 2],
+    /// +1 where the contour runs upwards, -1 downwards
+    winding: i32,
+}
+
+impl Edge {
+    fn x_at(&self, y: f64) -> f64 {
+        let t = (y - self.lo[1]) / (self.hi[1] - self.lo[1]);
+        self.lo[0] + t * (self.hi[0] - self.lo[0])
+    }
+}
+
+/// Triangulates the area `contours` enclose under `rule` into a counter-clockwise TriangleList.
+pub fn tessellate(contours: &[Contour], rule: FillRule) -> Vec<[f32; 2]> {
+    let mut edges = Vec::new();
+    for contour in contours {
+        for (i, &a) in contour.iter().enumerate() {
+            let (a, b) = (a.map(f64::from), contour[(i + 1) % contour.len()].map(f64::from));
+            // Horizontal edges bound no slab
+            if a[1] < b[1] {
+                edges.push(Edge { lo: a, hi: b, winding: 1 });
+            } else if a[1] > b[1] {
+                edges.push(Edge { lo: b, hi: a, winding: -1 });
+            }
+        }
+    }
+    edges.sort_by(|a, b| a.lo[1].total_cmp(&b.lo[1]));
+
+    let mut ys: Vec<f64> = edges.iter().flat_map(|e| [e.lo[1], e.hi[1]]).collect();
+    ys.extend(crossings(&edges));
+    ys.sort_by(f64::total_cmp);
+    let (Some(&bottom), Some(&top)) = (ys.first(), ys.last()) else { return Vec::new() };
+    let eps = (top - bottom) * EPSILON;
+    ys.dedup_by(|y, kept| *y - *kept <= eps);
+
+    let mut triangles = Vec::new();
+    let mut active: Vec<&Edge> = Vec::new();
+    let mut next = 0;
+    let mut row = Vec::new();
+    for slab in ys.windows(2) {
+        let (y0, y1) = (slab[0], slab[1]);
+        while next < edges.len() && edges[next].lo[1] <= y0 + eps {
+            active.push(&edges[next]);
+            next += 1;
+        }
+        active.retain(|e| e.hi[1] > y0 + eps);
+
+        // (x at the middle, at the bottom, at the top, winding), left to right
+        let mid = 0.5 * (y0 + y1);
+        row.clear();
+        row.extend(active.iter().map(|e| (e.x_at(mid), e.x_at(y0), e.x_at(y1), e.winding)));
+        row.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut winding = 0;
+        let mut left = None;
+        for &(_, x0, x1, w) in &row {
+            let was_inside = rule.inside(winding);
+            winding += w;
+            match (was_inside, rule.inside(winding)) {
+                (false, true) => left = Some((x0, x1)),
+                (true, false) => {
+                    if let Some(left) = left.take() {
+                        trapezoid(&mut triangles, y0, y1, left, (x0, x1), eps);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    triangles
+}
+
+// Heights at which two edges cross, excluding shared endpoints
+fn crossings(edges: &[Edge]) -> Vec<f64> {
+    let cross = |a: [f64; 2], b: [f64; 2]| a[0] * b[1] - a[1] * b[0];
+    let mut ys = Vec::new();
+    for (i, a) in edges.iter().enumerate() {
+        // Edges are sorted by their lower end, so later ones starting above `a` cannot meet it
+        for b in edges[i + 1..].iter().take_while(|b| b.lo[1] < a.hi[1]) {
+            let r = [a.hi[0] - a.lo[0], a.hi[1] - a.lo[1]];
+            let s = [b.hi[0] - b.lo[0], b.hi[1] - b.lo[1]];
+            let denom = cross(r, s);
+            if denom == 0.0 {
+                continue;
+            }
+            let d = [b.lo[0] - a.lo[0], b.lo[1] - a.lo[1]];
+            let (t, u) = (cross(d, s) / denom, cross(d, r) / denom);
+            if 0.0 < t && t < 1.0 && 0.0 < u && u < 1.0 {
+                ys.push(a.lo[1] + t * r[1]);
+            }
+        }
+    }
+    ys
+}
+
+// The span between two edges across a slab, as up to two counter-clockwise triangles
+fn trapezoid(out: &mut Vec<[f32; 2]>, y0: f64, y1: f64, (l0, l1): (f64, f64), (r0, r1): (f64, f64), eps: f64) {
+    // Crossings found in floating point may leave the edges swapped by a hair at one end
+    let (r0, r1) = (r0.max(l0), r1.max(l1));
+    let point = |x: f64, y: f64| [x as f32, y as f32];
+    let (bl, br, tr, tl) = (point(l0, y0), point(r0, y0), point(r1, y1), point(l1, y1));
+    if r0 - l0 > eps {
+        out.extend_from_slice(&[bl, br, tr]);
+    }
+    if r1 - l1 > eps {
+        out.extend_from_slice(&[bl, tr, tl]);
+    }
+}
+
+/// Parses a contour written as `X,Y X,Y X,Y ...`, with at least three points.
+pub fn parse_points(s: &str) -> Result<Contour, String> {
+    let points = s
+        .split_whitespace()
+        .map(|pair| {
+            let (x, y) = pair.split_once(',').ok_or_else(|| format!("expected X,Y, got '{pair}'"))?;
+            let coord = |v: &str| v.trim().parse::<f32>().map_err(|_| format!("invalid coordinate '{v}'"));
+            Ok([coord(x)?, coord(y)?])
+        })
+        .collect::<Result<Contour, String>>()?;
+    if points.len() < 3 {
+        return Err(format!("a contour needs at least 3 points, got {}", points.len()));
+    }
+    Ok(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::f64::consts::TAU;
+
+    // Signed area of a closed outline, positive when counter-clockwise
+    fn shoelace(contour: &[[f32; 2]]) -> f64 {
+        let n = contour.len();
+        (0..n)
+            .map(|i| {
+                let ([x0, y0], [x1, y1]) = (contour[i], contour[(i + 1) % n]);
+                f64::from(x0) * f64::from(y1) - f64::from(x1) * f64::from(y0)
+            })
+            .sum::<f64>()
+            / 2.0
+    }
+
+    fn triangle_areas(triangles: &[[f32; 2]]) -> Vec<f64> {
+        assert_eq!(triangles.len() % 3, 0);
+        triangles.chunks(3).map(shoelace).collect()
+    }
+
+    fn area(contours: &[Contour], rule: FillRule) -> f64 {
+        let areas = triangle_areas(&tessellate(contours, rule));
+        assert!(areas.iter().all(|&a| a > 0.0), "degenerate or clockwise triangle");
+        areas.iter().sum()
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() <= 1e-4 * b.abs().max(1.0), "{a} != {b}");
+    }
+
+    fn square(half: f32) -> Contour {
+        vec![[-half, -half], [half, -half], [half, half], [-half, half]]
+    }
+
+    // Points at `radii` around the origin, counter-clockwise at evenly spaced angles
+    fn star(radii: &[f64], turn: f64) -> Contour {
+        let n = radii.len() as f64;
+        let at = |i: usize, r: f64| {
+            let a = turn + TAU * i as f64 / n;
+            [(r * a.cos()) as f32, (r * a.sin()) as f32]
+        };
+        radii.iter().enumerate().map(|(i, &r)| at(i, r)).collect()
+    }
+
+    #[test]
+    fn hole_depends_on_winding_under_non_zero() {
+        let mut hole = square(0.5);
+        assert_close(area(&[square(1.0), hole.clone()], FillRule::NonZero), 4.0);
+        assert_close(area(&[square(1.0), hole.clone()], FillRule::EvenOdd), 3.0);
+        hole.reverse();
+        assert_close(area(&[square(1.0), hole.clone()], FillRule::NonZero), 3.0);
+        assert_close(area(&[square(1.0), hole], FillRule::EvenOdd), 3.0);
+    }
+
+    #[test]
+    fn pentagram_centre_is_filled_only_under_non_zero() {
+        let corners = star(&[1.0; 5], TAU / 4.0);
+        let pentagram: Contour = (0..5).map(|i| corners[i * 2 % 5]).collect();
+        // The centre pentagon has circumradius cos(72°) / cos(36°) of the star's
+        let inner = star(&[(TAU / 5.0).cos() / (TAU / 10.0).cos(); 5], -TAU / 4.0);
+        let pentagram = [pentagram];
+        let (non_zero, even_odd) = (area(&pentagram, FillRule::NonZero), area(&pentagram, FillRule::EvenOdd));
+        assert_close(non_zero - even_odd, shoelace(&inner));
+    }
+
+    #[test]
+    fn coincident_contours_cancel_under_even_odd() {
+        let twice = [square(1.0), square(1.0)];
+        assert_close(area(&twice, FillRule::NonZero), 4.0);
+        assert!(tessellate(&twice, FillRule::EvenOdd).is_empty());
+    }
+
+    #[test]
+    fn outlines_without_area_fill_nothing() {
+        let collinear = [vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]]];
+        let there_and_back = [vec![[0.0, 0.0], [1.0, 1.0], [0.0, 0.0], [1.0, 1.0]]];
+        for rule in [FillRule::NonZero, FillRule::EvenOdd] {
+            assert!(tessellate(&collinear, rule).is_empty());
+            assert!(tessellate(&there_and_back, rule).is_empty());
+        }
+    }
+
+    #[test]
+    fn points() {
+        assert_eq!(parse_points("0,0 1,0  0.5,1").unwrap(), vec![[0.0, 0.0], [1.0, 0.0], [0.5, 1.0]]);
+        assert!(parse_points("0,0 1,0").is_err());
+        assert!(parse_points("0,0 1 0,1").is_err());
+        assert!(parse_points("0,0 1,x 0,1").is_err());
+    }
+
+    proptest! {
+        // Star-shaped polygons are simple but arbitrarily concave
+        #[test]
+        fn simple_polygons_keep_their_area(
+            radii in prop::collection::vec(0.05f64..1.0, 3..40),
+            turn in 0.0f64..TAU,
+            clockwise: bool,
+        ) {
+            let mut outline = star(&radii, turn);
+            let expected = shoelace(&outline);
+            if clockwise {
+                outline.reverse();
+            }
+            for rule in [FillRule::NonZero, FillRule::EvenOdd] {
+                assert_close(area(&[outline.clone()], rule), expected);
+            }
+        }
+
+        // Outlines contain the disk of radius 0.5 * cos(60°) that the hole stays inside
+        #[test]
+        fn holes_are_cut_out(
+            radii in prop::collection::vec(0.5f64..1.0, 3..30),
+            hole_radii in prop::collection::vec(0.05f64..0.25, 3..30),
+            turn in 0.0f64..TAU,
+        ) {
+            let outline = star(&radii, turn);
+            let mut hole = star(&hole_radii, -turn);
+            let expected = shoelace(&outline) - shoelace(&hole);
+            hole.reverse();
+            for rule in [FillRule::NonZero, FillRule::EvenOdd] {
+                assert_close(area(&[outline.clone(), hole.clone()], rule), expected);
+            }
+        }
+
+        // Arbitrary point lists cross themselves; `area` checks every triangle has a positive area
+        #[test]
+        fn self_intersecting_polygons(points in prop::collection::vec((-1.0f32..1.0, -1.0f32..1.0), 3..25)) {
+            let outline: Contour = points.into_iter().map(|(x, y)| [x, y]).collect();
+            let outline = [outline];
+            let (non_zero, even_odd) = (area(&outline, FillRule::NonZero), area(&outline, FillRule::EvenOdd));
+            prop_assert!(even_odd <= non_zero + 1e-4);
+            prop_assert!(non_zero <= 4.0 + 1e-4);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, BindGroupLayoutEntry, Device, Queue, TextureView};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Wrap {
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+impl From<Filter> for wgpu::FilterMode {
+    fn from(f: Filter) -> Self {
+        match f {
+            Filter::Nearest => wgpu::FilterMode::Nearest,
+            Filter::Linear => wgpu::FilterMode::Linear,
+        }
+    }
+}
+
+impl From<Wrap> for wgpu::AddressMode {
+    fn from(w: Wrap) -> Self {
+        match w {
+            Wrap::Repeat => wgpu::AddressMode::Repeat,
+            Wrap::Clamp => wgpu::AddressMode::ClampToEdge,
+            Wrap::Mirror => wgpu::AddressMode::MirrorRepeat,
+        }
+    }
+}
+
+/// An image mapped onto a shape through UVs spanning the shape's local bounds.
+pub struct TextureConfig {
+    pub image: image::RgbaImage,
+    pub filter: Filter,
+    pub wrap: Wrap,
+    /// Number of times the image repeats across the shape bounds
+    pub scale: f32,
+    /// Multiply the image by the fill color, or by white without a fill, instead of replacing it
+    pub tint: bool,
+}
+
+/// Loads any supported image file as 8-bit RGBA.
+pub fn load_image(path: &str) -> Result<image::RgbaImage, String> {
+    image::open(path)
+        .map(|img| img.to_rgba8())
+        .map_err(|e| format!("failed to load image '{path}': {e}"))
+}
+
+/// Uploads sRGB-encoded RGBA8 pixels as a sampled 2D texture, if the device takes a
+/// texture that large.
+pub fn upload_rgba(
+    device: &Device,
+    queue: &Queue,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> Result<TextureView, String> {
+    let max = device.limits().max_texture_dimension_2d;
+    if width > max || height > max {
+        return Err(format!("the image is {width}x{height}, but this GPU takes at most {max}x{max}"));
+    }
+    Ok(upload(device, queue, width, height, wgpu::TextureFormat::Rgba8UnormSrgb, pixels))
+}
+
+/// Uploads tightly packed texels of `format` as a sampled 2D texture.
+pub fn upload(
+    device: &Device,
+    queue: &Queue,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    texels: &[u8],
+) -> TextureView {
+    let texture = device.create_texture_with_data(
+        queue,
+        &wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::LayerMajor,
+        texels,
+    );
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+pub fn create_sampler(device: &Device, filter: Filter, wrap: Wrap) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: wrap.into(),
+        address_mode_v: wrap.into(),
+        mag_filter: filter.into(),
+        min_filter: filter.into(),
+        ..Default::default()
+    })
+}
+
+/// A fragment-stage texture at binding 0 and its sampler at binding 1.
+pub const BIND_GROUP: [BindGroupLayoutEntry; 2] = [
+    BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    },
+    BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    },
+];
+
+pub fn bind_group_layout(device: &Device) -> BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor { label: None, entries: &BIND_GROUP })
+}
+
+/// Bind group for `config`, or for a 1x1 white texture when the shape is untextured
+/// so the pipeline layout stays the same either way.
+pub fn create_bind_group(
+    device: &Device,
+    queue: &Queue,
+    layout: &BindGroupLayout,
+    config: Option<&TextureConfig>,
+) -> Result<BindGroup, String> {
+    let (view, sampler) = match config {
+        Some(c) => (
+            upload_rgba(device, queue, c.image.width(), c.image.height(), c.image.as_raw())
+                .map_err(|e| format!("--texture: {e}"))?,
+            create_sampler(device, c.filter, c.wrap),
+        ),
+        None => (
+            upload_rgba(device, queue, 1, 1, &[255; 4])?,
+            create_sampler(device, Filter::Nearest, Wrap::Clamp),
+        ),
+    };
+    Ok(bind_group(device, layout, &view, &sampler))
+}
+
+pub fn bind_group(device: &Device, layout: &BindGroupLayout, view: &TextureView, sampler: &wgpu::Sampler) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(view) },
+            wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(sampler) },
+        ],
+    })
+}